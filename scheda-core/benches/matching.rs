@@ -0,0 +1,59 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use scheda_core::{
+    chrono::{Duration, TimeZone, Utc},
+    CompiledSchedule,
+};
+use scheda_parser::Parser;
+
+const SCHEDULES: [(&str, &str); 3] = [
+    ("single", "when hour 9, minute 30"),
+    (
+        "ranges",
+        "when month 5 to 7, day 19 to 22, hour 18 to 21, weekday tuesday",
+    ),
+    (
+        "many_parts",
+        "when month jan or mar or may or jul, day 1 or 8 or 15 or 22, \
+         hour 0 or 6 or 12 or 18, every 15 minutes, weekday mon to fri",
+    ),
+];
+
+fn matching(c: &mut Criterion) {
+    let mut group = c.benchmark_group("matches");
+
+    // A week's worth of minutes, so that both matches and misses are measured
+    let start = Utc.with_ymd_and_hms(2001, 5, 22, 0, 0, 0).unwrap();
+    let date_times: Vec<_> = (0..7 * 24 * 60)
+        .map(|minutes| start + Duration::minutes(minutes))
+        .collect();
+
+    for (name, schedule) in SCHEDULES {
+        let schedule = Parser::new(schedule).parse_schedule().unwrap();
+        let compiled = CompiledSchedule::from(&schedule);
+
+        group.bench_with_input(BenchmarkId::new("Schedule", name), &date_times, |b, dts| {
+            b.iter(|| {
+                dts.iter()
+                    .filter(|dt| schedule.matches(black_box(**dt)))
+                    .count()
+            })
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("CompiledSchedule", name),
+            &date_times,
+            |b, dts| {
+                b.iter(|| {
+                    dts.iter()
+                        .filter(|dt| compiled.matches(black_box(**dt)))
+                        .count()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, matching);
+criterion_main!(benches);
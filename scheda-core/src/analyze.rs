@@ -0,0 +1,486 @@
+//! Finds what a schedule says that parses fine but is unlikely to be meant,
+//! such as `when month feb, day 30`, which never fires, or the `9` in
+//! `hour 8 to 10 or 9`, which adds nothing.
+
+use std::{borrow::Cow, fmt};
+
+use chrono::{Datelike, Duration, Month, NaiveDate, Weekday};
+use num_traits::FromPrimitive;
+
+use crate::{
+    schedule::contains_year, search::SEARCH_HORIZON_YEARS, AsU8, DateTimePart, Diagnostic, Hour,
+    Minute, MonthDay, MonthDayFromEnd, Report, Schedule, Second, Span, WellFormedRange,
+};
+
+/// The first year of the 400-year Gregorian cycle years are checked within
+const CYCLE_START: i32 = 2000;
+
+/// One of the specs of a [`Schedule`], which [`Warning`]s point into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Spec {
+    Year,
+    Month,
+    Day,
+    MonthEnd,
+    Weekday,
+    NthWeekday,
+    Hour,
+    Minute,
+    Second,
+    Time,
+    Date,
+}
+
+impl Spec {
+    pub const ALL: [Spec; 11] = [
+        Spec::Year,
+        Spec::Month,
+        Spec::Day,
+        Spec::MonthEnd,
+        Spec::Weekday,
+        Spec::NthWeekday,
+        Spec::Hour,
+        Spec::Minute,
+        Spec::Second,
+        Spec::Time,
+        Spec::Date,
+    ];
+}
+
+/// Something a schedule says that's valid, but unlikely to be meant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// The schedule can never fire, e.g. `when month feb, day 30`
+    NeverFires(Cow<'static, str>),
+    /// The schedule only fires in leap years, e.g. `when month feb, day 29`
+    LeapYearsOnly,
+    /// A part matching nothing the rest of its spec doesn't already, e.g. the
+    /// `9` in `hour 8 to 10 or 9`
+    RedundantPart { spec: Spec, index: usize },
+    /// A part matching some of what an earlier one in its spec does, e.g. the
+    /// `2 to 5` in `hour 1 to 3 or 2 to 5`
+    OverlappingParts {
+        spec: Spec,
+        index: usize,
+        overlapped: usize,
+    },
+}
+
+impl Warning {
+    /// A stable, machine-readable identifier for this kind of warning
+    pub fn code(&self) -> &'static str {
+        match self {
+            Warning::NeverFires(_) => "never-fires",
+            Warning::LeapYearsOnly => "leap-years-only",
+            Warning::RedundantPart { .. } => "redundant-part",
+            Warning::OverlappingParts { .. } => "overlapping-parts",
+        }
+    }
+
+    /// Pins this warning to the given span of the input
+    pub fn at(self, span: Span) -> Diagnostic<Warning> {
+        Diagnostic { report: self, span }
+    }
+}
+
+impl Report for Warning {
+    const LABEL: &'static str = "warning";
+
+    fn code(&self) -> &'static str {
+        Warning::code(self)
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::NeverFires(reason) => write!(f, "schedule never fires, since {reason}"),
+            Warning::LeapYearsOnly => write!(f, "schedule only fires in leap years"),
+            Warning::RedundantPart { .. } => {
+                write!(f, "value already matched by the rest of its clause")
+            }
+            Warning::OverlappingParts { .. } => {
+                write!(f, "value overlaps with an earlier one in its clause")
+            }
+        }
+    }
+}
+
+/// Flags the parts of `spec` matching only what the others already do, and
+/// those overlapping an earlier one, where `from_u8` turns an [`AsU8`] value
+/// from `T::FIRST` to `last` back into a `T`
+fn part_warnings<T: AsU8 + WellFormedRange>(
+    spec: Spec,
+    parts: &[DateTimePart<T>],
+    last: u8,
+    from_u8: impl Fn(u8) -> T,
+    warnings: &mut Vec<Warning>,
+) {
+    // Values go up to 60, so each part's fit in a single bit set
+    let masks: Vec<u64> = parts
+        .iter()
+        .map(|part| {
+            (T::FIRST.as_u8()..=last)
+                .filter(|&value| part.contains(&from_u8(value)))
+                .fold(0, |mask, value| mask | 1 << value)
+        })
+        .collect();
+
+    // Checking from the last part makes the later of two duplicates the
+    // redundant one
+    let mut redundant = vec![false; parts.len()];
+    for index in (0..parts.len()).rev() {
+        let others = (0..parts.len())
+            .filter(|&other| other != index && !redundant[other])
+            .fold(0, |others, other| others | masks[other]);
+
+        redundant[index] = masks[index] & !others == 0;
+    }
+
+    for index in 0..parts.len() {
+        if redundant[index] {
+            warnings.push(Warning::RedundantPart { spec, index });
+            continue;
+        }
+
+        let overlapped =
+            (0..index).find(|&other| !redundant[other] && masks[other] & masks[index] != 0);
+        if let Some(overlapped) = overlapped {
+            warnings.push(Warning::OverlappingParts {
+                spec,
+                index,
+                overlapped,
+            });
+        }
+    }
+}
+
+/// Flags every item equal to an earlier one, or otherwise `covered`
+fn duplicate_warnings<T: PartialEq>(
+    spec: Spec,
+    items: &[T],
+    covered: impl Fn(&T) -> bool,
+    warnings: &mut Vec<Warning>,
+) {
+    for (index, item) in items.iter().enumerate() {
+        if items[..index].contains(item) || covered(item) {
+            warnings.push(Warning::RedundantPart { spec, index });
+        }
+    }
+}
+
+impl Schedule {
+    /// How many parts the given spec holds
+    pub fn spec_len(&self, spec: Spec) -> usize {
+        match spec {
+            Spec::Year => self.year_spec.len(),
+            Spec::Month => self.month_spec.len(),
+            Spec::Day => self.day_spec.len(),
+            Spec::MonthEnd => self.month_end_spec.len(),
+            Spec::Weekday => self.weekday_spec.len(),
+            Spec::NthWeekday => self.nth_weekday_spec.len(),
+            Spec::Hour => self.hour_spec.len(),
+            Spec::Minute => self.minute_spec.len(),
+            Spec::Second => self.second_spec.len(),
+            Spec::Time => self.time_spec.len(),
+            Spec::Date => self.date_spec.len(),
+        }
+    }
+
+    /// Whether this schedule ever fires, at any point in time.
+    ///
+    /// This is decided without searching, over a whole 400-year cycle of the
+    /// Gregorian calendar, so `when month feb, day 29` is satisfiable, unlike
+    /// `when month feb, day 30`. Only exceptions have to be searched past,
+    /// within that cycle from the schedule's first year.
+    pub fn is_satisfiable(&self) -> bool {
+        self.never_fires_because(&self.cycle_years()).is_none()
+    }
+
+    /// Looks for what this schedule says that's valid, but unlikely to be
+    /// meant, i.e. it never firing or only in leap years, and `or` parts
+    /// adding nothing to, or overlapping, the others in their clause.
+    pub fn analyze(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+
+        let years = self.cycle_years();
+        let is_leap =
+            |offset: usize| NaiveDate::from_ymd_opt(CYCLE_START + offset as i32, 2, 29).is_some();
+
+        match self.never_fires_because(&years) {
+            Some(reason) => warnings.push(Warning::NeverFires(reason)),
+            // Naming years or dates is taken as meaning to fire in those alone
+            None if self.year_spec.is_empty()
+                && self.date_spec.is_empty()
+                && (0..years.len()).all(|offset| !years[offset] || is_leap(offset)) =>
+            {
+                warnings.push(Warning::LeapYearsOnly);
+            }
+            None => {}
+        }
+
+        part_warnings(
+            Spec::Month,
+            &self.month_spec,
+            12,
+            |month| Month::from_u8(month).unwrap(),
+            &mut warnings,
+        );
+        part_warnings(
+            Spec::Day,
+            &self.day_spec,
+            31,
+            |day| MonthDay::from_u8(day).unwrap(),
+            &mut warnings,
+        );
+        part_warnings(
+            Spec::MonthEnd,
+            &self.month_end_spec,
+            31,
+            |day| MonthDayFromEnd::from_u8(32 - day).unwrap(),
+            &mut warnings,
+        );
+        // `AsU8` counts weekdays from Sunday, but `FromPrimitive` from Monday
+        part_warnings(
+            Spec::Weekday,
+            &self.weekday_spec,
+            6,
+            |weekday| Weekday::from_u8((weekday + 6) % 7).unwrap(),
+            &mut warnings,
+        );
+        part_warnings(
+            Spec::Hour,
+            &self.hour_spec,
+            23,
+            |hour| Hour::new(hour).unwrap(),
+            &mut warnings,
+        );
+        part_warnings(
+            Spec::Minute,
+            &self.minute_spec,
+            59,
+            |minute| Minute::new(minute).unwrap(),
+            &mut warnings,
+        );
+        part_warnings(
+            Spec::Second,
+            &self.second_spec,
+            59,
+            |second| Second::new(second).unwrap(),
+            &mut warnings,
+        );
+
+        // Years span too many values to be compared like the other parts, so
+        // only repeated ones are found
+        duplicate_warnings(Spec::Year, &self.year_spec, |_| false, &mut warnings);
+        let weekdays = &self.weekday_spec;
+        duplicate_warnings(
+            Spec::NthWeekday,
+            &self.nth_weekday_spec,
+            |nth_weekday| {
+                weekdays
+                    .iter()
+                    .any(|part| part.contains(&nth_weekday.weekday))
+            },
+            &mut warnings,
+        );
+        duplicate_warnings(Spec::Time, &self.time_spec, |_| false, &mut warnings);
+        // A date on its own already matches at every time of that date
+        let dates = &self.date_spec;
+        duplicate_warnings(
+            Spec::Date,
+            dates,
+            |on| {
+                on.time.is_some()
+                    && dates
+                        .iter()
+                        .any(|other| other.date == on.date && other.time.is_none())
+            },
+            &mut warnings,
+        );
+
+        warnings
+    }
+
+    /// Whether `date` matches the month, day and weekday specs, leaving out
+    /// years and one-off dates
+    fn matches_recurring_date(&self, date: NaiveDate, either_day: bool) -> bool {
+        self.contains_month(Month::from_u32(date.month()).unwrap())
+            && self.matches_day(date, either_day)
+    }
+
+    /// Which years of the 400-year cycle starting at [`CYCLE_START`] have a
+    /// date matching the month, day and weekday specs.
+    ///
+    /// The calendar repeats itself after that, so any year falls on the one at
+    /// its offset within the cycle.
+    fn cycle_years(&self) -> Vec<bool> {
+        let either_day = self.either_day();
+
+        (CYCLE_START..CYCLE_START + 400)
+            .map(|year| {
+                NaiveDate::from_ymd_opt(year, 1, 1)
+                    .unwrap()
+                    .iter_days()
+                    .take_while(|date| date.year() == year)
+                    .any(|date| self.matches_recurring_date(date, either_day))
+            })
+            .collect()
+    }
+
+    /// Why this schedule never fires, if it doesn't, given which `years` of
+    /// the cycle have a date matching its month, day and weekday specs
+    fn never_fires_because(&self, years: &[bool]) -> Option<Cow<'static, str>> {
+        if !years.contains(&true) {
+            return Some("its month, day and weekday clauses never fall on the same date".into());
+        }
+
+        let matches_time = |hour: Hour, minute: Minute| {
+            self.contains_hour(hour)
+                && self.contains_minute(minute)
+                && self.contains_time(&hour, &minute)
+        };
+        let any_time = (0..24).any(|hour| {
+            (0..60)
+                .any(|minute| matches_time(Hour::new(hour).unwrap(), Minute::new(minute).unwrap()))
+        });
+        if !any_time {
+            return Some("its hour, minute and `at` clauses never match the same time".into());
+        }
+
+        let in_cycle = |year: i32| years[(year - CYCLE_START).rem_euclid(400) as usize];
+        let any_year = (1..=9999).any(|year| {
+            contains_year(
+                &self.year_spec,
+                NaiveDate::from_ymd_opt(year, 1, 1).unwrap(),
+            ) && in_cycle(year)
+        });
+        if !self.year_spec.is_empty() && !any_year {
+            return Some("none of its years have a date matching its other clauses".into());
+        }
+
+        let any_date = self.date_spec.iter().any(|on| {
+            contains_year(&self.year_spec, on.date)
+                && self.matches_recurring_date(on.date, self.either_day())
+                && on
+                    .time
+                    .is_none_or(|time| matches_time(time.hour, time.minute))
+        });
+        if !self.date_spec.is_empty() && !any_date {
+            return Some("none of its `on` dates match its other clauses".into());
+        }
+
+        // Everything up to here leaves some time to fire at, unless
+        // exceptions rule it out
+        if !self.exceptions.is_empty() {
+            let first_year = self.first_year().unwrap_or(CYCLE_START);
+            let until_year = self
+                .last_year()
+                .map_or(first_year + SEARCH_HORIZON_YEARS, |last| {
+                    last.min(first_year + SEARCH_HORIZON_YEARS)
+                });
+            let from = NaiveDate::from_ymd_opt(first_year, 1, 1)?.and_hms_opt(0, 0, 0)?
+                - Duration::seconds(1);
+
+            // Normalizing first lets exceptions such as `hour 0 to 23` rule out
+            // whole dates at once, rather than hour by hour
+            let mut normalized = self.clone();
+            normalized.normalize();
+
+            if normalized.next_naive_after(from, until_year).is_none() {
+                return Some("its exceptions rule out every time it would fire".into());
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scheda_parser::Parser;
+
+    fn codes(schedule: &str) -> Vec<&'static str> {
+        Parser::new(schedule)
+            .parse_schedule()
+            .unwrap()
+            .analyze()
+            .iter()
+            .map(|warning| warning.code())
+            .collect()
+    }
+
+    fn is_satisfiable(schedule: &str) -> bool {
+        Parser::new(schedule)
+            .parse_schedule()
+            .unwrap()
+            .is_satisfiable()
+    }
+
+    #[test]
+    fn finds_schedules_that_never_fire() {
+        for schedule in [
+            "when month feb, day 30",
+            "when day 31, month apr or jun",
+            "when day 31, weekday mon, month feb",
+            "when weekday fifth mon, month feb, day 1 to 7",
+            "when hour 9, at 10:00",
+            "when year 2025 to 2027, month feb, day 29",
+            "when on 2026-12-24, month jan",
+            "when on 2026-12-24 9:00, hour 10",
+            "when minute 0, except hour 0 to 23",
+            "when on 2026-12-24, except (when on 2026-12-24)",
+        ] {
+            assert!(!is_satisfiable(schedule), "{schedule}");
+            assert_eq!(codes(schedule), ["never-fires"], "{schedule}");
+        }
+
+        for schedule in [
+            "when",
+            "when day 31",
+            "when day 30, month feb, combine or, weekday mon",
+            "when year 2024 to 2027, month feb, day 29",
+            "when day 13, weekday fri, month feb",
+            "when on 2026-12-24 9:00, hour 9",
+            "when minute 0, except hour 0 to 22",
+        ] {
+            assert!(is_satisfiable(schedule), "{schedule}");
+        }
+    }
+
+    #[test]
+    fn finds_schedules_firing_in_leap_years_only() {
+        assert_eq!(codes("when month feb, day 29"), ["leap-years-only"]);
+        assert_eq!(codes("when month feb, day -29"), ["leap-years-only"]);
+        assert!(codes("when month feb, day 28 or 29").is_empty());
+        assert!(codes("when year every 4 from 2024, month feb, day 29").is_empty());
+    }
+
+    #[test]
+    fn finds_redundant_and_overlapping_parts() {
+        assert_eq!(codes("when hour 9 or 9"), ["redundant-part"]);
+        assert_eq!(codes("when hour 8 to 10 or 9"), ["redundant-part"]);
+        assert_eq!(codes("when minute every 15 or 30"), ["redundant-part"]);
+        assert_eq!(codes("when hour 1 to 3 or 2 to 5"), ["overlapping-parts"]);
+        assert_eq!(codes("when weekday fri to mon or sun"), ["redundant-part"]);
+        assert_eq!(
+            codes("when weekday fri or last fri, at 9:00 or 9:00"),
+            ["redundant-part", "redundant-part"]
+        );
+        assert_eq!(
+            codes("when on 2026-12-24 or 2026-12-24 9:00"),
+            ["redundant-part"]
+        );
+        assert_eq!(codes("when year 2027 or 2026 or 2027"), ["redundant-part"]);
+        assert!(codes("when hour 1 to 3 or 4 to 5, minute every 15 from 5 or 0").is_empty());
+
+        let schedule = Parser::new("when hour 9 or 8 to 10 or 10 to 12")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(
+            format!("{:?}", schedule.analyze()),
+            "[RedundantPart { spec: Hour, index: 0 }, \
+             OverlappingParts { spec: Hour, index: 2, overlapped: 1 }]"
+        );
+    }
+}
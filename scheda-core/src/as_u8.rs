@@ -1,6 +1,6 @@
 use chrono::{Month, Weekday};
 
-use crate::{Hour, Minute, MonthDay};
+use crate::{Hour, Minute, MonthDay, MonthDayFromEnd, Second};
 
 pub trait AsU8: Sized {
     fn as_u8(&self) -> u8;
@@ -18,6 +18,12 @@ impl AsU8 for Minute {
     }
 }
 
+impl AsU8 for Second {
+    fn as_u8(&self) -> u8 {
+        self.get()
+    }
+}
+
 impl AsU8 for Month {
     fn as_u8(&self) -> u8 {
         self.number_from_month() as u8
@@ -30,6 +36,14 @@ impl AsU8 for MonthDay {
     }
 }
 
+impl AsU8 for MonthDayFromEnd {
+    /// Where the day would fall in a 31-day month, so that `day -5 to -1`
+    /// is an ascending range like any other
+    fn as_u8(&self) -> u8 {
+        32 - self.get()
+    }
+}
+
 impl AsU8 for Weekday {
     fn as_u8(&self) -> u8 {
         self.num_days_from_sunday() as u8
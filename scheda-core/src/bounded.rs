@@ -0,0 +1,60 @@
+use std::{fmt, num::NonZeroU8};
+
+/// An integer from `MIN` to `MAX`, inclusive.
+///
+/// It's stored as its offset from `MIN`, plus one, so that an `Option` of it
+/// takes no more room than the integer itself.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BoundedU8<const MIN: u8, const MAX: u8>(NonZeroU8);
+
+impl<const MIN: u8, const MAX: u8> BoundedU8<MIN, MAX> {
+    pub const MIN: Self = Self(NonZeroU8::MIN);
+    pub const MAX: Self = match Self::new(MAX) {
+        Some(max) => max,
+        None => panic!("`MAX` can't be smaller than `MIN`"),
+    };
+
+    pub const fn new(val: u8) -> Option<Self> {
+        if val < MIN || val > MAX {
+            return None;
+        }
+
+        match NonZeroU8::new(val - MIN + 1) {
+            Some(offset) => Some(Self(offset)),
+            None => None,
+        }
+    }
+
+    pub const fn get(self) -> u8 {
+        // Cannot overflow, since the offset is at most `MAX - MIN + 1`
+        self.0.get() - 1 + MIN
+    }
+}
+
+impl<const MIN: u8, const MAX: u8> fmt::Debug for BoundedU8<MIN, MAX> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+
+    use super::BoundedU8;
+
+    #[test]
+    fn only_holds_values_within_bounds() {
+        type Day = BoundedU8<1, 31>;
+
+        assert_eq!(Day::new(0), None);
+        assert_eq!(Day::new(1), Some(Day::MIN));
+        assert_eq!(Day::new(31), Some(Day::MAX));
+        assert_eq!(Day::new(32), None);
+        assert_eq!(Day::new(15).unwrap().get(), 15);
+        assert!(Day::new(3) < Day::new(12));
+
+        assert_eq!(BoundedU8::<0, 254>::MAX.get(), 254);
+        assert_eq!(size_of::<Option<Day>>(), 1);
+    }
+}
@@ -0,0 +1,524 @@
+use std::fmt::Debug;
+
+use chrono::{
+    DateTime, Datelike, Duration, Month, NaiveDate, NaiveDateTime, TimeZone, Timelike, Weekday,
+};
+use chrono_tz::Tz;
+use num_traits::FromPrimitive;
+use smallvec::SmallVec;
+
+use crate::{
+    dst::{self, DstPolicy, GapPolicy, OverlapPolicy},
+    schedule::{contains_date_time, contains_element, contains_year},
+    AbsoluteDate, AsU8, DateTimePart, Hour, Minute, MonthDay, MonthDayFromEnd, Ordinal, Schedule,
+    Second, Year,
+};
+
+/// A [`Schedule`] flattened into one bitmask per date-time part.
+///
+/// Bit `n` of a mask is set when the value `n` matches, so checking a date-time
+/// takes a handful of shifts and ANDs instead of walking every spec.
+///
+/// ```
+/// # use scheda_core::{chrono::{TimeZone, Utc}, CompiledSchedule, Schedule};
+/// let compiled = CompiledSchedule::from(&Schedule::new());
+///
+/// assert!(compiled.matches(Utc.with_ymd_and_hms(2001, 5, 22, 20, 0, 0).unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CompiledSchedule {
+    /// One mask of minutes per hour, so that hours, minutes and exact times of
+    /// day are all checked at once
+    times: [u64; 24],
+    /// Bits 0 to 59, one per second, only bit 0 being set if there's no
+    /// `second` clause
+    seconds: u64,
+    /// Bits 1 to 31, one per day of the month
+    days: u32,
+    /// Bits 1 to 31, one per day of a 31-day month, counting back from bit 31
+    /// for its last day
+    days_from_end: u32,
+    /// Bits 1 to 12, one per month
+    months: u16,
+    /// Bits 0 to 6, starting from Sunday
+    weekdays: u8,
+    /// Six bits per weekday starting from Sunday, one per [`Ordinal`]
+    nth_weekdays: u64,
+    /// Whether matching either the days or the weekdays is enough, as cron
+    /// does when both are restricted
+    either_day: bool,
+    /// Years and one-off dates have too many values to fit in a mask, but
+    /// rarely more than a couple of parts, so they're kept as they are
+    years: SmallVec<[DateTimePart<Year>; 2]>,
+    dates: SmallVec<[AbsoluteDate; 2]>,
+    /// Compiled in turn, with every second set for those without a `second` clause
+    exceptions: Vec<CompiledSchedule>,
+    timezone: Option<Tz>,
+    dst_policy: DstPolicy,
+}
+
+/// Sets the bit of every value in `values` that `parts` contains
+fn mask_of<T: AsU8 + Debug>(parts: &[DateTimePart<T>], values: impl Iterator<Item = T>) -> u64 {
+    values
+        .filter(|value| contains_element(parts, value))
+        .fold(0, |mask, value| mask | (1 << value.as_u8()))
+}
+
+/// Like [`mask_of`], but an empty `parts` matches nothing instead of everything
+fn mask_of_non_empty<T: AsU8 + Debug>(
+    parts: &[DateTimePart<T>],
+    values: impl Iterator<Item = T>,
+) -> u64 {
+    if parts.is_empty() {
+        return 0;
+    }
+
+    mask_of(parts, values)
+}
+
+fn nth_weekday_bit(weekday: Weekday, ordinal: Ordinal) -> u32 {
+    weekday.num_days_from_sunday() * 6 + ordinal as u32
+}
+
+impl From<&Schedule> for CompiledSchedule {
+    fn from(schedule: &Schedule) -> Self {
+        let minutes = mask_of(&schedule.minute_spec, (0..60).filter_map(Minute::new));
+        let times = std::array::from_fn(|hour| {
+            let hour = Hour::new(hour as u8).unwrap();
+            if !contains_element(&schedule.hour_spec, &hour) {
+                return 0;
+            }
+
+            (0..60)
+                .filter(|&minute| {
+                    minutes >> minute & 1 == 1
+                        && schedule.contains_time(&hour, &Minute::new(minute).unwrap())
+                })
+                .fold(0, |mask, minute| mask | (1 << minute))
+        });
+        let seconds = (0..60)
+            .filter(|&second| schedule.contains_second(Second::new(second).unwrap()))
+            .fold(0, |mask, second| mask | (1 << second));
+        // Days counted from either end of the month add up, so an empty spec
+        // only stands for every day when the other one is empty too
+        let (days, days_from_end) = if schedule.month_end_spec.is_empty() {
+            (
+                mask_of(&schedule.day_spec, (1..=31).filter_map(MonthDay::from_u8)),
+                0,
+            )
+        } else {
+            (
+                mask_of_non_empty(&schedule.day_spec, (1..=31).filter_map(MonthDay::from_u8)),
+                mask_of(
+                    &schedule.month_end_spec,
+                    (1..=31).filter_map(MonthDayFromEnd::from_u8),
+                ),
+            )
+        };
+        let months = mask_of(&schedule.month_spec, (1..=12).filter_map(Month::from_u8));
+        // Plain and nth weekdays add up, just like days of the month
+        let weekdays = if schedule.nth_weekday_spec.is_empty() {
+            mask_of
+        } else {
+            mask_of_non_empty
+        };
+        let weekdays = weekdays(
+            &schedule.weekday_spec,
+            [
+                Weekday::Sun,
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+            ]
+            .into_iter(),
+        );
+
+        Self {
+            times,
+            seconds,
+            days: days as u32,
+            days_from_end: days_from_end as u32,
+            months: months as u16,
+            weekdays: weekdays as u8,
+            either_day: schedule.either_day(),
+            nth_weekdays: schedule
+                .nth_weekday_spec
+                .iter()
+                .fold(0, |mask, nth_weekday| {
+                    mask | 1 << nth_weekday_bit(nth_weekday.weekday, nth_weekday.ordinal)
+                }),
+            years: schedule.year_spec.clone(),
+            dates: schedule.date_spec.clone(),
+            exceptions: schedule
+                .exceptions
+                .iter()
+                .map(|exception| {
+                    let mut compiled = Self::from(exception);
+                    if exception.second_spec.is_empty() {
+                        compiled.seconds = (1 << 60) - 1;
+                    }
+
+                    compiled
+                })
+                .collect(),
+            timezone: schedule.timezone,
+            dst_policy: schedule.dst_policy,
+        }
+    }
+}
+
+impl CompiledSchedule {
+    pub fn matches<TZ: TimeZone>(&self, date_time: DateTime<TZ>) -> bool {
+        // Converting out of line keeps the mask lookups below tight
+        if let Some(timezone) = self.timezone {
+            return self.matches_in(timezone, date_time.naive_utc());
+        }
+
+        self.matches_local(&date_time)
+    }
+
+    #[inline(never)]
+    fn matches_in(&self, timezone: Tz, utc: NaiveDateTime) -> bool {
+        self.matches_local(&timezone.from_utc_datetime(&utc))
+    }
+
+    /// Matches `date_time` in the time zone it's in, following the [`DstPolicy`]
+    fn matches_local<TZ: TimeZone>(&self, date_time: &DateTime<TZ>) -> bool {
+        // Converting to local time isn't free, so it's done only once
+        let local = date_time.naive_local();
+
+        if self.matches_naive(local) {
+            return self.dst_policy.overlap == OverlapPolicy::Twice || !dst::is_repeat(date_time);
+        }
+
+        // Local times the clocks just skipped over fire right after instead
+        self.dst_policy.gap == GapPolicy::NextValid
+            && local.second() == 0
+            && self.matches_skipped(date_time)
+    }
+
+    /// Whether any local time skipped right before `date_time` matches
+    fn matches_skipped<TZ: TimeZone>(&self, date_time: &DateTime<TZ>) -> bool {
+        dst::skipped_minutes_before(date_time).is_some_and(|(first, last)| {
+            std::iter::successors(Some(first), |minute| Some(*minute + Duration::minutes(1)))
+                .take_while(|minute| *minute <= last)
+                .any(|minute| {
+                    self.matches_minute(minute)
+                        && (0..60)
+                            .any(|second| self.matches_naive(minute + Duration::seconds(second)))
+                })
+        })
+    }
+
+    #[inline]
+    fn matches_naive(&self, local: NaiveDateTime) -> bool {
+        self.seconds >> local.second() & 1 == 1
+            && self.matches_minute(local)
+            && !self
+                .exceptions
+                .iter()
+                .any(|exception| exception.matches_naive(local))
+    }
+
+    /// Whether the minute `local` is in matches, whatever its second
+    #[inline]
+    fn matches_minute(&self, local: NaiveDateTime) -> bool {
+        let (date, time) = (local.date(), local.time());
+
+        let day = u64::from(self.days >> date.day());
+        let weekday = u64::from(self.weekdays >> date.weekday().num_days_from_sunday());
+        let day = self.combine_days(day, weekday);
+
+        let matches = u64::from(self.months >> date.month())
+            & (self.times[time.hour() as usize] >> time.minute())
+            & (day | u64::from(self.has_day_parts()));
+
+        // Days counted from the end of the month, nth weekdays, years and
+        // dates take working out, so they're only checked once the masks
+        // can't rule `local` out
+        matches & 1 == 1 && self.matches_rest(date, time.hour(), time.minute())
+    }
+
+    /// ANDs or ORs the lowest bits of `day` and `weekday`, without branching on
+    /// the mode worked out when compiling
+    fn combine_days(&self, day: u64, weekday: u64) -> u64 {
+        day & weekday | (day | weekday) & u64::from(self.either_day)
+    }
+
+    /// Whether days and weekdays may match in ways the plain masks can't tell
+    fn has_day_parts(&self) -> bool {
+        self.days_from_end != 0 || self.nth_weekdays != 0
+    }
+
+    /// Checks what's left after [`Self::matches_minute`]'s mask lookups
+    #[cold]
+    #[inline(never)]
+    fn matches_rest(&self, date: NaiveDate, hour: u32, minute: u32) -> bool {
+        let from_end = MonthDayFromEnd::of(date);
+        let (weekday, nth) = (date.weekday(), Ordinal::of(date));
+        let is_last = u64::from(from_end.get() <= 7);
+
+        let day = u64::from((self.days >> date.day()) | (self.days_from_end >> from_end.as_u8()));
+        let weekday = u64::from(self.weekdays >> weekday.num_days_from_sunday())
+            | (self.nth_weekdays >> nth_weekday_bit(weekday, nth))
+            | ((self.nth_weekdays >> nth_weekday_bit(weekday, Ordinal::Last)) & is_last);
+        let day = self.combine_days(day, weekday);
+
+        day & 1 == 1
+            && contains_year(&self.years, date)
+            && contains_date_time(
+                &self.dates,
+                date,
+                &Hour::new(hour as u8).unwrap(),
+                &Minute::new(minute as u8).unwrap(),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use chrono::{Duration, Month, NaiveDate, TimeZone, Utc, Weekday};
+    use chrono_tz::Tz;
+    use smallvec::smallvec;
+
+    use super::CompiledSchedule;
+    use crate::{
+        AbsoluteDate, DateTimePart, DayCombination, DstPolicy, Hour, Minute, MonthDay,
+        MonthDayFromEnd, NthWeekday, Ordinal, OverlapPolicy, Schedule, Second, TimeOfDay, Year,
+    };
+
+    #[test]
+    fn agrees_with_schedule() {
+        let weekdays = Schedule {
+            weekday_spec: smallvec![DateTimePart::Range {
+                starting: Weekday::Mon,
+                ending: Weekday::Wed
+            }],
+            ..Schedule::new()
+        };
+
+        let ranges = Schedule {
+            month_spec: smallvec![DateTimePart::Range {
+                starting: Month::May,
+                ending: Month::July
+            }],
+            day_spec: smallvec![DateTimePart::Range {
+                starting: MonthDay::from_u8(19).unwrap(),
+                ending: MonthDay::from_u8(22).unwrap()
+            }],
+            hour_spec: smallvec![DateTimePart::Range {
+                starting: Hour::new(18).unwrap(),
+                ending: Hour::new(21).unwrap()
+            }],
+            weekday_spec: smallvec![DateTimePart::Single(Weekday::Tue)],
+            ..Schedule::new()
+        };
+
+        let singles = Schedule {
+            month_spec: smallvec![
+                DateTimePart::Single(Month::January),
+                DateTimePart::Single(Month::December)
+            ],
+            day_spec: smallvec![
+                DateTimePart::Single(MonthDay::from_u8(1).unwrap()),
+                DateTimePart::Single(MonthDay::from_u8(31).unwrap())
+            ],
+            hour_spec: smallvec![DateTimePart::Single(Hour::new(0).unwrap())],
+            minute_spec: smallvec![DateTimePart::Single(Minute::new(0).unwrap())],
+            ..Schedule::new()
+        };
+
+        let steps = Schedule {
+            day_spec: smallvec![DateTimePart::Step {
+                starting: MonthDay::from_u8(1).unwrap(),
+                step: NonZeroU8::new(3).unwrap()
+            }],
+            minute_spec: smallvec![DateTimePart::Step {
+                starting: Minute::new(0).unwrap(),
+                step: NonZeroU8::new(15).unwrap()
+            }],
+            ..Schedule::new()
+        };
+
+        let in_timezone = Schedule {
+            hour_spec: smallvec![DateTimePart::Single(Hour::new(9).unwrap())],
+            weekday_spec: smallvec![DateTimePart::Single(Weekday::Mon)],
+            timezone: Some(Tz::Asia__Kolkata),
+            ..Schedule::new()
+        };
+
+        let times = Schedule {
+            hour_spec: smallvec![DateTimePart::Range {
+                starting: Hour::new(8).unwrap(),
+                ending: Hour::new(18).unwrap()
+            }],
+            time_spec: smallvec![
+                TimeOfDay {
+                    hour: Hour::new(7).unwrap(),
+                    minute: Minute::new(0).unwrap()
+                },
+                TimeOfDay {
+                    hour: Hour::new(14).unwrap(),
+                    minute: Minute::new(35).unwrap()
+                }
+            ],
+            ..Schedule::new()
+        };
+
+        let month_ends = Schedule {
+            day_spec: smallvec![DateTimePart::Single(MonthDay::from_u8(15).unwrap())],
+            month_end_spec: smallvec![
+                DateTimePart::Single(MonthDayFromEnd::from_u8(1).unwrap()),
+                DateTimePart::Range {
+                    starting: MonthDayFromEnd::from_u8(7).unwrap(),
+                    ending: MonthDayFromEnd::from_u8(5).unwrap()
+                }
+            ],
+            hour_spec: smallvec![DateTimePart::Single(Hour::new(23).unwrap())],
+            ..Schedule::new()
+        };
+
+        let nth_weekdays = Schedule {
+            weekday_spec: smallvec![DateTimePart::Single(Weekday::Sat)],
+            nth_weekday_spec: smallvec![
+                NthWeekday {
+                    ordinal: Ordinal::Second,
+                    weekday: Weekday::Tue
+                },
+                NthWeekday {
+                    ordinal: Ordinal::Last,
+                    weekday: Weekday::Fri
+                }
+            ],
+            ..Schedule::new()
+        };
+
+        let either_day = Schedule {
+            day_spec: smallvec![DateTimePart::Single(MonthDay::from_u8(1).unwrap())],
+            weekday_spec: smallvec![DateTimePart::Single(Weekday::Mon)],
+            nth_weekday_spec: smallvec![NthWeekday {
+                ordinal: Ordinal::Last,
+                weekday: Weekday::Fri
+            }],
+            day_combination: DayCombination::Or,
+            ..Schedule::new()
+        };
+
+        let skipped_by_dst = Schedule {
+            hour_spec: smallvec![DateTimePart::Single(Hour::new(2).unwrap())],
+            minute_spec: smallvec![DateTimePart::Single(Minute::new(30).unwrap())],
+            timezone: Some(Tz::America__New_York),
+            ..Schedule::new()
+        };
+
+        let seconds = Schedule {
+            minute_spec: smallvec![DateTimePart::Single(Minute::new(3).unwrap())],
+            second_spec: smallvec![
+                DateTimePart::Single(Second::new(0).unwrap()),
+                DateTimePart::WrappingRange {
+                    starting: Second::new(50).unwrap(),
+                    ending: Second::new(10).unwrap()
+                }
+            ],
+            ..Schedule::new()
+        };
+
+        let one_off = Schedule {
+            year_spec: smallvec![DateTimePart::Step {
+                starting: Year::new(1999).unwrap(),
+                step: NonZeroU8::new(2).unwrap()
+            }],
+            date_spec: smallvec![
+                AbsoluteDate {
+                    date: NaiveDate::from_ymd_opt(2001, 3, 14).unwrap(),
+                    time: None
+                },
+                AbsoluteDate {
+                    date: NaiveDate::from_ymd_opt(2001, 7, 1).unwrap(),
+                    time: Some(TimeOfDay {
+                        hour: Hour::new(12).unwrap(),
+                        minute: Minute::new(6).unwrap()
+                    })
+                }
+            ],
+            ..Schedule::new()
+        };
+
+        let hour = |hour| DateTimePart::Single(Hour::new(hour).unwrap());
+        let with_exceptions = Schedule {
+            minute_spec: smallvec![DateTimePart::Step {
+                starting: Minute::new(0).unwrap(),
+                step: NonZeroU8::new(7).unwrap()
+            }],
+            second_spec: smallvec![DateTimePart::Step {
+                starting: Second::new(0).unwrap(),
+                step: NonZeroU8::new(20).unwrap()
+            }],
+            exceptions: vec![
+                Schedule {
+                    month_spec: smallvec![DateTimePart::Single(Month::December)],
+                    ..Schedule::new()
+                },
+                Schedule {
+                    hour_spec: smallvec![hour(12), hour(13)],
+                    second_spec: smallvec![DateTimePart::Single(Second::new(20).unwrap())],
+                    exceptions: vec![Schedule {
+                        weekday_spec: smallvec![DateTimePart::Single(Weekday::Wed)],
+                        ..Schedule::new()
+                    }],
+                    ..Schedule::new()
+                },
+            ],
+            timezone: Some(Tz::America__New_York),
+            ..Schedule::new()
+        };
+
+        let repeated_by_dst = Schedule {
+            hour_spec: smallvec![DateTimePart::Single(Hour::new(2).unwrap())],
+            timezone: Some(Tz::Europe__Berlin),
+            dst_policy: DstPolicy {
+                overlap: OverlapPolicy::Twice,
+                ..DstPolicy::default()
+            },
+            ..Schedule::new()
+        };
+
+        for schedule in [
+            weekdays,
+            ranges,
+            singles,
+            steps,
+            in_timezone,
+            times,
+            month_ends,
+            nth_weekdays,
+            either_day,
+            skipped_by_dst,
+            seconds,
+            one_off,
+            with_exceptions,
+            repeated_by_dst,
+        ] {
+            let compiled = CompiledSchedule::from(&schedule);
+
+            // Every 7 minutes for a year, so that all fields get to move, and
+            // every second of some of those minutes
+            let mut date_time = Utc.with_ymd_and_hms(2001, 1, 1, 0, 0, 0).unwrap();
+            for idx in 0..80_000 {
+                assert_eq!(compiled.matches(date_time), schedule.matches(date_time));
+                if idx % 100 == 0 {
+                    for second in 1..60 {
+                        let date_time = date_time + Duration::seconds(second);
+                        assert_eq!(compiled.matches(date_time), schedule.matches(date_time));
+                    }
+                }
+                date_time += Duration::minutes(7);
+            }
+        }
+    }
+}
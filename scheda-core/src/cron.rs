@@ -0,0 +1,263 @@
+//! Writes schedules out as crontab expressions, with five fields, or six
+//! starting with seconds for schedules with a `second` clause.
+//!
+//! Only what every cron implementation agrees on is written: numbers, `*`,
+//! ranges, lists and steps. Anything that can't be said that way, such as a
+//! time zone or `day last`, is an [`Error::Unsupported`].
+
+use smallvec::SmallVec;
+
+use crate::{AsU8, DateTimePart, DayCombination, DstPolicy, Error, Hour, Minute, Result, Schedule};
+
+/// Groups ascending `values` into runs of consecutive ones, e.g. `0, 1, 2, 5`
+/// into `(0, 2)` and `(5, 5)`
+pub(crate) fn runs(values: impl IntoIterator<Item = u8>) -> Vec<(u8, u8)> {
+    let mut runs: Vec<(u8, u8)> = Vec::new();
+
+    for value in values {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == value => *end = value,
+            _ => runs.push((value, value)),
+        }
+    }
+
+    runs
+}
+
+/// Writes every value in `values` as a cron list, merging consecutive ones
+/// into ranges, e.g. `0-5,10`
+fn cron_list(values: impl IntoIterator<Item = u8>) -> String {
+    let runs: Vec<_> = runs(values)
+        .into_iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{start}-{end}")
+            }
+        })
+        .collect();
+
+    runs.join(",")
+}
+
+/// Writes a field whose values go from `min` to `max`.
+///
+/// Steps from `min` are written as `*/N` only if `star_steps` is set, since
+/// vixie cron treats a day or weekday field starting with `*` as unrestricted
+/// when deciding whether to OR them.
+fn cron_field<T: AsU8>(parts: &[DateTimePart<T>], min: u8, max: u8, star_steps: bool) -> String {
+    if parts.is_empty() {
+        return "*".into();
+    }
+
+    let parts: Vec<_> = parts
+        .iter()
+        .map(|part| match part {
+            DateTimePart::Single(single) => single.as_u8().to_string(),
+            DateTimePart::Range { starting, ending } => {
+                format!("{}-{}", starting.as_u8(), ending.as_u8())
+            }
+            DateTimePart::WrappingRange { starting, ending } => {
+                format!("{}-{max},{min}-{}", starting.as_u8(), ending.as_u8())
+            }
+            DateTimePart::Step { starting, step } if star_steps && starting.as_u8() == min => {
+                format!("*/{step}")
+            }
+            DateTimePart::Step { starting, step } => {
+                format!("{}-{max}/{step}", starting.as_u8())
+            }
+        })
+        .collect();
+
+    parts.join(",")
+}
+
+/// Whether `parts` is a single step from `min`, the only kind of restricted
+/// field that can be written starting with `*`
+fn is_star_step<T: AsU8>(parts: &[DateTimePart<T>], min: u8) -> bool {
+    matches!(parts, [DateTimePart::Step { starting, .. }] if starting.as_u8() == min)
+}
+
+impl Schedule {
+    /// Writes this schedule as a five-field crontab expression, e.g. `30 9 * * 1-5`,
+    /// or as a six-field one if it has a `second` clause, e.g. `*/10 * * * * *`.
+    ///
+    /// Fails with [`Error::Unsupported`] if cron has no way of saying the same
+    /// thing, e.g. for schedules with a time zone or nth weekdays.
+    pub fn to_cron(&self) -> Result<String> {
+        let unsupported = |reason: &'static str| Err(Error::Unsupported(reason.into()));
+
+        if self.timezone.is_some() {
+            return unsupported("cron expressions have no time zone");
+        }
+        if self.dst_policy != DstPolicy::default() {
+            return unsupported("cron has no way of changing how DST transitions are handled");
+        }
+        if !self.month_end_spec.is_empty() {
+            return unsupported("cron has no days counted from the end of the month");
+        }
+        if !self.nth_weekday_spec.is_empty() {
+            return unsupported("cron has no nth weekdays");
+        }
+        if !self.year_spec.is_empty() || !self.date_spec.is_empty() {
+            return unsupported("cron has no years or one-off dates");
+        }
+        if !self.exceptions.is_empty() {
+            return unsupported("cron has no exceptions");
+        }
+
+        let (minutes, hours) = if self.time_spec.is_empty() {
+            (
+                cron_field(&self.minute_spec, 0, 59, true),
+                cron_field(&self.hour_spec, 0, 23, true),
+            )
+        } else {
+            let (hours, minutes) = self.times_as_grid().ok_or_else(|| {
+                Error::Unsupported(
+                    "cron can only fire on the same minutes of every hour it fires on".into(),
+                )
+            })?;
+
+            (cron_list(minutes), cron_list(hours))
+        };
+
+        let either_day = self.day_combination == DayCombination::Or;
+        let both_days = self.restricts_days() && self.restricts_weekdays();
+
+        // Cron ORs days and weekdays when both are restricted, which only one
+        // of them starting with `*` gets around
+        let (star_days, star_weekdays) = match (both_days, either_day) {
+            (false, _) => (true, true),
+            (true, true) => (false, false),
+            (true, false) if is_star_step(&self.day_spec, 1) => (true, false),
+            (true, false) if is_star_step(&self.weekday_spec, 0) => (false, true),
+            (true, false) => {
+                return unsupported(
+                    "cron fires when either the day or the weekday matches, not both",
+                )
+            }
+        };
+
+        // Six-field crons start with seconds, but most only have the other five
+        let seconds = if self.second_spec.is_empty() {
+            String::new()
+        } else {
+            cron_field(&self.second_spec, 0, 59, true) + " "
+        };
+
+        Ok(format!(
+            "{seconds}{minutes} {hours} {} {} {}",
+            cron_field(&self.day_spec, 1, 31, star_days),
+            cron_field(&self.month_spec, 1, 12, true),
+            cron_field(&self.weekday_spec, 0, 6, star_weekdays),
+        ))
+    }
+
+    /// The hours and minutes a schedule with exact times of day fires on, if
+    /// it fires on the same minutes of every one of those hours.
+    ///
+    /// Formats without times of day can only say that much.
+    pub(crate) fn times_as_grid(&self) -> Option<(SmallVec<[u8; 24]>, Vec<u8>)> {
+        let mut hours = SmallVec::<[u8; 24]>::new();
+        let mut minutes = None;
+
+        for hour in 0..24 {
+            let fires_on = (0..60)
+                .filter(|&minute| {
+                    let (hour, minute) = (Hour::new(hour).unwrap(), Minute::new(minute).unwrap());
+
+                    self.time_spec.iter().any(|time| time.is(&hour, &minute))
+                        && self.contains_hour(hour)
+                        && self.contains_minute(minute)
+                })
+                .fold(0u64, |mask, minute| mask | 1 << minute);
+
+            if fires_on == 0 {
+                continue;
+            }
+
+            if *minutes.get_or_insert(fires_on) != fires_on {
+                return None;
+            }
+            hours.push(hour);
+        }
+
+        let minutes = minutes?;
+
+        Some((
+            hours,
+            (0..60)
+                .filter(|minute| minutes >> minute & 1 == 1)
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scheda_parser::Parser;
+
+    fn to_cron(schedule: &str) -> Result<String, &'static str> {
+        Parser::new(schedule)
+            .parse_schedule()
+            .unwrap()
+            .to_cron()
+            .map_err(|err| err.code())
+    }
+
+    #[test]
+    fn writes_cron_expressions() {
+        assert_eq!(to_cron("when").unwrap(), "* * * * *");
+        assert_eq!(to_cron("when every 15 minutes").unwrap(), "*/15 * * * *");
+        assert_eq!(
+            to_cron("when hour 9, minute 30, weekday mon to fri").unwrap(),
+            "30 9 * * 1-5"
+        );
+        assert_eq!(
+            to_cron("when hour 22 to 2, minute every 10 from 5, month dec").unwrap(),
+            "5-59/10 22-23,0-2 * 12 *"
+        );
+        assert_eq!(
+            to_cron("when every 10 seconds, hour 9").unwrap(),
+            "*/10 * 9 * * *"
+        );
+        assert_eq!(
+            to_cron("when second 0 or 30, minute 5").unwrap(),
+            "0,30 5 * * * *"
+        );
+        assert_eq!(
+            to_cron("when at 9:15 or 17:15 or 17:45 or 9:45").unwrap(),
+            "15,45 9,17 * * *"
+        );
+    }
+
+    #[test]
+    fn writes_day_combinations() {
+        assert_eq!(
+            to_cron("when day 1, weekday mon, combine or, hour 0, minute 0").unwrap(),
+            "0 0 1 * 1"
+        );
+        assert_eq!(
+            to_cron("when every 2 days, weekday mon, combine or").unwrap(),
+            "0 0 1-31/2 * 1"
+        );
+        assert_eq!(
+            to_cron("when every 2 days, weekday mon").unwrap(),
+            "0 0 */2 * 1"
+        );
+        assert_eq!(to_cron("when day 13, weekday fri"), Err("unsupported"));
+    }
+
+    #[test]
+    fn refuses_what_cron_cant_express() {
+        assert_eq!(to_cron("when hour 9 in Europe/Berlin"), Err("unsupported"));
+        assert_eq!(to_cron("when day last"), Err("unsupported"));
+        assert_eq!(to_cron("when weekday second tue"), Err("unsupported"));
+        assert_eq!(to_cron("when at 9:15 or 10:30"), Err("unsupported"));
+        assert_eq!(to_cron("when hour 9, dst twice"), Err("unsupported"));
+        assert_eq!(to_cron("when year 2027, hour 9"), Err("unsupported"));
+        assert_eq!(to_cron("when on 2026-12-24 23:00"), Err("unsupported"));
+        assert_eq!(to_cron("when hour 9, except month dec"), Err("unsupported"));
+    }
+}
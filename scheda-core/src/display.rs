@@ -0,0 +1,351 @@
+//! Writes schedules back out in the `when` syntax.
+//!
+//! The output is canonical: clauses always come in the same order, and each
+//! value in its shortest spelling, so that parsing it gives back an equal
+//! [`Schedule`].
+
+use std::fmt;
+
+use chrono::{Month, Weekday};
+
+use crate::{
+    AbsoluteDate, AsU8, DateTimePart, DayCombination, DstPolicy, GapPolicy, Hour, Minute, MonthDay,
+    MonthDayFromEnd, NthWeekday, Ordinal, OverlapPolicy, Schedule, ScheduleExpr, Second, TimeOfDay,
+    WellFormedRange, Year,
+};
+
+/// How a single value is written in the `when` syntax
+pub trait DisplayAtom {
+    fn fmt_atom(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+impl DisplayAtom for Hour {
+    fn fmt_atom(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get())
+    }
+}
+
+impl DisplayAtom for Minute {
+    fn fmt_atom(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get())
+    }
+}
+
+impl DisplayAtom for Second {
+    fn fmt_atom(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get())
+    }
+}
+
+impl DisplayAtom for Year {
+    fn fmt_atom(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get())
+    }
+}
+
+impl DisplayAtom for MonthDay {
+    fn fmt_atom(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get())
+    }
+}
+
+impl DisplayAtom for MonthDayFromEnd {
+    fn fmt_atom(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            1 => f.write_str("last"),
+            days => write!(f, "-{days}"),
+        }
+    }
+}
+
+impl DisplayAtom for Month {
+    fn fmt_atom(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name()[..3].to_ascii_lowercase())
+    }
+}
+
+impl DisplayAtom for Weekday {
+    fn fmt_atom(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string().to_ascii_lowercase())
+    }
+}
+
+/// Lets a [`DisplayAtom`] be used with `write!`
+struct Atom<'a, T>(&'a T);
+
+impl<T: DisplayAtom> fmt::Display for Atom<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_atom(f)
+    }
+}
+
+impl<T: DisplayAtom + AsU8 + WellFormedRange> fmt::Display for DateTimePart<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateTimePart::Single(single) => single.fmt_atom(f),
+            DateTimePart::Range { starting, ending }
+            | DateTimePart::WrappingRange { starting, ending } => {
+                write!(f, "{} to {}", Atom(starting), Atom(ending))
+            }
+            DateTimePart::Step { starting, step } if starting.as_u8() == T::FIRST.as_u8() => {
+                write!(f, "every {step}")
+            }
+            DateTimePart::Step { starting, step } => {
+                write!(f, "every {step} from {}", Atom(starting))
+            }
+        }
+    }
+}
+
+impl fmt::Display for DateTimePart<Year> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateTimePart::Single(single) => single.fmt_atom(f),
+            DateTimePart::Range { starting, ending }
+            | DateTimePart::WrappingRange { starting, ending } => {
+                write!(f, "{} to {}", Atom(starting), Atom(ending))
+            }
+            DateTimePart::Step { starting, step } if *starting == Year::FIRST => {
+                write!(f, "every {step}")
+            }
+            DateTimePart::Step { starting, step } => {
+                write!(f, "every {step} from {}", Atom(starting))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Ordinal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Ordinal::First => "first",
+            Ordinal::Second => "second",
+            Ordinal::Third => "third",
+            Ordinal::Fourth => "fourth",
+            Ordinal::Fifth => "fifth",
+            Ordinal::Last => "last",
+        })
+    }
+}
+
+impl fmt::Display for NthWeekday {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.ordinal, Atom(&self.weekday))
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{:02}", self.hour.get(), self.minute.get())
+    }
+}
+
+impl fmt::Display for AbsoluteDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.date.format("%Y-%m-%d"))?;
+
+        match &self.time {
+            Some(time) => write!(f, " {time}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for DayCombination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DayCombination::And => "and",
+            DayCombination::Or => "or",
+        })
+    }
+}
+
+impl fmt::Display for GapPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GapPolicy::Skip => "skip",
+            GapPolicy::NextValid => "shift",
+        })
+    }
+}
+
+impl fmt::Display for OverlapPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OverlapPolicy::Once => "once",
+            OverlapPolicy::Twice => "twice",
+        })
+    }
+}
+
+/// Writes `items` as a single clause, e.g. `month jan or mar to may`.
+///
+/// Nothing is written if there are no items, since that clause would match anything.
+struct Clause<'a> {
+    kind: &'static str,
+    items: Vec<Box<dyn fmt::Display + 'a>>,
+}
+
+impl<'a> Clause<'a> {
+    fn new(kind: &'static str) -> Self {
+        Self {
+            kind,
+            items: Vec::new(),
+        }
+    }
+
+    fn with(mut self, items: impl IntoIterator<Item = &'a (impl fmt::Display + 'a)>) -> Self {
+        self.items.extend(
+            items
+                .into_iter()
+                .map(|item| Box::new(item) as Box<dyn fmt::Display>),
+        );
+        self
+    }
+}
+
+impl fmt::Display for Clause<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.kind)?;
+
+        for (idx, item) in self.items.iter().enumerate() {
+            let separator = if idx == 0 { " " } else { " or " };
+            write!(f, "{separator}{item}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes an exception on its own when it's a single clause, e.g. `month dec`,
+/// and as a whole nested schedule otherwise, e.g. `(when weekday sat, hour 9)`
+struct Exception<'a>(&'a Schedule);
+
+impl fmt::Display for Exception<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.clauses().as_slice() {
+            [clause] if clause.kind != "except" && self.0.timezone.is_none() => {
+                write!(f, "{clause}")
+            }
+            _ => write!(f, "({})", self.0),
+        }
+    }
+}
+
+impl Schedule {
+    /// The clauses this schedule is written as, leaving out those matching anything
+    fn clauses(&self) -> Vec<Clause<'_>> {
+        let default_policy = DstPolicy::default();
+
+        let clauses = [
+            Clause::new("year").with(&self.year_spec),
+            Clause::new("month").with(&self.month_spec),
+            Clause::new("day")
+                .with(&self.day_spec)
+                .with(&self.month_end_spec),
+            Clause::new("weekday")
+                .with(&self.weekday_spec)
+                .with(&self.nth_weekday_spec),
+            Clause::new("hour").with(&self.hour_spec),
+            Clause::new("minute").with(&self.minute_spec),
+            Clause::new("second").with(&self.second_spec),
+            Clause::new("at").with(&self.time_spec),
+            Clause::new("on").with(&self.date_spec),
+            Clause::new("combine").with(
+                (self.day_combination != DayCombination::default())
+                    .then_some(&self.day_combination),
+            ),
+            Clause::new("dst")
+                .with((self.dst_policy.gap != default_policy.gap).then_some(&self.dst_policy.gap)),
+            Clause::new("dst").with(
+                (self.dst_policy.overlap != default_policy.overlap)
+                    .then_some(&self.dst_policy.overlap),
+            ),
+        ];
+
+        // Each exception gets a clause of its own, so that single-clause ones
+        // can't run into each other, as in `except month dec or hour 12`
+        let exceptions = self.exceptions.iter().map(|exception| {
+            let mut clause = Clause::new("except");
+            clause.items.push(Box::new(Exception(exception)));
+            clause
+        });
+
+        clauses
+            .into_iter()
+            .chain(exceptions)
+            .filter(|clause| !clause.items.is_empty())
+            .collect()
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("when")?;
+
+        for (idx, clause) in self.clauses().iter().enumerate() {
+            let separator = if idx == 0 { " " } else { ", " };
+            write!(f, "{separator}{clause}")?;
+        }
+
+        if let Some(timezone) = &self.timezone {
+            write!(f, " in {}", timezone.name())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An operand of `and`, `or` or `not`, written between parentheses unless
+/// it's `bare`, as when it binds tightly enough on its own
+struct Operand<'a> {
+    expr: &'a ScheduleExpr,
+    bare: bool,
+}
+
+impl fmt::Display for Operand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.expr {
+            ScheduleExpr::Schedule(schedule) => write!(f, "({schedule})"),
+            ScheduleExpr::Not(_) => write!(f, "{}", self.expr),
+            expr if self.bare => write!(f, "{expr}"),
+            expr => write!(f, "({expr})"),
+        }
+    }
+}
+
+impl fmt::Display for ScheduleExpr {
+    /// `and` binds tighter than `or`, and both group from the left, so
+    /// parentheses only go where the tree says otherwise
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Schedule(schedule) => write!(f, "{schedule}"),
+            Self::Or(left, right) => {
+                let left = Operand {
+                    expr: left,
+                    bare: true,
+                };
+                let right = Operand {
+                    expr: right,
+                    bare: matches!(**right, Self::And(..)),
+                };
+
+                write!(f, "{left} or {right}")
+            }
+            Self::And(left, right) => {
+                let left = Operand {
+                    expr: left,
+                    bare: matches!(**left, Self::And(..)),
+                };
+
+                match &**right {
+                    Self::Not(expr) => {
+                        write!(f, "{left} but not {}", Operand { expr, bare: false })
+                    }
+                    expr => write!(f, "{left} and {}", Operand { expr, bare: false }),
+                }
+            }
+            Self::Not(expr) => write!(f, "not {}", Operand { expr, bare: false }),
+        }
+    }
+}
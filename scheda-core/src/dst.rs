@@ -0,0 +1,181 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, Offset, TimeZone, Timelike};
+
+/// How a schedule treats the local times that daylight saving time transitions
+/// skip over or repeat.
+///
+/// Defaults to what cron does: skipped times fire as soon as the clocks have
+/// moved forward, and repeated times fire only once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DstPolicy {
+    pub gap: GapPolicy,
+    pub overlap: OverlapPolicy,
+}
+
+/// What happens to local times skipped when clocks are set forward,
+/// e.g. `hour 2, minute 30` on the day New York goes from 02:00 to 03:00
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum GapPolicy {
+    /// Skipped times don't fire at all
+    Skip,
+    /// Skipped times fire at the first instant after the clocks moved forward
+    #[default]
+    NextValid,
+}
+
+/// What happens to local times repeated when clocks are set back,
+/// e.g. `hour 1, minute 30` on the day New York goes from 02:00 back to 01:00
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum OverlapPolicy {
+    /// Repeated times fire on their first occurrence only
+    #[default]
+    Once,
+    /// Repeated times fire on both occurrences
+    Twice,
+}
+
+/// No time zone has ever shifted its clocks by a whole day at once, so this
+/// bounds how far the edges of a transition are looked for.
+const MAX_TRANSITION_MINUTES: usize = 24 * 60;
+
+fn truncate_to_minute(naive: NaiveDateTime) -> NaiveDateTime {
+    naive
+        .with_second(0)
+        .and_then(|naive| naive.with_nanosecond(0))
+        .unwrap()
+}
+
+/// Walks from `naive` in steps of `step` for as long as `keep_going` holds,
+/// returning the last time it held for.
+fn walk(
+    naive: NaiveDateTime,
+    step: Duration,
+    keep_going: impl Fn(NaiveDateTime) -> bool,
+) -> NaiveDateTime {
+    let mut last = naive;
+
+    for _ in 0..MAX_TRANSITION_MINUTES {
+        let next = last + step;
+        if !keep_going(next) {
+            break;
+        }
+        last = next;
+    }
+
+    last
+}
+
+/// Whether the offset from UTC at `date_time` differs from the one at the
+/// UTC time `earlier`.
+///
+/// Looking up a single offset is much cheaper than looking up what a local
+/// time maps to, so this rules out transitions before anything else.
+fn offset_changed_since<Tz: TimeZone>(date_time: &DateTime<Tz>, earlier: NaiveDateTime) -> bool {
+    date_time
+        .timezone()
+        .offset_from_utc_datetime(&earlier)
+        .fix()
+        != date_time.offset().fix()
+}
+
+/// Whether `date_time` is the second time its local time happens, after the
+/// clocks were set back
+pub(crate) fn is_repeat<Tz: TimeZone>(date_time: &DateTime<Tz>) -> bool {
+    // Clocks are never set back by more than a day
+    let earliest = date_time.naive_utc() - Duration::minutes(MAX_TRANSITION_MINUTES as i64);
+    if !offset_changed_since(date_time, earliest) {
+        return false;
+    }
+
+    match date_time
+        .timezone()
+        .from_local_datetime(&date_time.naive_local())
+    {
+        LocalResult::Ambiguous(earliest, _) => earliest != *date_time,
+        _ => false,
+    }
+}
+
+/// The first and last minutes of the run of repeated local times `naive` is in, if any
+pub(crate) fn repeated_minutes<Tz: TimeZone>(
+    time_zone: &Tz,
+    naive: NaiveDateTime,
+) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let is_repeated = |naive| {
+        matches!(
+            time_zone.from_local_datetime(&naive),
+            LocalResult::Ambiguous(..)
+        )
+    };
+
+    let minute = truncate_to_minute(naive);
+    if !is_repeated(minute) {
+        return None;
+    }
+
+    Some((
+        walk(minute, -Duration::minutes(1), is_repeated),
+        walk(minute, Duration::minutes(1), is_repeated),
+    ))
+}
+
+/// The first and last minutes of the local times skipped right before the
+/// minute `date_time` is in, if the clocks were just set forward
+#[inline]
+pub(crate) fn skipped_minutes_before<Tz: TimeZone>(
+    date_time: &DateTime<Tz>,
+) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    // The minute before can only have been skipped if the clocks moved within
+    // it. Stepping back within the hour is much cheaper than subtracting a minute
+    let utc = date_time.naive_utc();
+    let minute_before = match utc.minute() {
+        0 => utc.checked_sub_signed(Duration::minutes(1)),
+        minute => utc.with_minute(minute - 1),
+    };
+    if !minute_before.is_some_and(|minute_before| offset_changed_since(date_time, minute_before)) {
+        return None;
+    }
+
+    skipped_minutes_ending(date_time)
+}
+
+/// The skipped minutes ending right before `date_time`'s, once the offset is
+/// known to have changed. Kept out of line, it's rarely needed.
+#[inline(never)]
+fn skipped_minutes_ending<Tz: TimeZone>(
+    date_time: &DateTime<Tz>,
+) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let time_zone = date_time.timezone();
+    let is_skipped = |naive| matches!(time_zone.from_local_datetime(&naive), LocalResult::None);
+
+    let last = truncate_to_minute(date_time.naive_local()) - Duration::minutes(1);
+    if !is_skipped(last) {
+        return None;
+    }
+
+    Some((walk(last, -Duration::minutes(1), is_skipped), last))
+}
+
+/// The first instant after the run of skipped local times `naive` is in
+pub(crate) fn first_after_skipped<Tz: TimeZone>(
+    time_zone: &Tz,
+    naive: NaiveDateTime,
+) -> Option<DateTime<Tz>> {
+    let is_skipped = |naive| matches!(time_zone.from_local_datetime(&naive), LocalResult::None);
+
+    let last = walk(truncate_to_minute(naive), Duration::minutes(1), is_skipped);
+
+    time_zone
+        .from_local_datetime(&(last + Duration::minutes(1)))
+        .earliest()
+}
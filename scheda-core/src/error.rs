@@ -1,4 +1,7 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, fmt, ops::Range};
+
+/// A byte range into the text a schedule was parsed from
+pub type Span = Range<usize>;
 
 #[derive(Debug)]
 pub enum Error {
@@ -6,6 +9,158 @@ pub enum Error {
     MalformedRange(&'static str),
     UnknownDateTimePart(Box<str>),
     InvalidSyntax(Cow<'static, str>),
+    InvalidValue {
+        expected: Cow<'static, str>,
+        found: Box<str>,
+    },
+    /// A number outside the interval a date-time part allows, e.g. `hour 24`
+    OutOfRange {
+        expected: Cow<'static, str>,
+        min: u8,
+        max: u8,
+        found: Box<str>,
+    },
+    /// Something valid in one format, such as cron, that has no equivalent in another
+    Unsupported(Cow<'static, str>),
+    /// Every error found while parsing, in the order they appear in the input
+    Parse(Vec<Diagnostic>),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// A stable, machine-readable identifier for this kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::MissingWhenStmt => "missing-when",
+            Error::MalformedRange(_) => "malformed-range",
+            Error::UnknownDateTimePart(_) => "unknown-date-time-part",
+            Error::InvalidSyntax(_) => "invalid-syntax",
+            Error::InvalidValue { .. } => "invalid-value",
+            Error::OutOfRange { .. } => "out-of-range",
+            Error::Unsupported(_) => "unsupported",
+            Error::Parse(_) => "parse",
+        }
+    }
+
+    /// Pins this error to the given span of the input
+    pub fn at(self, span: Span) -> Diagnostic {
+        Diagnostic { report: self, span }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingWhenStmt => write!(f, "expected schedule to start with `when`"),
+            Error::MalformedRange(reason) => write!(f, "malformed range: {reason}"),
+            Error::UnknownDateTimePart(part) => write!(f, "unknown date-time part `{part}`"),
+            Error::InvalidSyntax(reason) => write!(f, "{reason}"),
+            Error::InvalidValue { expected, found } => {
+                write!(f, "expected {expected}, found `{found}`")
+            }
+            Error::OutOfRange {
+                expected,
+                min,
+                max,
+                found,
+            } => write!(
+                f,
+                "expected {expected} between {min} and {max}, found `{found}`"
+            ),
+            Error::Unsupported(reason) => write!(f, "{reason}"),
+            Error::Parse(diagnostics) => match diagnostics.as_slice() {
+                [diagnostic] => write!(f, "{}", diagnostic.report),
+                diagnostics => write!(f, "found {} errors in schedule", diagnostics.len()),
+            },
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// What a [`Diagnostic`] reports, either an [`Error`] or a
+/// [`Warning`](crate::Warning)
+pub trait Report: fmt::Display {
+    /// How it's labelled when rendered, e.g. `error`
+    const LABEL: &'static str;
+
+    /// A stable, machine-readable identifier for this kind of report
+    fn code(&self) -> &'static str;
+}
+
+impl Report for Error {
+    const LABEL: &'static str = "error";
+
+    fn code(&self) -> &'static str {
+        Error::code(self)
+    }
+}
+
+/// An error, or a warning, pointing at the part of the input that caused it
+#[derive(Debug)]
+pub struct Diagnostic<E = Error> {
+    pub report: E,
+    pub span: Span,
+}
+
+impl<E: Report> Diagnostic<E> {
+    pub fn code(&self) -> &'static str {
+        self.report.code()
+    }
+
+    /// Renders this diagnostic against the input it refers to, underlining the
+    /// offending text:
+    ///
+    /// ```no-rust
+    /// error[malformed-range]: malformed range: range's starting point is bigger than its end
+    ///  --> 1:10
+    ///   |
+    /// 1 | when day 20 to 10
+    ///   |          ^^^^^^^^
+    /// ```
+    pub fn render(&self, input: &str) -> String {
+        let start = self.span.start.min(input.len());
+
+        let line_start = input[..start].rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = input[start..]
+            .find('\n')
+            .map_or(input.len(), |idx| start + idx);
+        let line = &input[line_start..line_end];
+
+        let line_number = input[..line_start].matches('\n').count() + 1;
+        let column = input[line_start..start].chars().count();
+        // Spans running past the end of the line are cut short, and empty
+        // ones still get a single caret
+        let width = input[start..self.span.end.clamp(start, line_end)]
+            .chars()
+            .count()
+            .max(1);
+
+        let gutter = " ".repeat(line_number.to_string().len());
+
+        format!(
+            "{label}[{code}]: {report}\n\
+             {gutter}--> {line_number}:{column}\n\
+             {gutter} |\n\
+             {line_number} | {line}\n\
+             {gutter} | {padding}{carets}\n",
+            label = E::LABEL,
+            code = self.code(),
+            report = self.report,
+            column = column + 1,
+            padding = " ".repeat(column),
+            carets = "^".repeat(width),
+        )
+    }
+}
+
+impl<E: Report> fmt::Display for Diagnostic<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.report, self.span.start, self.span.end
+        )
+    }
+}
@@ -0,0 +1,492 @@
+//! Combines schedules with `and`, `or` and `not`, for what no single schedule
+//! can say, e.g. `(when weekday mon to fri, at 9:00) or (when weekday sat, at 12:00)`.
+
+use chrono::{DateTime, Duration, NaiveDate, Offset, TimeZone, Timelike};
+
+use crate::{
+    schedule::contains_date_time, search::SEARCH_HORIZON_YEARS, DateTimePart, Hour, Minute,
+    Schedule, Second,
+};
+
+/// How far ahead a negation on its own is searched for an instant its operand
+/// doesn't match
+const NEGATION_HORIZON_DAYS: i64 = 366;
+
+/// How many times the operands of an `and` may disagree on a candidate before
+/// the search stops leapfrogging between them and scans ahead instead
+const LEAPFROG_LIMIT: usize = 10_000;
+
+/// Schedules combined into one, matching whenever the combination holds.
+///
+/// Each schedule matches in its own time zone, so schedules in different time
+/// zones can be combined.
+// Most of the tree is schedules, which boxing would only add an allocation to
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ScheduleExpr {
+    Schedule(Schedule),
+    /// Matches when both sides do
+    And(Box<ScheduleExpr>, Box<ScheduleExpr>),
+    /// Matches when either side does
+    Or(Box<ScheduleExpr>, Box<ScheduleExpr>),
+    /// Matches whenever the schedules within don't
+    Not(Box<ScheduleExpr>),
+}
+
+impl From<Schedule> for ScheduleExpr {
+    fn from(schedule: Schedule) -> Self {
+        Self::Schedule(schedule)
+    }
+}
+
+/// Lazily yields every instant matching a [`ScheduleExpr`], in ascending order.
+///
+/// Created by [`ScheduleExpr::upcoming`].
+pub struct ExprUpcoming<'a, Tz: TimeZone> {
+    expr: &'a ScheduleExpr,
+    cursor: Option<DateTime<Tz>>,
+}
+
+impl<'a, Tz: TimeZone> Iterator for ExprUpcoming<'a, Tz> {
+    type Item = DateTime<Tz>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cursor = self.cursor.take()?;
+        let next = self.expr.next_after(cursor)?;
+
+        self.cursor = Some(next.clone());
+
+        Some(next)
+    }
+}
+
+impl ScheduleExpr {
+    pub fn matches<Tz: TimeZone>(&self, date_time: DateTime<Tz>) -> bool {
+        match self {
+            Self::Schedule(schedule) => schedule.matches(date_time),
+            Self::And(left, right) => left.matches(date_time.clone()) && right.matches(date_time),
+            Self::Or(left, right) => left.matches(date_time.clone()) || right.matches(date_time),
+            Self::Not(expr) => !expr.matches(date_time),
+        }
+    }
+
+    /// Finds the first instant strictly after `date_time` that matches this expression.
+    ///
+    /// Returns `None` if it can't ever fire again, as with [`Schedule::next_after`].
+    /// A negation that isn't part of an `and` matches most instants, and is
+    /// only searched up to a year ahead. An `and` whose operands keep
+    /// disagreeing, even once its negated schedules are taken into account,
+    /// can take much longer to search than its operands on their own.
+    pub fn next_after<Tz: TimeZone>(&self, date_time: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        // 400 Gregorian years always span the same number of days
+        let horizon = Duration::days(i64::from(SEARCH_HORIZON_YEARS) / 400 * 146_097);
+        let until = date_time.clone() + horizon;
+
+        self.next_until(&date_time, &until)
+    }
+
+    /// Lazily iterates over every instant after `from` that matches this expression.
+    pub fn upcoming<Tz: TimeZone>(&self, from: DateTime<Tz>) -> ExprUpcoming<'_, Tz> {
+        ExprUpcoming {
+            expr: self,
+            cursor: Some(from),
+        }
+    }
+
+    /// Finds the first match after `date_time`, giving up past `until`
+    fn next_until<Tz: TimeZone>(
+        &self,
+        date_time: &DateTime<Tz>,
+        until: &DateTime<Tz>,
+    ) -> Option<DateTime<Tz>> {
+        match self {
+            Self::Schedule(schedule) => schedule
+                .next_after(date_time.clone())
+                .filter(|found| found <= until),
+            // The earliest of either side's next matches, so the right side
+            // needn't be searched past the left side's
+            Self::Or(left, right) => {
+                let left = left.next_until(date_time, until);
+                let right = right.next_until(date_time, left.as_ref().unwrap_or(until));
+
+                match (left, right) {
+                    (Some(left), Some(right)) => Some(left.min(right)),
+                    (left, right) => left.or(right),
+                }
+            }
+            Self::And(..) => {
+                let mut operands = Vec::new();
+                self.push_operands(&mut operands);
+
+                // Negations match nearly everywhere, so only the other
+                // operands tell where to look next
+                let Some(lead) = operands
+                    .iter()
+                    .copied()
+                    .find(|operand| !matches!(operand, Self::Not(_)))
+                else {
+                    return self.scan_until(date_time, &negation_until(date_time, until));
+                };
+
+                // Taking negated schedules as exceptions lets the lead skip
+                // over what they match, e.g. all of `(when weekday mon) and
+                // not (when weekday mon)`, instead of landing on it over and over
+                let merged = lead.except_negated(&operands).map(Self::Schedule);
+                let lead = merged.as_ref().unwrap_or(lead);
+
+                let mut candidate = lead.next_until(date_time, until)?;
+
+                // Leapfrogs from one operand's next match to another's until
+                // they all agree
+                for _ in 0..LEAPFROG_LIMIT {
+                    let Some(operand) = operands
+                        .iter()
+                        .find(|operand| !operand.matches(candidate.clone()))
+                    else {
+                        return Some(candidate);
+                    };
+
+                    let next = match operand {
+                        Self::Not(_) => lead,
+                        operand => operand,
+                    };
+                    candidate = next.next_until(&candidate, until)?;
+                }
+
+                // Operands that take this long to agree, if they ever do,
+                // are searched the slow way instead
+                self.scan_until(&candidate, until)
+            }
+            Self::Not(_) => self.scan_until(date_time, &negation_until(date_time, until)),
+        }
+    }
+
+    /// Collects the operands of a chain of `and`s, e.g. all three in `a and b and c`
+    fn push_operands<'a>(&'a self, operands: &mut Vec<&'a Self>) {
+        match self {
+            Self::And(left, right) => {
+                left.push_operands(operands);
+                right.push_operands(operands);
+            }
+            expr => operands.push(expr),
+        }
+    }
+
+    /// This schedule, with the negated schedules among `operands` as
+    /// exceptions, for those that match in the same time zone and way.
+    ///
+    /// It matches whenever this schedule and all those negations do, give or
+    /// take the times clocks skip over, so it's only a first pick of candidates.
+    fn except_negated(&self, operands: &[&Self]) -> Option<Schedule> {
+        let Self::Schedule(schedule) = self else {
+            return None;
+        };
+
+        let mut merged = schedule.clone();
+        for operand in operands {
+            let Self::Not(negated) = operand else {
+                continue;
+            };
+            let Self::Schedule(negated) = negated.as_ref() else {
+                continue;
+            };
+            if (negated.timezone, negated.dst_policy) != (schedule.timezone, schedule.dst_policy) {
+                continue;
+            }
+
+            let mut exception = negated.clone();
+            // Unlike schedules, exceptions without a `second` clause match every second
+            if exception.second_spec.is_empty() {
+                exception
+                    .second_spec
+                    .push(DateTimePart::Single(Second::MIN));
+            }
+            merged.exceptions.push(exception);
+        }
+
+        Some(merged)
+    }
+
+    /// Checks the whole seconds after `date_time` in turn, up to `until`,
+    /// skipping over those known not to match
+    fn scan_until<Tz: TimeZone>(
+        &self,
+        date_time: &DateTime<Tz>,
+        until: &DateTime<Tz>,
+    ) -> Option<DateTime<Tz>> {
+        let mut candidate = date_time.with_nanosecond(0)? + Duration::seconds(1);
+
+        while candidate <= *until {
+            if self.matches(candidate.clone()) {
+                return Some(candidate);
+            }
+            candidate = self.unmatched_until(&candidate, until);
+        }
+
+        None
+    }
+
+    /// The first instant after `date_time`, which this expression matches,
+    /// that it might not match
+    fn matched_until<Tz: TimeZone>(
+        &self,
+        date_time: &DateTime<Tz>,
+        until: &DateTime<Tz>,
+    ) -> DateTime<Tz> {
+        match self {
+            Self::Schedule(schedule) => schedule.matched_until(date_time),
+            Self::And(left, right) => left
+                .matched_until(date_time, until)
+                .min(right.matched_until(date_time, until)),
+            Self::Or(left, right) => [left, right]
+                .into_iter()
+                .filter(|side| side.matches(date_time.clone()))
+                .map(|side| side.matched_until(date_time, until))
+                .max()
+                .unwrap(),
+            Self::Not(expr) => expr.unmatched_until(date_time, until),
+        }
+    }
+
+    /// The first instant after `date_time`, which this expression doesn't
+    /// match, that it might match, or one past `until`
+    fn unmatched_until<Tz: TimeZone>(
+        &self,
+        date_time: &DateTime<Tz>,
+        until: &DateTime<Tz>,
+    ) -> DateTime<Tz> {
+        match self {
+            Self::Schedule(_) => self
+                .next_until(date_time, until)
+                .unwrap_or_else(|| until.clone() + Duration::seconds(1)),
+            // Either side not matching is enough
+            Self::And(left, right) => [left, right]
+                .into_iter()
+                .filter(|side| !side.matches(date_time.clone()))
+                .map(|side| side.unmatched_until(date_time, until))
+                .max()
+                .unwrap(),
+            Self::Or(left, right) => left
+                .unmatched_until(date_time, until)
+                .min(right.unmatched_until(date_time, until)),
+            Self::Not(expr) => expr.matched_until(date_time, until),
+        }
+    }
+}
+
+/// How far a search for a negation from `date_time` goes, since it matches
+/// most instants but might match none
+fn negation_until<Tz: TimeZone>(date_time: &DateTime<Tz>, until: &DateTime<Tz>) -> DateTime<Tz> {
+    until
+        .clone()
+        .min(date_time.clone() + Duration::days(NEGATION_HORIZON_DAYS))
+}
+
+impl Schedule {
+    /// The first instant after `date_time`, which this schedule matches, that
+    /// it might not match, skipping whole days, hours and minutes it matches
+    /// throughout
+    fn matched_until<Tz: TimeZone>(&self, date_time: &DateTime<Tz>) -> DateTime<Tz> {
+        match self.timezone {
+            Some(timezone) => self
+                .matched_until_local(&date_time.with_timezone(&timezone))
+                .with_timezone(&date_time.timezone()),
+            None => self.matched_until_local(date_time),
+        }
+    }
+
+    /// Like [`Schedule::matched_until`], in the time zone `date_time` is in
+    fn matched_until_local<Tz: TimeZone>(&self, date_time: &DateTime<Tz>) -> DateTime<Tz> {
+        let local = date_time.naive_local();
+        let (date, hour, minute) = (local.date(), local.hour(), local.minute());
+        let start_of_hour = local.date().and_hms_opt(hour, 0, 0).unwrap();
+
+        let periods = [
+            (
+                vec![],
+                date.succ_opt().and_then(|next| next.and_hms_opt(0, 0, 0)),
+            ),
+            (vec![hour], Some(start_of_hour + Duration::hours(1))),
+            (
+                vec![hour, minute],
+                Some(start_of_hour + Duration::minutes(i64::from(minute) + 1)),
+            ),
+        ];
+
+        periods
+            .into_iter()
+            .filter(|(time, _)| self.matches_throughout(date, time))
+            .find_map(|(_, end)| {
+                let end = date_time.timezone().from_local_datetime(&end?).earliest()?;
+
+                // Clocks changing within the period may skip or repeat times
+                // the DST policy treats differently
+                (end.offset().fix() == date_time.offset().fix()).then_some(end)
+            })
+            .unwrap_or_else(|| date_time.with_nanosecond(0).unwrap() + Duration::seconds(1))
+    }
+
+    /// Whether this schedule matches every second of `date` starting with
+    /// `time`, which holds up to an hour and a minute, leaving no room for an
+    /// exception to match any of them
+    fn matches_throughout(&self, date: NaiveDate, time: &[u32]) -> bool {
+        let hours = time.first().map_or(0..24, |&hour| hour..hour + 1);
+        let minutes = time.get(1).map_or(0..60, |&minute| minute..minute + 1);
+        let hour = time.first().map(|&hour| Hour::new(hour as u8).unwrap());
+        let minute = time
+            .get(1)
+            .map(|&minute| Minute::new(minute as u8).unwrap());
+
+        self.matches_date(date, self.either_day())
+            && (0..60).all(|second| self.contains_second(Second::new(second).unwrap()))
+            && hours.into_iter().all(|hour| {
+                let hour = Hour::new(hour as u8).unwrap();
+
+                self.contains_hour(hour)
+                    && minutes.clone().all(|minute| {
+                        let minute = Minute::new(minute as u8).unwrap();
+
+                        self.contains_minute(minute)
+                            && self.contains_time(&hour, &minute)
+                            && contains_date_time(&self.date_spec, date, &hour, &minute)
+                    })
+            })
+            && !self.exceptions.iter().any(|exception| {
+                exception.matches_date(date, exception.either_day())
+                    && hour.is_none_or(|hour| exception.contains_hour(hour))
+                    && minute.is_none_or(|minute| exception.contains_minute(minute))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+    use scheda_parser::Parser;
+
+    fn next_after(expr: &str, date_time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        Parser::new(expr)
+            .parse_expr()
+            .unwrap()
+            .next_after(date_time)
+    }
+
+    #[test]
+    fn matches_combined_schedules() {
+        let expr = Parser::new(
+            "(when weekday mon to fri, at 9:00) or (when weekday sat, at 12:00) \
+             but not (when month dec)",
+        )
+        .parse_expr()
+        .unwrap();
+
+        // 2024-03-08 is a Friday
+        assert!(expr.matches(Utc.with_ymd_and_hms(2024, 3, 8, 9, 0, 0).unwrap()));
+        assert!(expr.matches(Utc.with_ymd_and_hms(2024, 3, 9, 12, 0, 0).unwrap()));
+        assert!(!expr.matches(Utc.with_ymd_and_hms(2024, 3, 9, 9, 0, 0).unwrap()));
+        assert!(!expr.matches(Utc.with_ymd_and_hms(2024, 12, 7, 12, 0, 0).unwrap()));
+        // `and` binds tighter than `or`, so December weekdays still match
+        assert!(expr.matches(Utc.with_ymd_and_hms(2024, 12, 6, 9, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn merges_upcoming_matches() {
+        let expr = Parser::new(
+            "(when weekday mon to fri, at 9:00 in Europe/Berlin) \
+             or (when weekday sat, at 12:00 in America/New_York)",
+        )
+        .parse_expr()
+        .unwrap();
+        let upcoming: Vec<_> = expr
+            .upcoming(Utc.with_ymd_and_hms(2024, 3, 8, 9, 0, 0).unwrap())
+            .take(3)
+            .collect();
+
+        assert_eq!(
+            upcoming,
+            [
+                Utc.with_ymd_and_hms(2024, 3, 9, 17, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 3, 11, 8, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 3, 12, 8, 0, 0).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn searches_intersections_and_negations() {
+        let from = Utc.with_ymd_and_hms(2024, 3, 8, 9, 0, 0).unwrap();
+
+        // The 13th that's a Friday
+        assert_eq!(
+            next_after("(when day 13, at 0:00) and (when weekday fri)", from),
+            Some(Utc.with_ymd_and_hms(2024, 9, 13, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            next_after(
+                "(when hour 9, minute 0) but not (when weekday sat to sun)",
+                from
+            ),
+            Some(Utc.with_ymd_and_hms(2024, 3, 11, 9, 0, 0).unwrap())
+        );
+        assert_eq!(
+            next_after("not (when day 8, hour 9 to 10, second 0 to 59)", from),
+            Some(Utc.with_ymd_and_hms(2024, 3, 8, 11, 0, 0).unwrap())
+        );
+        assert_eq!(
+            next_after("(when on 2024-03-10) and (when on 2024-03-11)", from),
+            None
+        );
+    }
+
+    #[test]
+    fn skips_over_what_negations_rule_out() {
+        let from = Utc.with_ymd_and_hms(2024, 3, 8, 9, 0, 0).unwrap();
+        let always = "(when hour 0 to 23, minute 0 to 59, second 0 to 59)";
+
+        assert_eq!(next_after(&format!("not {always}"), from), None);
+        assert_eq!(
+            next_after(
+                "not (when year 2024, hour 0 to 23, minute 0 to 59, second 0 to 59)",
+                from
+            ),
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+        );
+        // The negation isn't searched past the Monday
+        assert_eq!(
+            next_after(&format!("(when weekday mon) or not {always}"), from),
+            Some(Utc.with_ymd_and_hms(2024, 3, 11, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            next_after("(when weekday mon) and not (when weekday mon)", from),
+            None
+        );
+        assert_eq!(
+            next_after(
+                "(when weekday mon, second 0 to 59) and not (when weekday mon)",
+                from
+            ),
+            Some(Utc.with_ymd_and_hms(2024, 3, 11, 0, 0, 1).unwrap())
+        );
+    }
+
+    #[test]
+    fn keeps_searching_when_operands_disagree_for_long() {
+        // 2024-03-09 is a Saturday, and the only hour that matches is a week
+        // of minutes later, more than the leapfrogging takes
+        let from = Utc.with_ymd_and_hms(2024, 3, 9, 23, 59, 0).unwrap();
+
+        assert_eq!(
+            next_after(
+                "(when minute every 1) \
+                 and not ((when hour 0 to 22) or (when weekday sun to fri))",
+                from
+            ),
+            Some(Utc.with_ymd_and_hms(2024, 3, 16, 23, 0, 0).unwrap())
+        );
+    }
+}
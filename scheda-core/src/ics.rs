@@ -0,0 +1,166 @@
+//! Writes the upcoming occurrences of a schedule out as an iCalendar (RFC 5545)
+//! file, with one `VEVENT` per occurrence.
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::Schedule;
+
+/// The longest a content line may be, in octets, before it has to be folded
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Escapes the characters that are special in iCalendar text values
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for char in text.chars() {
+        match char {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(char);
+            }
+            '\n' => escaped.push_str("\\n"),
+            char => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+/// Writes `line` ended by CRLF, folding it onto continuation lines starting
+/// with a space whenever it runs past [`MAX_LINE_OCTETS`]
+fn push_line(ics: &mut String, line: &str) {
+    let mut octets = 0;
+
+    for char in line.chars() {
+        // Continuation lines lose an octet to their leading space
+        if octets + char.len_utf8() > MAX_LINE_OCTETS {
+            ics.push_str("\r\n ");
+            octets = 1;
+        }
+
+        ics.push(char);
+        octets += char.len_utf8();
+    }
+
+    ics.push_str("\r\n");
+}
+
+/// A 64-bit FNV-1a hash of `text`, which unlike [`std::hash::Hash`] stays
+/// the same across Rust versions and platforms
+fn fingerprint(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn format_utc<Tz: TimeZone>(date_time: &DateTime<Tz>) -> String {
+    date_time
+        .with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+impl Schedule {
+    /// Writes the next `count` occurrences of this schedule after `from` as an
+    /// iCalendar file, each one a `VEVENT` summarized by the schedule itself.
+    ///
+    /// Occurrences are written in UTC, so the file needs no `VTIMEZONE`.
+    /// Fewer than `count` events are written if the schedule stops firing.
+    pub fn to_ics<Tz: TimeZone>(&self, from: DateTime<Tz>, count: usize) -> String {
+        let stamp = format_utc(&from);
+        let when = self.to_string();
+        let summary = escape_text(&when);
+        // Schedules display the same whenever they're equivalent, so this
+        // tells apart schedules that happen to share an occurrence
+        let fingerprint = fingerprint(&when);
+
+        let mut ics = String::new();
+        push_line(&mut ics, "BEGIN:VCALENDAR");
+        push_line(&mut ics, "VERSION:2.0");
+        push_line(&mut ics, "PRODID:-//scheda//scheda//EN");
+
+        for occurrence in self.upcoming(from).take(count) {
+            let start = format_utc(&occurrence);
+
+            push_line(&mut ics, "BEGIN:VEVENT");
+            // A schedule fires at most once per instant, so the two together
+            // make for UIDs that are unique and stay the same across exports
+            push_line(&mut ics, &format!("UID:{start}-{fingerprint:016x}@scheda"));
+            push_line(&mut ics, &format!("DTSTAMP:{stamp}"));
+            push_line(&mut ics, &format!("DTSTART:{start}"));
+            push_line(&mut ics, &format!("SUMMARY:{summary}"));
+            push_line(&mut ics, "END:VEVENT");
+        }
+
+        push_line(&mut ics, "END:VCALENDAR");
+
+        ics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use chrono_tz::America::New_York;
+    use scheda_parser::Parser;
+
+    #[test]
+    fn writes_upcoming_occurrences_as_events() {
+        let schedule =
+            Parser::new("when hour 9, minute 30, weekday mon or fri in America/New_York")
+                .parse_schedule()
+                .unwrap();
+        let from = New_York.with_ymd_and_hms(2024, 3, 8, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            schedule.to_ics(from, 2),
+            "BEGIN:VCALENDAR\r\n\
+             VERSION:2.0\r\n\
+             PRODID:-//scheda//scheda//EN\r\n\
+             BEGIN:VEVENT\r\n\
+             UID:20240311T133000Z-5f788615c5d5d5c3@scheda\r\n\
+             DTSTAMP:20240308T170000Z\r\n\
+             DTSTART:20240311T133000Z\r\n\
+             SUMMARY:when weekday mon or fri\\, hour 9\\, minute 30 in America/New_York\r\n\
+             END:VEVENT\r\n\
+             BEGIN:VEVENT\r\n\
+             UID:20240315T133000Z-5f788615c5d5d5c3@scheda\r\n\
+             DTSTAMP:20240308T170000Z\r\n\
+             DTSTART:20240315T133000Z\r\n\
+             SUMMARY:when weekday mon or fri\\, hour 9\\, minute 30 in America/New_York\r\n\
+             END:VEVENT\r\n\
+             END:VCALENDAR\r\n"
+        );
+
+        // Schedules sharing an occurrence, here on Monday, still give it
+        // different UIDs
+        let uid = |schedule: &str| {
+            let ics = Parser::new(schedule)
+                .parse_schedule()
+                .unwrap()
+                .to_ics(from, 1);
+            ics.lines()
+                .find_map(|line| line.strip_prefix("UID:"))
+                .unwrap()
+                .to_owned()
+        };
+        let (mondays, weekdays) = (
+            uid("when hour 9, minute 30, weekday mon in America/New_York"),
+            uid("when hour 9, minute 30, weekday mon to fri in America/New_York"),
+        );
+        assert_eq!(mondays[..16], weekdays[..16]);
+        assert_ne!(mondays, weekdays);
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let mut ics = String::new();
+        super::push_line(&mut ics, &"x".repeat(160));
+
+        let lines: Vec<_> = ics.split("\r\n").collect();
+        assert_eq!(lines[0].len(), 75);
+        assert_eq!(lines[1], format!(" {}", "x".repeat(74)));
+        assert_eq!(lines[2], format!(" {}", "x".repeat(11)));
+        assert_eq!(lines[3], "");
+    }
+}
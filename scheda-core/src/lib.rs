@@ -26,28 +26,226 @@
 //! when month 1 to 9
 //! when month 3 to Dec
 //! ```
+//!
+//! Months, weekdays, hours, minutes and seconds wrap around, so a range starting after
+//! its end runs through the end of the cycle and starts over:
+//!
+//! ```no-rust
+//! when month Nov to Feb
+//! when weekday Fri to Mon
+//! when hour 22 to 4
+//! ```
+//!
+//! ## Nth weekdays
+//!
+//! Weekdays can be narrowed down to one of their occurrences within the month:
+//!
+//! ```no-rust
+//! when weekday second tue
+//! when weekday last fri or sat
+//! ```
+//!
+//! ## Days from the end of the month
+//!
+//! Days of the month can also be counted back from its end, `last` being `-1`:
+//!
+//! ```no-rust
+//! when day last
+//! when day -3 to -1
+//! when day 15 or last
+//! ```
+//!
+//! ## Combining days and weekdays
+//!
+//! Days of the month and weekdays both have to match by default. Like cron,
+//! `combine or` makes either of them enough when both are given:
+//!
+//! ```no-rust
+//! when day 1, weekday mon, combine or
+//! ```
+//!
+//! ## Steps
+//!
+//! Seconds, minutes, hours and days can be stepped through with `every`. Steps count
+//! from the start of the enclosing period, and the fields below a step are
+//! pinned to their first value unless given explicitly. Like any other
+//! clause, a step only matches what the clause of the field it steps through
+//! also does, so the last one below fires at 10, 12, 14 and 16 o'clock.
+//!
+//! ```no-rust
+//! when every 15 minutes
+//! when every 2 hours, weekday mon to fri
+//! when every 3 days, hour 9
+//! when every 2 hours, hour 9 to 17
+//! ```
+//!
+//! Days count from the first of each month, so day steps start over with
+//! every month rather than carrying on across it: `every 3 days` fires on
+//! January 28 and 31, then on February 1 and 4, and `every 2 days` fires on
+//! both the 31st and the 1st after every 31-day month.
+//!
+//! Any clause can also step through its own values, optionally starting
+//! `from` another one. These don't pin anything:
+//!
+//! ```no-rust
+//! when minute every 15 from 5
+//! when month every 3 from feb, day 1, hour 0, minute 0
+//! ```
+//!
+//! ## Seconds
+//!
+//! Schedules without a `second` clause fire on the first second of every
+//! minute they match, and only match that second. A `second` clause fires on
+//! the seconds it gives instead:
+//!
+//! ```no-rust
+//! when second 0 or 30
+//! when every 10 seconds
+//! when hour 9, minute 0, second 15
+//! ```
+//!
+//! ## Times of day
+//!
+//! `at` pairs an hour with a minute, in either 24-hour or 12-hour notation:
+//!
+//! ```no-rust
+//! when at 14:30 or 18:00
+//! when at 9:15 am, weekday mon to fri
+//! ```
+//!
+//! ## Years and one-off dates
+//!
+//! Schedules repeat every year unless a `year` clause limits them to some:
+//!
+//! ```no-rust
+//! when year 2027 to 2028, month jan, day 1, at 0:00
+//! when year every 4 from 2028
+//! ```
+//!
+//! `on` gives ISO 8601 dates, optionally with a time of day after a space or
+//! a `T`, for schedules that only fire once or a handful of times:
+//!
+//! ```no-rust
+//! when on 2026-12-24 23:00
+//! when on 2026-12-31 or 2027-01-01, hour 9, minute 0
+//! ```
+//!
+//! Once past their last year or date, schedules are exhausted, and
+//! [`Schedule::next_after`] finds nothing.
+//!
+//! ## Exceptions
+//!
+//! `except` leaves out whatever another schedule matches. A single clause
+//! stands for a schedule of its own, and several clauses go between
+//! parentheses, after their own `when`:
+//!
+//! ```no-rust
+//! when hour 9, minute 0, except month dec
+//! when every 15 minutes, except (when weekday sat to sun, hour 0 to 6)
+//! when weekday mon to fri, except (when day 24 to 26, except weekday mon)
+//! ```
+//!
+//! Exceptions are matched in the time zone of the schedule they're part of,
+//! and, having no `second` clause, leave out the whole minutes they match.
+//!
+//! ## Combining schedules
+//!
+//! Schedules between parentheses can be combined into a [`ScheduleExpr`]
+//! with `and`, `or`, `but not` and `not`, for what no single schedule can say.
+//! `and` binds tighter than `or`, and each schedule keeps its own time zone:
+//!
+//! ```no-rust
+//! (when weekday mon to fri, at 9:00) or (when weekday sat, at 12:00)
+//! ((when hour 9 to 17) or (when hour 20)) but not (when day 25, month dec)
+//! (when at 9:00 in Europe/Berlin) and (when weekday mon in America/New_York)
+//! ```
+//!
+//! ## Time zones
+//!
+//! Schedules match date-times in whatever time zone they're given in, unless
+//! they end with an IANA time zone to convert them to first:
+//!
+//! ```no-rust
+//! when hour 9, weekday mon to fri in America/Sao_Paulo
+//! ```
+//!
+//! Local times that daylight saving time transitions skip over fire as soon as
+//! the clocks have moved forward, and local times they repeat fire only once.
+//! `dst skip` and `dst twice` change either of these, as does
+//! [`Schedule::dst_policy`]:
+//!
+//! ```no-rust
+//! when hour 2, minute 30, dst skip, dst twice in America/New_York
+//! ```
+//!
+//! ## Writing schedules back out
+//!
+//! Schedules display in the `when` syntax, always in the same canonical form,
+//! and [`Schedule::to_cron`] and [`Schedule::to_systemd`] write them as crontab
+//! expressions and systemd calendar events whenever those can express them.
+//! A bare `when` matches every minute. [`Schedule::normalize`] rewrites a
+//! schedule into its shortest equivalent first, e.g. `hour 1 to 3 or 2 to 5`
+//! into `hour 1 to 5`.
+//!
+//! [`Schedule::to_rrule`] and [`Schedule::from_rrule`] convert them to and
+//! from iCalendar recurrence rules, and [`Schedule::to_ics`] writes their next
+//! occurrences out as calendar events.
+//!
+//! ## Checking schedules
+//!
+//! Some schedules parse fine but never fire, such as `when month feb, day 30`.
+//! [`Schedule::is_satisfiable`] tells those apart over a whole leap-year
+//! cycle, and [`Schedule::analyze`] also warns about schedules firing only in
+//! leap years and about `or` parts that are redundant or overlap. Parsing with
+//! `scheda_parser::Parser::parse_with_warnings` points each [`Warning`] at the
+//! input, rendered like errors are:
+//!
+//! ```no-rust
+//! warning[redundant-part]: value already matched by the rest of its clause
+//!  --> 1:22
+//!   |
+//! 1 | when hour 8 to 10 or 9
+//!   |                      ^
+//! ```
+//!
+//! ## Serialization
+//!
+//! The `serde` feature derives `Serialize` and `Deserialize` for schedules
+//! and everything in them, field by field. `scheda-parser`'s own `serde`
+//! feature offers a compact alternative, `scheda_parser::compact`, which
+//! stores schedules as their `when` syntax instead.
 
+mod analyze;
 mod as_u8;
+mod bounded;
+mod compiled;
+mod cron;
+mod display;
+mod dst;
 mod error;
+mod expr;
+mod ics;
+mod normalize;
+mod rrule;
 mod schedule;
+mod search;
+#[cfg(feature = "serde")]
+mod serde;
+mod systemd;
 mod types;
 mod well_formed_range;
 
+pub use analyze::{Spec, Warning};
 pub use as_u8::AsU8;
+pub use bounded::BoundedU8;
 pub use chrono;
-pub use error::{Error, Result};
+pub use chrono_tz;
+pub use compiled::CompiledSchedule;
+pub use display::DisplayAtom;
+pub use dst::{DstPolicy, GapPolicy, OverlapPolicy};
+pub use error::{Diagnostic, Error, Report, Result, Span};
+pub use expr::{ExprUpcoming, ScheduleExpr};
 pub use schedule::Schedule;
+pub use search::{Preceding, Upcoming};
 pub use types::*;
 pub use well_formed_range::WellFormedRange;
-
-// TODO: Revisit ZeroToN to check if we want to use it
-// struct ZeroToN<const N: usize>(NonZeroU8);
-
-// impl <const N: usize> ZeroToN<N> {
-//     pub fn new(val: u8) -> Option<Self> {
-//         (val <= (N as u8)).then(|| {
-//             // Safety: val+1 can never be zero once val is unsigned
-//             Self(unsafe { NonZeroU8::new_unchecked(val + 1) })
-//         })
-//     }
-// }
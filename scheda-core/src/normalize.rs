@@ -0,0 +1,390 @@
+//! Rewrites schedules into a canonical form, so that two schedules meaning the
+//! same thing compare equal, e.g. `hour 1 to 3 or 2 to 5` and `hour 1 to 5`.
+
+use std::num::NonZeroU8;
+
+use chrono::{Month, Weekday};
+use num_traits::FromPrimitive;
+use smallvec::SmallVec;
+
+use crate::{
+    cron::runs, AsU8, DateTimePart, DayCombination, Hour, Minute, MonthDay, MonthDayFromEnd,
+    Schedule, Second, WellFormedRange, Year,
+};
+
+/// Rewrites `parts` as the fewest parts matching the same values, in ascending
+/// order, where `from_u8` turns an [`AsU8`] value from `T::FIRST` to `last`
+/// back into a `T`.
+///
+/// Returns `None` if `parts` matches every value.
+fn canonical_parts<T: AsU8 + WellFormedRange>(
+    parts: &[DateTimePart<T>],
+    last: u8,
+    from_u8: impl Fn(u8) -> T,
+) -> Option<Vec<DateTimePart<T>>> {
+    let first = T::FIRST.as_u8();
+    let values: Vec<u8> = (first..=last)
+        .filter(|&value| parts.iter().any(|part| part.contains(&from_u8(value))))
+        .collect();
+
+    if parts.is_empty() || values.len() == usize::from(last - first + 1) {
+        return None;
+    }
+
+    // Every `step`-th value up to the end of the cycle, e.g. `minute 5 or 25 or 45`
+    if let [start, next, ..] = values[..] {
+        let step = next - start;
+        let is_step = values.len() >= 3
+            && step >= 2
+            && values.windows(2).all(|pair| pair[1] - pair[0] == step)
+            && u16::from(values[values.len() - 1]) + u16::from(step) > u16::from(last);
+
+        if is_step {
+            return Some(vec![DateTimePart::Step {
+                starting: from_u8(start),
+                step: NonZeroU8::new(step).unwrap(),
+            }]);
+        }
+    }
+
+    let mut runs = runs(values);
+
+    // Runs touching both ends of a cycle that wraps around are a single one
+    let wrapping = match (runs.first(), runs.last()) {
+        (Some(&(head, head_end)), Some(&(tail, last_value)))
+            if T::WRAPS_AROUND && runs.len() > 1 && head == first && last_value == last =>
+        {
+            runs.pop();
+            runs.remove(0);
+
+            Some(DateTimePart::WrappingRange {
+                starting: from_u8(tail),
+                ending: from_u8(head_end),
+            })
+        }
+        _ => None,
+    };
+
+    let parts = runs
+        .into_iter()
+        .map(|(start, end)| match start == end {
+            true => DateTimePart::Single(from_u8(start)),
+            false => DateTimePart::Range {
+                starting: from_u8(start),
+                ending: from_u8(end),
+            },
+        })
+        .chain(wrapping)
+        .collect();
+
+    Some(parts)
+}
+
+/// Replaces `parts` with their canonical form, returning whether they turned
+/// out to restrict nothing, e.g. `weekday sun to sat`
+fn normalize_parts<T: AsU8 + WellFormedRange, const N: usize>(
+    parts: &mut SmallVec<[DateTimePart<T>; N]>,
+    last: u8,
+    from_u8: impl Fn(u8) -> T,
+) -> bool
+where
+    [DateTimePart<T>; N]: smallvec::Array<Item = DateTimePart<T>>,
+{
+    let restricted = !parts.is_empty();
+    let canonical = canonical_parts(parts, last, from_u8);
+    let matches_everything = canonical.is_none();
+
+    *parts = canonical.unwrap_or_default().into_iter().collect();
+
+    restricted && matches_everything
+}
+
+impl Schedule {
+    /// Rewrites this schedule into its canonical form, without changing when it fires.
+    ///
+    /// Overlapping and adjacent ranges are merged, values are sorted and
+    /// deduplicated, and clauses matching every value are dropped, so that
+    /// schedules meaning the same thing compare equal once normalized:
+    ///
+    /// ```
+    /// # use scheda_core::{DateTimePart, Hour, Schedule};
+    /// let mut schedule = Schedule::new();
+    /// for hour in [3, 1, 2, 2] {
+    ///     schedule.hour_spec.push(DateTimePart::Single(Hour::new(hour).unwrap()));
+    /// }
+    /// schedule.normalize();
+    ///
+    /// assert_eq!(schedule.to_string(), "when hour 1 to 3");
+    /// ```
+    pub fn normalize(&mut self) {
+        self.normalize_as(false);
+    }
+
+    /// Normalizes this schedule, knowing whether it's an exception, which
+    /// match every second of a minute rather than the first one by default
+    fn normalize_as(&mut self, is_exception: bool) {
+        let either_day = self.either_day();
+
+        normalize_parts(&mut self.month_spec, 12, |month| {
+            Month::from_u8(month).unwrap()
+        });
+        normalize_parts(&mut self.hour_spec, 23, |hour| Hour::new(hour).unwrap());
+        normalize_parts(&mut self.minute_spec, 59, |minute| {
+            Minute::new(minute).unwrap()
+        });
+
+        // Without a `second` clause, schedules fire on the first second of the
+        // minute rather than on every one
+        let every_second = normalize_parts(&mut self.second_spec, 59, |second| {
+            Second::new(second).unwrap()
+        });
+        if every_second && !is_exception {
+            self.second_spec.push(DateTimePart::Range {
+                starting: Second::MIN,
+                ending: Second::MAX,
+            });
+        }
+        if self.second_spec.as_slice() == [DateTimePart::Single(Second::MIN)] && !is_exception {
+            self.second_spec.clear();
+        }
+
+        // Days counted from either end of the month add up, so either one
+        // matching every day is enough
+        let every_day = normalize_parts(&mut self.day_spec, 31, |day| {
+            MonthDay::from_u8(day).unwrap()
+        }) | normalize_parts(&mut self.month_end_spec, 31, |day| {
+            MonthDayFromEnd::from_u8(32 - day).unwrap()
+        });
+        // `AsU8` counts weekdays from Sunday, but `FromPrimitive` from Monday
+        let every_weekday = normalize_parts(&mut self.weekday_spec, 6, |weekday| {
+            Weekday::from_u8((weekday + 6) % 7).unwrap()
+        });
+
+        if every_day || (either_day && every_weekday) {
+            self.day_spec.clear();
+            self.month_end_spec.clear();
+        }
+        if every_weekday || (either_day && every_day) {
+            self.weekday_spec.clear();
+            self.nth_weekday_spec.clear();
+        }
+
+        let weekdays = &self.weekday_spec;
+        self.nth_weekday_spec.retain(|nth_weekday| {
+            !weekdays
+                .iter()
+                .any(|part| part.contains(&nth_weekday.weekday))
+        });
+        self.nth_weekday_spec.sort();
+        self.nth_weekday_spec.dedup();
+
+        // Only both days and weekdays being restricted makes `combine or` do anything
+        if !(self.restricts_days() && self.restricts_weekdays()) {
+            self.day_combination = DayCombination::default();
+        }
+
+        self.time_spec.sort();
+        self.time_spec.dedup();
+
+        // Years span too many values to be rewritten like the other parts, so
+        // only overlapping and adjacent ones are merged, followed by the steps
+        let mut spans: Vec<(Year, Year)> = Vec::new();
+        let mut steps = SmallVec::<[DateTimePart<Year>; 2]>::new();
+        for part in self.year_spec.drain(..) {
+            match part {
+                DateTimePart::Single(year) => spans.push((year, year)),
+                DateTimePart::Range { starting, ending }
+                | DateTimePart::WrappingRange { starting, ending } => {
+                    spans.push((starting, ending))
+                }
+                step @ DateTimePart::Step { .. } => steps.push(step),
+            }
+        }
+        spans.sort();
+        steps.sort();
+        steps.dedup();
+
+        let mut merged: Vec<(Year, Year)> = Vec::with_capacity(spans.len());
+        for (starting, ending) in spans {
+            match merged.last_mut() {
+                Some((_, last)) if starting.0 <= last.0.saturating_add(1) => {
+                    *last = ending.max(*last)
+                }
+                _ => merged.push((starting, ending)),
+            }
+        }
+        self.year_spec = merged
+            .into_iter()
+            .map(|(starting, ending)| match starting == ending {
+                true => DateTimePart::Single(starting),
+                false => DateTimePart::Range { starting, ending },
+            })
+            .chain(steps)
+            .collect();
+
+        // A date on its own already matches at every time of that date
+        let dates = self.date_spec.clone();
+        self.date_spec.retain(|on| {
+            on.time.is_none()
+                || !dates
+                    .iter()
+                    .any(|other| other.date == on.date && other.time.is_none())
+        });
+        self.date_spec.sort();
+        self.date_spec.dedup();
+
+        for exception in &mut self.exceptions {
+            exception.normalize_as(true);
+        }
+        self.exceptions
+            .sort_by_cached_key(|exception| exception.to_string());
+        self.exceptions.dedup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use scheda_parser::Parser;
+
+    fn normalized(schedule: &str) -> String {
+        let mut schedule = Parser::new(schedule).parse_schedule().unwrap();
+        schedule.normalize();
+
+        schedule.to_string()
+    }
+
+    #[test]
+    fn merges_and_sorts_parts() {
+        assert_eq!(
+            normalized("when hour 6 or 2 to 5 or 1 to 3"),
+            "when hour 1 to 6"
+        );
+        assert_eq!(
+            normalized("when weekday sat or mon or sun, hour 0 to 2 or 23"),
+            "when weekday sat to mon, hour 23 to 2"
+        );
+        assert_eq!(
+            normalized("when minute 45 or 15 or 0 or 30"),
+            "when minute every 15"
+        );
+        assert_eq!(
+            normalized("when day every 10 from 5 or 25"),
+            "when day every 10 from 5"
+        );
+        assert_eq!(
+            normalized("when at 18:00 or 9:30 or 18:00, weekday last fri or fri or second tue"),
+            "when weekday fri or second tue, at 9:30 or 18:00"
+        );
+    }
+
+    #[test]
+    fn drops_clauses_matching_everything() {
+        assert_eq!(
+            normalized("when month jan to dec, weekday mon to sun"),
+            "when"
+        );
+        assert_eq!(normalized("when day 1 to 31 or last"), "when");
+        assert_eq!(
+            normalized("when day 1, weekday sun to sat, combine or"),
+            "when"
+        );
+        assert_eq!(
+            normalized("when day 1, weekday sun to sat or first mon"),
+            "when day 1"
+        );
+        assert_eq!(normalized("when day 1, combine or"), "when day 1");
+    }
+
+    #[test]
+    fn keeps_every_second_but_drops_the_first_one() {
+        assert_eq!(
+            normalized("when second 0 to 29 or 30 to 59"),
+            "when second 0 to 59"
+        );
+        assert_eq!(normalized("when hour 9, second 0"), "when hour 9");
+        assert_eq!(
+            normalized("when second 30 or 0 or 10 or 20 or 50 or 40"),
+            "when second every 10"
+        );
+    }
+
+    #[test]
+    fn sorts_years_and_one_off_dates() {
+        assert_eq!(
+            normalized("when year 2030 or 2027 to 2028 or 2030"),
+            "when year 2027 to 2028 or 2030"
+        );
+        assert_eq!(
+            normalized("when year 2027 or 2028"),
+            normalized("when year 2027 to 2028")
+        );
+        assert_eq!(
+            normalized("when year 2031 or 2026 to 2029 or 2028 to 2030 or every 4 from 2024"),
+            "when year 2026 to 2031 or every 4 from 2024"
+        );
+        assert_eq!(
+            normalized("when on 2026-12-24 23:00 or 2025-01-01 or 2026-12-24 or 2025-01-01"),
+            "when on 2025-01-01 or 2026-12-24"
+        );
+    }
+
+    #[test]
+    fn normalizes_exceptions_in_order() {
+        assert_eq!(
+            normalized("when except hour 13 or 12, except month dec, except hour 12 to 13"),
+            "when except hour 12 to 13, except month dec"
+        );
+        // Unlike schedules, exceptions match every second by default
+        assert_eq!(
+            normalized("when second every 10, except (when hour 9, second 0 to 59)"),
+            "when second every 10, except hour 9"
+        );
+        assert_eq!(
+            normalized("when second every 10, except (when hour 9, second 0)"),
+            "when second every 10, except (when hour 9, second 0)"
+        );
+    }
+
+    #[test]
+    fn equal_schedules_compare_equal_once_normalized() {
+        let schedules: HashSet<_> = [
+            "when every 15 minutes",
+            "when minute 0 or 15 or 30 or 45",
+            "when minute 30 or 0 or 45 or 15 or 30, month jan to dec",
+        ]
+        .into_iter()
+        .map(|schedule| {
+            let mut schedule = Parser::new(schedule).parse_schedule().unwrap();
+            schedule.normalize();
+            schedule
+        })
+        .collect();
+
+        assert_eq!(schedules.len(), 1);
+    }
+
+    #[test]
+    fn schedules_are_ordered() {
+        let parse = |schedule| Parser::new(schedule).parse_schedule().unwrap();
+
+        let mut schedules = vec![
+            parse("when weekday sat"),
+            parse("when weekday sun in Europe/Paris"),
+            parse("when weekday sun"),
+            parse("when weekday second mon"),
+        ];
+        schedules.sort();
+
+        assert_eq!(
+            schedules,
+            [
+                parse("when weekday second mon"),
+                parse("when weekday sun"),
+                parse("when weekday sun in Europe/Paris"),
+                parse("when weekday sat"),
+            ]
+        );
+    }
+}
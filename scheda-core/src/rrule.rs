@@ -0,0 +1,805 @@
+//! Converts schedules to and from iCalendar recurrence rules (RFC 5545), such
+//! as `FREQ=WEEKLY;BYDAY=MO,WE;BYHOUR=9;BYMINUTE=30`.
+//!
+//! Recurrence rules are anchored to a `DTSTART`, which schedules don't have.
+//! Whatever a rule would take from it is taken from midnight on Monday, the
+//! first of January.
+//!
+//! `INTERVAL` keeps counting from `DTSTART` across minutes, hours, days and
+//! years, whereas `every` starts over with each of them. The two only agree
+//! when the interval evenly divides the enclosing period, e.g. 15 seconds or 3
+//! months, so any other interval is rejected, as are daily ones, months not
+//! all being as long.
+
+use std::num::NonZeroU8;
+
+use chrono::{Month, Weekday};
+use num_traits::FromPrimitive;
+
+use crate::{
+    schedule::contains_element, DateTimePart, Diagnostic, DstPolicy, Error, Hour, Minute, MonthDay,
+    MonthDayFromEnd, NthWeekday, Ordinal, Result, Schedule, Second, Span, WellFormedRange,
+};
+
+/// How often a rule repeats, from its `FREQ` part
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Frequency {
+    Secondly,
+    Minutely,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_uppercase().as_str() {
+            "SECONDLY" => Some(Self::Secondly),
+            "MINUTELY" => Some(Self::Minutely),
+            "HOURLY" => Some(Self::Hourly),
+            "DAILY" => Some(Self::Daily),
+            "WEEKLY" => Some(Self::Weekly),
+            "MONTHLY" => Some(Self::Monthly),
+            "YEARLY" => Some(Self::Yearly),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Secondly => "SECONDLY",
+            Self::Minutely => "MINUTELY",
+            Self::Hourly => "HOURLY",
+            Self::Daily => "DAILY",
+            Self::Weekly => "WEEKLY",
+            Self::Monthly => "MONTHLY",
+            Self::Yearly => "YEARLY",
+        }
+    }
+}
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+/// Splits `text`, which starts at `start` in the rule, keeping track of where each piece is
+fn split_spanned(text: &str, start: usize, separator: char) -> Vec<(&str, Span)> {
+    let mut pieces = Vec::new();
+    let mut offset = start;
+
+    for piece in text.split(separator) {
+        pieces.push((piece, offset..offset + piece.len()));
+        offset += piece.len() + separator.len_utf8();
+    }
+
+    pieces
+}
+
+fn invalid_value(expected: &'static str, found: &str, span: Span) -> Diagnostic {
+    Error::InvalidValue {
+        expected: expected.into(),
+        found: found.into(),
+    }
+    .at(span)
+}
+
+/// Parses every value of a comma-separated list with `parse`
+fn parse_list<T>(
+    value: &str,
+    start: usize,
+    expected: &'static str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> std::result::Result<Vec<T>, Diagnostic> {
+    split_spanned(value, start, ',')
+        .into_iter()
+        .map(|(item, span)| parse(item).ok_or_else(|| invalid_value(expected, item, span)))
+        .collect()
+}
+
+fn parse_in_range(value: &str, min: i8, max: i8) -> Option<i8> {
+    value
+        .strip_prefix('+')
+        .unwrap_or(value)
+        .parse()
+        .ok()
+        .filter(|value| (min..=max).contains(value))
+}
+
+/// A single `BYDAY` value, such as `MO`, `2TU` or `-1FR`, found at `span`
+fn parse_weekday(
+    value: &str,
+    span: Span,
+) -> std::result::Result<(Option<Ordinal>, Weekday), Diagnostic> {
+    let invalid = || {
+        invalid_value(
+            "a weekday, such as `MO`, `2TU` or `-1FR`",
+            value,
+            span.clone(),
+        )
+    };
+
+    let split = value.len().checked_sub(2).ok_or_else(invalid)?;
+    if !value.is_char_boundary(split) {
+        return Err(invalid());
+    }
+    let (nth, name) = value.split_at(split);
+
+    let (_, weekday) = WEEKDAYS
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(name))
+        .ok_or_else(invalid)?;
+
+    let ordinal = match nth {
+        "" => None,
+        nth => Some(match parse_in_range(nth, -5, 5).ok_or_else(invalid)? {
+            1 => Ordinal::First,
+            2 => Ordinal::Second,
+            3 => Ordinal::Third,
+            4 => Ordinal::Fourth,
+            5 => Ordinal::Fifth,
+            -1 => Ordinal::Last,
+            0 => return Err(invalid()),
+            _ => {
+                return Err(Error::Unsupported(
+                    "weekdays can only be counted back from the end of the month as `-1`, \
+                     the last one"
+                        .into(),
+                )
+                .at(span))
+            }
+        }),
+    };
+
+    Ok((ordinal, *weekday))
+}
+
+/// Writes the numbers `contains` holds for in `values`, e.g. `1,15,-1`
+fn rrule_list(values: impl IntoIterator<Item = i8>, contains: impl Fn(i8) -> bool) -> String {
+    let values: Vec<_> = values
+        .into_iter()
+        .filter(|&value| contains(value))
+        .map(|value| value.to_string())
+        .collect();
+
+    values.join(",")
+}
+
+impl Schedule {
+    /// Parses an iCalendar recurrence rule, with or without its `RRULE:` prefix.
+    ///
+    /// `FREQ`, `INTERVAL`, `BYMONTH`, `BYMONTHDAY`, `BYDAY`, `BYHOUR`,
+    /// `BYMINUTE` and `BYSECOND` are understood. Parts that bound the rule, such as `COUNT`
+    /// and `UNTIL`, or that a [`Schedule`] can't represent, such as `BYSETPOS`,
+    /// are reported as [`Error::Unsupported`].
+    ///
+    /// Every part is checked, and the returned [`Error::Parse`] lists every
+    /// error found.
+    pub fn from_rrule(rule: &str) -> Result<Schedule> {
+        let prefix = rule
+            .get(..6)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("RRULE:"));
+        let start = prefix.map_or(0, str::len);
+
+        let mut schedule = Schedule::new();
+        let mut diagnostics = Vec::new();
+        let mut frequency = None;
+        let mut has_frequency = false;
+        let mut interval: Option<(NonZeroU8, Span)> = None;
+        let mut has_days = false;
+        let mut has_ordinals = false;
+        let mut has_seconds = false;
+        let mut seen = Vec::new();
+
+        for (part, span) in split_spanned(&rule[start..], start, ';') {
+            if part.is_empty() {
+                continue;
+            }
+
+            let Some((name, value)) = part.split_once('=') else {
+                diagnostics.push(
+                    Error::InvalidSyntax("expected `NAME=VALUE`, such as `FREQ=DAILY`".into())
+                        .at(span),
+                );
+                continue;
+            };
+            let value_start = span.start + name.len() + 1;
+            has_frequency |= name.eq_ignore_ascii_case("FREQ");
+            let value_span = value_start..span.end;
+
+            let name_upper = name.to_ascii_uppercase();
+            if seen.contains(&name_upper) {
+                diagnostics.push(
+                    Error::InvalidSyntax(format!("`{name_upper}` can only be given once").into())
+                        .at(span),
+                );
+                continue;
+            }
+            seen.push(name_upper.clone());
+
+            let parsed = match name_upper.as_str() {
+                "FREQ" => match Frequency::parse(value) {
+                    Some(parsed) => {
+                        frequency = Some(parsed);
+                        Ok(())
+                    }
+                    None => Err(invalid_value(
+                        "a frequency, such as `DAILY` or `WEEKLY`",
+                        value,
+                        value_span,
+                    )),
+                },
+                "INTERVAL" => match value.parse().ok().and_then(NonZeroU8::new) {
+                    Some(parsed) => {
+                        interval = Some((parsed, value_span));
+                        Ok(())
+                    }
+                    None => Err(invalid_value("an interval, such as `2`", value, value_span)),
+                },
+                "BYMONTH" => parse_list(value, value_start, "a month between 1 and 12", |month| {
+                    Month::from_i8(parse_in_range(month, 1, 12)?)
+                })
+                .map(|months| {
+                    let months = months.into_iter().map(DateTimePart::Single);
+                    schedule.month_spec.extend(months);
+                }),
+                "BYMONTHDAY" => parse_list(
+                    value,
+                    value_start,
+                    "a day of the month between -31 and 31",
+                    |day| parse_in_range(day, -31, 31).filter(|day| *day != 0),
+                )
+                .map(|days| {
+                    has_days = true;
+
+                    for day in days {
+                        match day.is_positive() {
+                            true => schedule.day_spec.push(DateTimePart::Single(
+                                MonthDay::from_u8(day.unsigned_abs()).unwrap(),
+                            )),
+                            false => schedule.month_end_spec.push(DateTimePart::Single(
+                                MonthDayFromEnd::from_u8(day.unsigned_abs()).unwrap(),
+                            )),
+                        }
+                    }
+                }),
+                "BYDAY" => split_spanned(value, value_start, ',')
+                    .into_iter()
+                    .map(|(item, span)| parse_weekday(item, span))
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map(|weekdays| {
+                        has_days = true;
+
+                        for (ordinal, weekday) in weekdays {
+                            match ordinal {
+                                Some(ordinal) => {
+                                    has_ordinals = true;
+                                    schedule
+                                        .nth_weekday_spec
+                                        .push(NthWeekday { ordinal, weekday });
+                                }
+                                None => schedule.weekday_spec.push(DateTimePart::Single(weekday)),
+                            }
+                        }
+                    }),
+                "BYHOUR" => parse_list(value, value_start, "an hour between 0 and 23", |hour| {
+                    Hour::new(parse_in_range(hour, 0, 23)? as u8)
+                })
+                .map(|hours| {
+                    let hours = hours.into_iter().map(DateTimePart::Single);
+                    schedule.hour_spec.extend(hours);
+                }),
+                "BYMINUTE" => {
+                    parse_list(value, value_start, "a minute between 0 and 59", |minute| {
+                        Minute::new(parse_in_range(minute, 0, 59)? as u8)
+                    })
+                    .map(|minutes| {
+                        let minutes = minutes.into_iter().map(DateTimePart::Single);
+                        schedule.minute_spec.extend(minutes);
+                    })
+                }
+                "BYSECOND" => {
+                    parse_list(value, value_start, "a second between 0 and 59", |second| {
+                        Second::new(parse_in_range(second, 0, 59)? as u8)
+                    })
+                    .map(|seconds| {
+                        has_seconds = true;
+
+                        let seconds = seconds.into_iter().map(DateTimePart::Single);
+                        schedule.second_spec.extend(seconds);
+                    })
+                }
+                // Only changes where weeks start for weekly intervals, which aren't supported
+                "WKST" => Ok(()),
+                "COUNT" | "UNTIL" => Err(Error::Unsupported(
+                    "schedules repeat forever, so they can't stop after a count or a date".into(),
+                )
+                .at(span)),
+                "BYSETPOS" | "BYWEEKNO" | "BYYEARDAY" => Err(Error::Unsupported(
+                    "only `BYMONTH`, `BYMONTHDAY`, `BYDAY`, `BYHOUR`, `BYMINUTE` and `BYSECOND` \
+                     are supported"
+                        .into(),
+                )
+                .at(span)),
+                _ => Err(invalid_value(
+                    "a rule part, such as `FREQ` or `BYDAY`",
+                    name,
+                    span.start..span.start + name.len(),
+                )),
+            };
+
+            if let Err(diagnostic) = parsed {
+                diagnostics.push(diagnostic);
+            }
+        }
+
+        let Some(frequency) = frequency else {
+            if !has_frequency {
+                diagnostics.push(
+                    Error::InvalidSyntax("expected a `FREQ` part, such as `FREQ=DAILY`".into())
+                        .at(start..rule.len()),
+                );
+            }
+            diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+            return Err(Error::Parse(diagnostics));
+        };
+
+        if has_ordinals && frequency < Frequency::Monthly {
+            diagnostics.push(
+                Error::InvalidSyntax(
+                    "weekdays with ordinals, such as `2TU`, need a monthly or yearly `FREQ`".into(),
+                )
+                .at(start..rule.len()),
+            );
+        }
+        if has_ordinals && frequency == Frequency::Yearly && schedule.month_spec.is_empty() {
+            diagnostics.push(
+                Error::Unsupported(
+                    "weekdays with ordinals can only count within the month, so `BYMONTH` is needed"
+                        .into(),
+                )
+                .at(start..rule.len()),
+            );
+        }
+
+        if let Some((interval, span)) = interval.filter(|(interval, _)| interval.get() > 1) {
+            if let Err(diagnostic) = schedule.step_by_interval(frequency, interval, span) {
+                diagnostics.push(diagnostic);
+            }
+        }
+
+        if !diagnostics.is_empty() {
+            diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+            return Err(Error::Parse(diagnostics));
+        }
+
+        if frequency == Frequency::Secondly && !has_seconds && schedule.second_spec.is_empty() {
+            schedule.second_spec.push(DateTimePart::Range {
+                starting: Second::MIN,
+                ending: Second::MAX,
+            });
+        }
+        // Schedules without a `second` clause already fire on the first one
+        if schedule.second_spec.as_slice() == [DateTimePart::Single(Second::MIN)] {
+            schedule.second_spec.clear();
+        }
+
+        // What a rule would otherwise take from its `DTSTART`
+        if frequency >= Frequency::Hourly && schedule.minute_spec.is_empty() {
+            schedule
+                .minute_spec
+                .push(DateTimePart::Single(Minute::FIRST));
+        }
+        if frequency >= Frequency::Daily && schedule.hour_spec.is_empty() {
+            schedule.hour_spec.push(DateTimePart::Single(Hour::FIRST));
+        }
+        if frequency == Frequency::Weekly && !has_days {
+            schedule
+                .weekday_spec
+                .push(DateTimePart::Single(Weekday::Mon));
+        }
+        if frequency >= Frequency::Monthly && !has_days {
+            schedule
+                .day_spec
+                .push(DateTimePart::Single(MonthDay::FIRST));
+        }
+        if frequency == Frequency::Yearly && !has_days && schedule.month_spec.is_empty() {
+            schedule.month_spec.push(DateTimePart::Single(Month::FIRST));
+        }
+
+        Ok(schedule)
+    }
+
+    /// Turns an `INTERVAL` into a step through the field `frequency` repeats on
+    fn step_by_interval(
+        &mut self,
+        frequency: Frequency,
+        interval: NonZeroU8,
+        span: Span,
+    ) -> std::result::Result<(), Diagnostic> {
+        // Steps start over every period, so only intervals dividing it evenly
+        // keep counting the way `DTSTART` does
+        let step = |stepped_is_empty: bool, period: u8| {
+            if !stepped_is_empty {
+                return Err(Error::Unsupported(
+                    "`INTERVAL` can't be combined with a `BY` part on the field it steps through"
+                        .into(),
+                )
+                .at(span.clone()));
+            }
+            if interval.get() >= period || period % interval.get() != 0 {
+                return Err(Error::Unsupported(
+                    format!("intervals must evenly divide the {period} values they step through")
+                        .into(),
+                )
+                .at(span.clone()));
+            }
+
+            Ok(interval)
+        };
+
+        match frequency {
+            Frequency::Secondly => {
+                let step = step(self.second_spec.is_empty(), 60)?;
+                self.second_spec.push(DateTimePart::Step {
+                    starting: Second::FIRST,
+                    step,
+                });
+            }
+            Frequency::Minutely => {
+                let step = step(self.minute_spec.is_empty(), 60)?;
+                self.minute_spec.push(DateTimePart::Step {
+                    starting: Minute::FIRST,
+                    step,
+                });
+            }
+            Frequency::Hourly => {
+                let step = step(self.hour_spec.is_empty(), 24)?;
+                self.hour_spec.push(DateTimePart::Step {
+                    starting: Hour::FIRST,
+                    step,
+                });
+            }
+            Frequency::Monthly => {
+                let step = step(self.month_spec.is_empty(), 12)?;
+                self.month_spec.push(DateTimePart::Step {
+                    starting: Month::FIRST,
+                    step,
+                });
+            }
+            Frequency::Daily => {
+                return Err(Error::Unsupported(
+                    "months have different numbers of days, which daily intervals can't divide"
+                        .into(),
+                )
+                .at(span))
+            }
+            Frequency::Weekly | Frequency::Yearly => {
+                return Err(Error::Unsupported(
+                    "schedules have no weeks or years to step through".into(),
+                )
+                .at(span))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes this schedule as an iCalendar recurrence rule, without the
+    /// `RRULE:` prefix, e.g. `FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR;BYHOUR=9;BYMINUTE=0`.
+    ///
+    /// Every value is listed out, so nothing depends on the `DTSTART` the rule
+    /// is paired with, other than its time zone, which should be this
+    /// schedule's own.
+    ///
+    /// Fails with [`Error::Unsupported`] if a recurrence rule has no way of
+    /// saying the same thing, e.g. for schedules firing on either a day or a weekday.
+    pub fn to_rrule(&self) -> Result<String> {
+        if self.dst_policy != DstPolicy::default() {
+            return Err(Error::Unsupported(
+                "recurrence rules have no way of changing how DST transitions are handled".into(),
+            ));
+        }
+        if self.either_day() {
+            return Err(Error::Unsupported(
+                "recurrence rules only fire when both the day and the weekday match".into(),
+            ));
+        }
+        if !self.year_spec.is_empty() || !self.date_spec.is_empty() {
+            return Err(Error::Unsupported(
+                "recurrence rules have no years or one-off dates".into(),
+            ));
+        }
+        if !self.exceptions.is_empty() {
+            return Err(Error::Unsupported(
+                "recurrence rules have no exceptions, short of the deprecated `EXRULE`".into(),
+            ));
+        }
+
+        let (hours, minutes): (Vec<u8>, Vec<u8>) = if self.time_spec.is_empty() {
+            (
+                (0..24)
+                    .filter(|&hour| self.contains_hour(Hour::new(hour).unwrap()))
+                    .collect(),
+                (0..60)
+                    .filter(|&minute| self.contains_minute(Minute::new(minute).unwrap()))
+                    .collect(),
+            )
+        } else {
+            let (hours, minutes) = self.times_as_grid().ok_or_else(|| {
+                Error::Unsupported(
+                    "recurrence rules can only fire on the same minutes of every hour they fire on"
+                        .into(),
+                )
+            })?;
+
+            (hours.to_vec(), minutes)
+        };
+
+        let seconds: Vec<u8> = (0..60)
+            .filter(|&second| self.contains_second(Second::new(second).unwrap()))
+            .collect();
+
+        // The coarsest frequency whose finer fields are all spelled out
+        let frequency = if !self.nth_weekday_spec.is_empty() {
+            Frequency::Monthly
+        } else if seconds.len() == 60 {
+            Frequency::Secondly
+        } else if minutes.len() == 60 {
+            Frequency::Minutely
+        } else if hours.len() == 24 {
+            Frequency::Hourly
+        } else {
+            Frequency::Daily
+        };
+
+        let mut rule = format!("FREQ={}", frequency.name());
+
+        if !self.month_spec.is_empty() {
+            rule += ";BYMONTH=";
+            rule += &rrule_list(1..=12, |month| {
+                contains_element(&self.month_spec, &Month::from_i8(month).unwrap())
+            });
+        }
+
+        if self.restricts_days() {
+            rule += ";BYMONTHDAY=";
+            rule += &rrule_list((1..=31).chain((-31..=-1).rev()), |day| {
+                match day.is_positive() {
+                    true => (self.day_spec.iter())
+                        .any(|part| part.contains(&MonthDay::from_u8(day as u8).unwrap())),
+                    false => (self.month_end_spec.iter()).any(|part| {
+                        part.contains(&MonthDayFromEnd::from_u8(day.unsigned_abs()).unwrap())
+                    }),
+                }
+            });
+        }
+
+        if self.restricts_weekdays() {
+            let mut weekdays: Vec<_> = WEEKDAYS
+                .iter()
+                .filter(|(_, weekday)| self.weekday_spec.iter().any(|part| part.contains(weekday)))
+                .map(|(code, _)| code.to_string())
+                .collect();
+
+            weekdays.extend(self.nth_weekday_spec.iter().map(|nth_weekday| {
+                let nth = match nth_weekday.ordinal {
+                    Ordinal::First => 1,
+                    Ordinal::Second => 2,
+                    Ordinal::Third => 3,
+                    Ordinal::Fourth => 4,
+                    Ordinal::Fifth => 5,
+                    Ordinal::Last => -1,
+                };
+                let (code, _) = WEEKDAYS
+                    .iter()
+                    .find(|(_, weekday)| *weekday == nth_weekday.weekday)
+                    .unwrap();
+
+                format!("{nth}{code}")
+            }));
+
+            rule += ";BYDAY=";
+            rule += &weekdays.join(",");
+        }
+
+        // Whatever isn't listed would be taken from `DTSTART`
+        if frequency >= Frequency::Daily || hours.len() < 24 {
+            rule += ";BYHOUR=";
+            rule += &rrule_list(0..24, |hour| hours.contains(&(hour as u8)));
+        }
+        if frequency >= Frequency::Hourly || minutes.len() < 60 {
+            rule += ";BYMINUTE=";
+            rule += &rrule_list(0..60, |minute| minutes.contains(&(minute as u8)));
+        }
+        // Leaving `BYSECOND` out takes the first second from a `DTSTART` at midnight
+        if frequency >= Frequency::Minutely && seconds != [0] {
+            rule += ";BYSECOND=";
+            rule += &rrule_list(0..60, |second| seconds.contains(&(second as u8)));
+        }
+
+        Ok(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scheda_parser::Parser;
+
+    use crate::{Error, Schedule};
+
+    fn parse_errors(rule: &str) -> Vec<(&'static str, &str)> {
+        match Schedule::from_rrule(rule) {
+            Err(Error::Parse(diagnostics)) => diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.code(), &rule[diagnostic.span.clone()]))
+                .collect(),
+            other => panic!("expected parse errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_recurrence_rules() {
+        for (rule, schedule) in [
+            ("FREQ=DAILY;BYHOUR=9;BYMINUTE=30", "when hour 9, minute 30"),
+            (
+                "RRULE:FREQ=HOURLY;INTERVAL=3",
+                "when hour every 3, minute 0",
+            ),
+            (
+                "FREQ=WEEKLY;BYDAY=TU,TH",
+                "when weekday tue or thu, hour 0, minute 0",
+            ),
+            ("FREQ=WEEKLY", "when weekday mon, hour 0, minute 0"),
+            (
+                "FREQ=MONTHLY;BYDAY=-1FR,2TU;BYHOUR=18",
+                "when weekday last fri or second tue, hour 18, minute 0",
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=1,-1",
+                "when day 1 or last, hour 0, minute 0",
+            ),
+            (
+                "FREQ=MONTHLY;INTERVAL=3;BYMONTHDAY=15",
+                "when month every 3, day 15, hour 0, minute 0",
+            ),
+            ("FREQ=YEARLY", "when month jan, day 1, hour 0, minute 0"),
+            (
+                "FREQ=YEARLY;BYMONTH=1;BYDAY=SU,MO,TU,WE,TH,FR,SA",
+                "when month jan, weekday sun or mon or tue or wed or thu or fri or sat, \
+                 hour 0, minute 0",
+            ),
+            // Every 20 minutes from 9:00 to 16:40, as in RFC 5545
+            (
+                "FREQ=MINUTELY;INTERVAL=20;BYHOUR=9,10,11,12,13,14,15,16",
+                "when hour 9 or 10 or 11 or 12 or 13 or 14 or 15 or 16, minute every 20",
+            ),
+            ("FREQ=SECONDLY;INTERVAL=15", "when second every 15"),
+            ("FREQ=SECONDLY;BYMINUTE=0", "when minute 0, second 0 to 59"),
+            ("FREQ=MINUTELY;BYSECOND=0,30", "when second 0 or 30"),
+            ("FREQ=DAILY;BYSECOND=0", "when hour 0, minute 0"),
+        ] {
+            assert_eq!(Schedule::from_rrule(rule).unwrap().to_string(), schedule);
+        }
+    }
+
+    #[test]
+    fn reports_unsupported_and_invalid_rules() {
+        assert_eq!(
+            parse_errors("FREQ=DAILY;COUNT=10;BYSETPOS=-1"),
+            [("unsupported", "COUNT=10"), ("unsupported", "BYSETPOS=-1")]
+        );
+        assert_eq!(
+            parse_errors("FREQ=WEEKLY;INTERVAL=2"),
+            [("unsupported", "2")]
+        );
+        assert_eq!(
+            parse_errors("FREQ=HOURLY;INTERVAL=2;BYHOUR=9,10"),
+            [("unsupported", "2")]
+        );
+        // Intervals keep counting across periods they don't divide evenly
+        assert_eq!(
+            parse_errors("FREQ=DAILY;INTERVAL=3"),
+            [("unsupported", "3")]
+        );
+        assert_eq!(
+            parse_errors("FREQ=HOURLY;INTERVAL=7"),
+            [("unsupported", "7")]
+        );
+        assert_eq!(
+            parse_errors("FREQ=MINUTELY;INTERVAL=60"),
+            [("unsupported", "60")]
+        );
+        assert_eq!(
+            parse_errors("FREQ=DAILY;BYHOUR=9;FREQ=WEEKLY;byhour=10"),
+            [
+                ("invalid-syntax", "FREQ=WEEKLY"),
+                ("invalid-syntax", "byhour=10")
+            ]
+        );
+        assert_eq!(
+            parse_errors("FREQ=FORTNIGHTLY;BYHOUR=9,24;BYDAY=XX"),
+            [
+                ("invalid-value", "FORTNIGHTLY"),
+                ("invalid-value", "24"),
+                ("invalid-value", "XX")
+            ]
+        );
+        assert_eq!(parse_errors("BYHOUR=9"), [("invalid-syntax", "BYHOUR=9")]);
+        // Only the last weekday of the month can be counted from its end
+        assert_eq!(
+            parse_errors("FREQ=MONTHLY;BYDAY=-1FR,-2FR"),
+            [("unsupported", "-2FR")]
+        );
+        for weekday in ["0MO", "-6TU", "É"] {
+            assert_eq!(
+                parse_errors(&format!("FREQ=MONTHLY;BYDAY={weekday}")),
+                [("invalid-value", weekday)]
+            );
+        }
+        assert_eq!(
+            parse_errors("FREQ=DAILY;BYDAY=2TU"),
+            [("invalid-syntax", "FREQ=DAILY;BYDAY=2TU")]
+        );
+    }
+
+    fn to_rrule(schedule: &str) -> Result<String, &'static str> {
+        Parser::new(schedule)
+            .parse_schedule()
+            .unwrap()
+            .to_rrule()
+            .map_err(|err| err.code())
+    }
+
+    #[test]
+    fn writes_recurrence_rules() {
+        assert_eq!(to_rrule("when").unwrap(), "FREQ=MINUTELY");
+        assert_eq!(
+            to_rrule("when every 15 minutes").unwrap(),
+            "FREQ=HOURLY;BYMINUTE=0,15,30,45"
+        );
+        assert_eq!(
+            to_rrule("when hour 9, minute 0, weekday mon to fri").unwrap(),
+            "FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR;BYHOUR=9;BYMINUTE=0"
+        );
+        assert_eq!(
+            to_rrule("when weekday second tue, at 18:30").unwrap(),
+            "FREQ=MONTHLY;BYDAY=2TU;BYHOUR=18;BYMINUTE=30"
+        );
+        assert_eq!(
+            to_rrule("when month dec, day 1 or last, at 0:00").unwrap(),
+            "FREQ=DAILY;BYMONTH=12;BYMONTHDAY=1,-1;BYHOUR=0;BYMINUTE=0"
+        );
+        assert_eq!(
+            to_rrule("when hour 9 to 10").unwrap(),
+            "FREQ=MINUTELY;BYHOUR=9,10"
+        );
+        assert_eq!(
+            to_rrule("when every 20 seconds").unwrap(),
+            "FREQ=MINUTELY;BYSECOND=0,20,40"
+        );
+        assert_eq!(
+            to_rrule("when hour 9, second 0 to 59").unwrap(),
+            "FREQ=SECONDLY;BYHOUR=9"
+        );
+
+        assert_eq!(
+            to_rrule("when day 1, weekday mon, combine or"),
+            Err("unsupported")
+        );
+        assert_eq!(to_rrule("when at 9:15 or 10:30"), Err("unsupported"));
+        assert_eq!(to_rrule("when year 2027"), Err("unsupported"));
+        assert_eq!(to_rrule("when on 2026-12-24"), Err("unsupported"));
+        assert_eq!(
+            to_rrule("when hour 9, except month dec"),
+            Err("unsupported")
+        );
+    }
+}
@@ -1,24 +1,64 @@
-use std::fmt::Debug;
+use std::{cmp::Ordering, fmt::Debug};
 
-use chrono::{DateTime, Datelike, Month, NaiveDateTime, TimeZone, Timelike, Weekday};
+use chrono::{
+    DateTime, Datelike, Duration, Month, NaiveDate, NaiveDateTime, TimeZone, Timelike, Weekday,
+};
+use chrono_tz::Tz;
 use num_traits::FromPrimitive;
 use smallvec::SmallVec;
 
-use crate::{AsU8, DateTimePart, Hour, Minute, MonthDay};
+use crate::{
+    dst::{self, DstPolicy, GapPolicy, OverlapPolicy},
+    AbsoluteDate, AsU8, DateTimePart, DayCombination, Hour, Minute, MonthDay, MonthDayFromEnd,
+    NthWeekday, Second, TimeOfDay, Year,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Schedule {
     /// The specification for month days
     pub hour_spec: SmallVec<[DateTimePart<Hour>; 2]>,
     /// The specification for month days
     pub minute_spec: SmallVec<[DateTimePart<Minute>; 2]>,
+    /// The specification for seconds.
+    ///
+    /// Unlike the other specs, an empty one doesn't match every second, but
+    /// only the first one of each minute.
+    pub second_spec: SmallVec<[DateTimePart<Second>; 2]>,
     /// The specification for month days
     pub day_spec: SmallVec<[DateTimePart<MonthDay>; 4]>,
+    /// The specification for month days counted back from the end of the month
+    pub month_end_spec: SmallVec<[DateTimePart<MonthDayFromEnd>; 2]>,
     /// The specification for months
     pub month_spec: SmallVec<[DateTimePart<Month>; 4]>,
+    /// The specification for years
+    pub year_spec: SmallVec<[DateTimePart<Year>; 2]>,
     /// The specification for months
     pub weekday_spec: SmallVec<[DateTimePart<Weekday>; 4]>,
-    // TODO: add timezone
+    /// Weekdays that only match on one of their occurrences within the month
+    pub nth_weekday_spec: SmallVec<[NthWeekday; 2]>,
+    /// Exact times of day, on top of the hour and minute specs
+    pub time_spec: SmallVec<[TimeOfDay; 4]>,
+    /// One-off dates, on top of every other spec, which the schedule stops
+    /// firing after
+    pub date_spec: SmallVec<[AbsoluteDate; 2]>,
+    /// Schedules whose matches are taken out of this one's, e.g. `except month dec`.
+    ///
+    /// They're matched in this schedule's time zone, and one without a
+    /// `second` clause rules out every second of the minutes it matches.
+    pub exceptions: Vec<Schedule>,
+    /// The time zone date-times are converted to before being matched.
+    ///
+    /// If `None`, date-times are matched in whatever time zone they come in.
+    pub timezone: Option<Tz>,
+    /// Whether the day and weekday specs both have to match, or either of them
+    pub day_combination: DayCombination,
+    /// How local times skipped or repeated by daylight saving time transitions are handled
+    pub dst_policy: DstPolicy,
 }
 
 impl Default for Schedule {
@@ -27,14 +67,75 @@ impl Default for Schedule {
     }
 }
 
+/// [`Weekday`] and [`Tz`] aren't ordered, so weekdays are compared as counted
+/// by [`AsU8`], from Sunday, and time zones by name
+impl Ord for Schedule {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let weekdays = |schedule: &Self| {
+            schedule
+                .weekday_spec
+                .iter()
+                .map(|part| part.map(|weekday| weekday.as_u8()))
+                .collect::<SmallVec<[DateTimePart<u8>; 4]>>()
+        };
+
+        self.hour_spec
+            .cmp(&other.hour_spec)
+            .then_with(|| self.minute_spec.cmp(&other.minute_spec))
+            .then_with(|| self.second_spec.cmp(&other.second_spec))
+            .then_with(|| self.day_spec.cmp(&other.day_spec))
+            .then_with(|| self.month_end_spec.cmp(&other.month_end_spec))
+            .then_with(|| self.month_spec.cmp(&other.month_spec))
+            .then_with(|| self.year_spec.cmp(&other.year_spec))
+            .then_with(|| weekdays(self).cmp(&weekdays(other)))
+            .then_with(|| self.nth_weekday_spec.cmp(&other.nth_weekday_spec))
+            .then_with(|| self.time_spec.cmp(&other.time_spec))
+            .then_with(|| self.date_spec.cmp(&other.date_spec))
+            .then_with(|| self.exceptions.cmp(&other.exceptions))
+            .then_with(|| {
+                let name = |schedule: &Self| schedule.timezone.map(|timezone| timezone.name());
+                name(self).cmp(&name(other))
+            })
+            .then_with(|| self.day_combination.cmp(&other.day_combination))
+            .then_with(|| self.dst_policy.cmp(&other.dst_policy))
+    }
+}
+
+impl PartialOrd for Schedule {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 #[inline]
-fn contains_element<'a, T: AsU8 + Debug + 'a>(parts: &[DateTimePart<T>], element: &T) -> bool {
+pub(crate) fn contains_element<'a, T: AsU8 + Debug + 'a>(
+    parts: &[DateTimePart<T>],
+    element: &T,
+) -> bool {
     if parts.is_empty() {
         // Wild-card
         return true;
     }
 
-    parts.iter().any(|part| part.contains(&element))
+    parts.iter().any(|part| part.contains(element))
+}
+
+#[inline]
+pub(crate) fn contains_year(parts: &[DateTimePart<Year>], date: NaiveDate) -> bool {
+    // Wild-card
+    parts.is_empty()
+        || Year::of(date).is_some_and(|year| parts.iter().any(|part| part.contains(&year)))
+}
+
+#[inline]
+pub(crate) fn contains_date_time(
+    dates: &[AbsoluteDate],
+    date: NaiveDate,
+    hour: &Hour,
+    minute: &Minute,
+) -> bool {
+    // Wild-card
+    dates.is_empty() || dates.iter().any(|on| on.is(date, hour, minute))
 }
 
 pub struct DateTimePartExtractor<Tz: TimeZone> {
@@ -58,74 +159,287 @@ impl<Tz: TimeZone> DateTimePartExtractor<Tz> {
         Minute::new(self.date_time.time().minute() as u8).unwrap()
     }
 
-    pub fn month(&self) -> Month {
-        Month::from_u32(self.date_time.month()).unwrap()
+    pub fn second(&self) -> Second {
+        Second::new(self.date_time.time().second() as u8).unwrap()
     }
 
-    pub fn month_day(&self) -> MonthDay {
-        MonthDay::from_u8(self.date_time.day() as u8).unwrap()
+    pub fn month(&self) -> Month {
+        Month::from_u32(self.date_time.month()).unwrap()
     }
 
-    pub fn weekday(&self) -> Weekday {
-        self.date_time.weekday()
+    pub fn date(&self) -> NaiveDate {
+        self.date_time.date_naive()
     }
 }
 
 impl Schedule {
     pub fn new() -> Self {
         Self {
+            year_spec: SmallVec::new(),
             month_spec: SmallVec::new(),
             day_spec: SmallVec::new(),
+            month_end_spec: SmallVec::new(),
             weekday_spec: SmallVec::new(),
+            nth_weekday_spec: SmallVec::new(),
             hour_spec: SmallVec::new(),
             minute_spec: SmallVec::new(),
+            second_spec: SmallVec::new(),
+            time_spec: SmallVec::new(),
+            date_spec: SmallVec::new(),
+            exceptions: Vec::new(),
+            day_combination: DayCombination::default(),
+            timezone: None,
+            dst_policy: DstPolicy::default(),
         }
     }
 
-    fn contains_hour(&self, hour: Hour) -> bool {
+    pub(crate) fn contains_hour(&self, hour: Hour) -> bool {
         contains_element(self.hour_spec.as_slice(), &hour)
     }
 
-    fn contains_minute(&self, minute: Minute) -> bool {
+    pub(crate) fn contains_minute(&self, minute: Minute) -> bool {
         contains_element(self.minute_spec.as_slice(), &minute)
     }
 
-    fn contains_day(&self, day: MonthDay) -> bool {
-        contains_element(self.day_spec.as_slice(), &day)
+    /// Whether the schedule fires on `second` of the minutes it matches,
+    /// which is only the first one if there's no `second` clause
+    pub(crate) fn contains_second(&self, second: Second) -> bool {
+        if self.second_spec.is_empty() {
+            return second == Second::MIN;
+        }
+
+        self.second_spec.iter().any(|part| part.contains(&second))
+    }
+
+    pub(crate) fn contains_day(&self, day: MonthDay, from_end: MonthDayFromEnd) -> bool {
+        if self.month_end_spec.is_empty() {
+            return contains_element(self.day_spec.as_slice(), &day);
+        }
+
+        // Days counted from either end of the month add up, e.g. `day 1 or last`
+        self.day_spec.iter().any(|part| part.contains(&day))
+            || self
+                .month_end_spec
+                .iter()
+                .any(|part| part.contains(&from_end))
+    }
+
+    pub(crate) fn contains_year(&self, date: NaiveDate) -> bool {
+        contains_year(self.year_spec.as_slice(), date)
     }
 
-    fn contains_month(&self, month: Month) -> bool {
+    /// Whether the schedule may fire at some time of `date`, as far as its
+    /// one-off dates go
+    pub(crate) fn contains_date(&self, date: NaiveDate) -> bool {
+        // Wild-card
+        self.date_spec.is_empty() || self.date_spec.iter().any(|on| on.date == date)
+    }
+
+    pub(crate) fn contains_month(&self, month: Month) -> bool {
         contains_element(self.month_spec.as_slice(), &month)
     }
 
-    fn contains_weekday(&self, weekday: Weekday) -> bool {
-        contains_element(self.weekday_spec.as_slice(), &weekday)
+    pub(crate) fn contains_weekday(&self, date: NaiveDate) -> bool {
+        if self.nth_weekday_spec.is_empty() {
+            return contains_element(self.weekday_spec.as_slice(), &date.weekday());
+        }
+
+        // Like days of the month, plain and nth weekdays add up, e.g. `weekday sat or first mon`
+        self.weekday_spec
+            .iter()
+            .any(|part| part.contains(&date.weekday()))
+            || self
+                .nth_weekday_spec
+                .iter()
+                .any(|nth_weekday| nth_weekday.contains(date))
+    }
+
+    pub(crate) fn contains_time(&self, hour: &Hour, minute: &Minute) -> bool {
+        // Wild-card
+        self.time_spec.is_empty() || self.time_spec.iter().any(|time| time.is(hour, minute))
+    }
+
+    /// Whether the given date satisfies the day and weekday specs, requiring
+    /// only either of them if `either_day`, as [`Schedule::either_day`] says
+    pub(crate) fn matches_day(&self, date: NaiveDate, either_day: bool) -> bool {
+        let contains_day = self.contains_day(
+            MonthDay::from_u8(date.day() as u8).unwrap(),
+            MonthDayFromEnd::of(date),
+        );
+
+        if either_day {
+            contains_day || self.contains_weekday(date)
+        } else {
+            contains_day && self.contains_weekday(date)
+        }
+    }
+
+    /// Whether matching either the days or the weekdays is enough, which
+    /// [`DayCombination::Or`] only makes so when both are restricted.
+    ///
+    /// Matching takes this as an argument, so that searches work it out once
+    /// rather than for every date they go through.
+    pub(crate) fn either_day(&self) -> bool {
+        self.day_combination == DayCombination::Or
+            && self.restricts_days()
+            && self.restricts_weekdays()
+    }
+
+    pub(crate) fn restricts_days(&self) -> bool {
+        !self.day_spec.is_empty() || !self.month_end_spec.is_empty()
+    }
+
+    pub(crate) fn restricts_weekdays(&self) -> bool {
+        !self.weekday_spec.is_empty() || !self.nth_weekday_spec.is_empty()
+    }
+
+    /// Whether the given date satisfies the year, month, day, weekday and
+    /// one-off date specs
+    pub(crate) fn matches_date(&self, date: NaiveDate, either_day: bool) -> bool {
+        self.contains_year(date)
+            && self.contains_month(Month::from_u32(date.month()).unwrap())
+            && self.matches_day(date, either_day)
+            && self.contains_date(date)
     }
 
     pub fn matches<TZ: TimeZone>(&self, date_time: DateTime<TZ>) -> bool {
-        let extractor = DateTimePartExtractor::new(date_time);
+        // Kept out of line, as converting between time zones would otherwise
+        // slow down matching date-times already in the right one
+        if let Some(timezone) = self.timezone {
+            return self.matches_in(timezone, date_time.naive_utc());
+        }
+
+        self.matches_local(date_time)
+    }
+
+    #[inline(never)]
+    fn matches_in(&self, timezone: Tz, utc: NaiveDateTime) -> bool {
+        self.matches_local(timezone.from_utc_datetime(&utc))
+    }
+
+    /// Matches `date_time` in the time zone it's in, following the [`DstPolicy`]
+    fn matches_local<TZ: TimeZone>(&self, date_time: DateTime<TZ>) -> bool {
+        if self.matches_parts(DateTimePartExtractor::new(date_time.clone())) {
+            return self.dst_policy.overlap == OverlapPolicy::Twice || !dst::is_repeat(&date_time);
+        }
+
+        // Local times the clocks just skipped over fire right after instead
+        self.dst_policy.gap == GapPolicy::NextValid
+            && date_time.second() == 0
+            && dst::skipped_minutes_before(&date_time).is_some_and(|(first, last)| {
+                self.next_naive_after(first - Duration::seconds(1), last.year())
+                    .is_some_and(|skipped| skipped < last + Duration::minutes(1))
+            })
+    }
+
+    /// Whether any exception rules out every instant of `date` starting with
+    /// `time`, which holds up to an hour, a minute and a second
+    pub(crate) fn excludes(&self, date: NaiveDate, time: &[u32]) -> bool {
+        self.exceptions
+            .iter()
+            .any(|exception| exception.covers(date, time))
+    }
 
-        self.contains_month(extractor.month())
-            && self.contains_day(extractor.month_day())
+    /// Which exceptions apply on `date`, nested ones included.
+    ///
+    /// Every date with the same key has the same times of day matching, so
+    /// a date that turned out to have none rules out the others. One-off
+    /// dates can bring times of their own, so there's no key with any.
+    pub(crate) fn times_of_day_key(&self, date: NaiveDate) -> Option<Vec<bool>> {
+        if !self.date_spec.is_empty() {
+            return None;
+        }
+
+        let mut key = Vec::with_capacity(self.exceptions.len());
+        for exception in &self.exceptions {
+            key.push(exception.matches_date(date, exception.either_day()));
+            key.extend(exception.times_of_day_key(date)?);
+        }
+
+        Some(key)
+    }
+
+    /// Whether this schedule, as an exception, rules out every instant of
+    /// `date` starting with `time`.
+    ///
+    /// Clauses finer than `time` goes must match everything for that to be
+    /// sure, e.g. `except hour 12` covers all of 12h00 but `except minute 0`
+    /// only part of it.
+    fn covers(&self, date: NaiveDate, time: &[u32]) -> bool {
+        let hour = time.first().map(|&hour| Hour::new(hour as u8).unwrap());
+        let minute = time
+            .get(1)
+            .map(|&minute| Minute::new(minute as u8).unwrap());
+        let second = time
+            .get(2)
+            .map(|&second| Second::new(second as u8).unwrap());
+
+        let covers_hour = hour.map_or(self.hour_spec.is_empty(), |hour| self.contains_hour(hour));
+        let covers_minute = minute.map_or(self.minute_spec.is_empty(), |minute| {
+            self.contains_minute(minute)
+        });
+        let covers_time = match (&hour, &minute) {
+            (Some(hour), Some(minute)) => {
+                self.contains_time(hour, minute)
+                    && contains_date_time(&self.date_spec, date, hour, minute)
+            }
+            _ => self.time_spec.is_empty() && self.date_spec.iter().all(|on| on.time.is_none()),
+        };
+        // Unlike schedules, exceptions without a `second` clause match every second
+        let covers_second = self.second_spec.is_empty()
+            || second
+                .is_some_and(|second| self.second_spec.iter().any(|part| part.contains(&second)));
+        // Exceptions may have holes of their own, which only whole seconds can be checked against
+        let has_holes = match second {
+            Some(_) => self.excludes(date, time),
+            None => !self.exceptions.is_empty(),
+        };
+
+        self.matches_date(date, self.either_day())
+            && covers_hour
+            && covers_minute
+            && covers_time
+            && covers_second
+            && !has_holes
+    }
+
+    fn matches_parts<TZ: TimeZone>(&self, extractor: DateTimePartExtractor<TZ>) -> bool {
+        self.contains_year(extractor.date())
+            && self.contains_month(extractor.month())
+            && self.matches_day(extractor.date(), self.either_day())
             && self.contains_hour(extractor.hour())
             && self.contains_minute(extractor.minute())
-            && self.contains_weekday(extractor.weekday())
+            && self.contains_second(extractor.second())
+            && self.contains_time(&extractor.hour(), &extractor.minute())
+            && contains_date_time(
+                &self.date_spec,
+                extractor.date(),
+                &extractor.hour(),
+                &extractor.minute(),
+            )
+            && !self.excludes(
+                extractor.date(),
+                &[
+                    extractor.hour().get().into(),
+                    extractor.minute().get().into(),
+                    extractor.second().get().into(),
+                ],
+            )
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+    use chrono::{DateTime, TimeZone, Utc};
+    use chrono_tz::Tz;
     use scheda_parser::Parser;
+    use smallvec::smallvec;
+
+    use crate::{DateTimePart, DstPolicy, GapPolicy, Hour, Minute, OverlapPolicy, Schedule};
 
     fn from_ymdhm(year: i32, month: u32, day: u32, hour: u32, min: u32) -> DateTime<Utc> {
-        DateTime::from_utc(
-            NaiveDateTime::new(
-                NaiveDate::from_ymd_opt(year, month, day).unwrap(),
-                NaiveTime::from_hms_opt(hour, min, 0).unwrap(),
-            ),
-            Utc,
-        )
+        Utc.with_ymd_and_hms(year, month, day, hour, min, 0)
+            .unwrap()
     }
 
     fn assert_matches(date_time: DateTime<Utc>, schedule: &str) {
@@ -137,7 +451,7 @@ mod tests {
     fn assert_no_match(date_time: DateTime<Utc>, schedule: &str) {
         let schedule = Parser::new(schedule).parse_schedule().unwrap();
 
-        assert_eq!(schedule.matches(date_time), false);
+        assert!(!schedule.matches(date_time));
     }
 
     #[test]
@@ -175,4 +489,263 @@ mod tests {
             "when month 5, day 19 to 22, hour 20, weekday tuesday",
         );
     }
+
+    #[test]
+    fn matches_step_schedules() {
+        // Tuesday, 22/05/2001 20h45
+        let d05_22_2001 = from_ymdhm(2001, 5, 22, 20, 45);
+
+        assert_matches(d05_22_2001, "when every 15 minutes");
+        assert_matches(d05_22_2001, "when every minute");
+        assert_no_match(d05_22_2001, "when every 10 minutes");
+
+        assert_matches(from_ymdhm(2001, 5, 22, 20, 0), "when every 2 hours");
+        assert_no_match(from_ymdhm(2001, 5, 22, 21, 0), "when every 2 hours");
+        // Minutes are pinned to zero when stepping through hours
+        assert_no_match(d05_22_2001, "when every 2 hours");
+        assert_matches(d05_22_2001, "when every 2 hours, minute 45");
+        assert_matches(
+            from_ymdhm(2001, 5, 22, 20, 0),
+            "when every 2 hours, weekday mon to fri",
+        );
+        assert_no_match(
+            from_ymdhm(2001, 5, 26, 20, 0),
+            "when every 2 hours, weekday mon to fri",
+        );
+
+        // Days are counted from the first of the month
+        assert_matches(from_ymdhm(2001, 5, 22, 0, 0), "when every 3 days");
+        assert_no_match(from_ymdhm(2001, 5, 23, 0, 0), "when every 3 days");
+        assert_no_match(from_ymdhm(2001, 5, 22, 1, 0), "when every 3 days");
+    }
+
+    #[test]
+    fn matches_seconds() {
+        let at = |seconds| from_ymdhm(2001, 5, 22, 20, 0) + chrono::Duration::seconds(seconds);
+
+        assert_matches(at(30), "when second 0 or 30");
+        assert_no_match(at(15), "when second 0 or 30");
+        assert_matches(at(40), "when every 10 seconds");
+        assert_no_match(at(45), "when every 10 seconds");
+        assert_matches(at(59), "when second 50 to 10");
+
+        // Without a `second` clause, only the first second of each minute matches
+        assert_matches(at(0), "when hour 20");
+        assert_no_match(at(30), "when hour 20");
+    }
+
+    #[test]
+    fn matches_years_and_one_off_dates() {
+        assert_matches(from_ymdhm(2001, 5, 22, 20, 0), "when year 2001");
+        assert_matches(from_ymdhm(2001, 5, 22, 20, 0), "when year 1999 to 2002");
+        assert_matches(
+            from_ymdhm(2001, 5, 22, 20, 0),
+            "when year every 2 from 1995",
+        );
+        assert_no_match(
+            from_ymdhm(2001, 5, 22, 20, 0),
+            "when year every 2 from 2000",
+        );
+        assert_no_match(from_ymdhm(2001, 5, 22, 20, 0), "when year 2002 to 2003");
+
+        let schedule = "when on 2001-05-22 20:00 or 2001-05-23";
+        assert_matches(from_ymdhm(2001, 5, 22, 20, 0), schedule);
+        assert_no_match(from_ymdhm(2001, 5, 22, 21, 0), schedule);
+        assert_matches(from_ymdhm(2001, 5, 23, 21, 0), schedule);
+        assert_no_match(from_ymdhm(2002, 5, 23, 21, 0), schedule);
+
+        // One-off dates narrow down the other clauses rather than adding to them
+        assert_no_match(from_ymdhm(2001, 5, 23, 21, 0), "when on 2001-05-23, hour 9");
+    }
+
+    #[test]
+    fn matches_exceptions() {
+        // Tuesday, 22/05/2001 and Tuesday, 18/12/2001
+        let weekdays = "when weekday mon to fri, at 9:00, except month dec";
+        assert_matches(from_ymdhm(2001, 5, 22, 9, 0), weekdays);
+        assert_no_match(from_ymdhm(2001, 12, 18, 9, 0), weekdays);
+
+        let hourly = "when minute 0, except hour 12 to 13";
+        assert_matches(from_ymdhm(2001, 5, 22, 11, 0), hourly);
+        assert_no_match(from_ymdhm(2001, 5, 22, 12, 0), hourly);
+        assert_no_match(from_ymdhm(2001, 5, 22, 13, 0), hourly);
+        assert_matches(from_ymdhm(2001, 5, 22, 14, 0), hourly);
+
+        // Nested schedules only rule out what they match as a whole
+        let nested = "when hour 9, minute 0, except (when weekday tue, day 22 to 28)";
+        assert_no_match(from_ymdhm(2001, 5, 22, 9, 0), nested);
+        assert_matches(from_ymdhm(2001, 5, 15, 9, 0), nested);
+        assert_matches(from_ymdhm(2001, 5, 23, 9, 0), nested);
+
+        // Exceptions without a `second` clause rule out whole minutes
+        let at = |seconds| from_ymdhm(2001, 5, 22, 20, 0) + chrono::Duration::seconds(seconds);
+        assert_no_match(at(30), "when every 10 seconds, except hour 20");
+        assert_matches(
+            at(30),
+            "when every 10 seconds, except (when hour 20, second 0)",
+        );
+        assert_no_match(
+            at(0),
+            "when every 10 seconds, except (when hour 20, second 0)",
+        );
+    }
+
+    #[test]
+    fn matches_wrapping_ranges() {
+        // Tuesday, 22/05/2001
+        assert_matches(from_ymdhm(2001, 5, 22, 23, 0), "when hour 22 to 4");
+        assert_matches(from_ymdhm(2001, 5, 22, 0, 0), "when hour 22 to 4");
+        assert_matches(from_ymdhm(2001, 5, 22, 4, 59), "when hour 22 to 4");
+        assert_no_match(from_ymdhm(2001, 5, 22, 5, 0), "when hour 22 to 4");
+        assert_no_match(from_ymdhm(2001, 5, 22, 21, 59), "when hour 22 to 4");
+
+        assert_matches(from_ymdhm(2001, 5, 22, 20, 55), "when minute 50 to 10");
+        assert_no_match(from_ymdhm(2001, 5, 22, 20, 30), "when minute 50 to 10");
+
+        assert_matches(from_ymdhm(2001, 5, 25, 12, 0), "when weekday fri to mon");
+        assert_matches(from_ymdhm(2001, 5, 27, 12, 0), "when weekday fri to mon");
+        assert_matches(from_ymdhm(2001, 5, 28, 12, 0), "when weekday fri to mon");
+        assert_no_match(from_ymdhm(2001, 5, 22, 12, 0), "when weekday fri to mon");
+        assert_matches(from_ymdhm(2001, 5, 22, 12, 0), "when weekday mon to sun");
+
+        assert_matches(from_ymdhm(2001, 1, 22, 12, 0), "when month nov to feb");
+        assert_no_match(from_ymdhm(2001, 5, 22, 12, 0), "when month nov to feb");
+    }
+
+    #[test]
+    fn matches_times_of_day() {
+        let schedule = "when at 14:30 or 18:00";
+
+        assert_matches(from_ymdhm(2001, 5, 22, 14, 30), schedule);
+        assert_matches(from_ymdhm(2001, 5, 22, 18, 0), schedule);
+        // Unlike `hour 14 or 18, minute 0 or 30`
+        assert_no_match(from_ymdhm(2001, 5, 22, 14, 0), schedule);
+        assert_no_match(from_ymdhm(2001, 5, 22, 18, 30), schedule);
+
+        assert_matches(from_ymdhm(2001, 5, 22, 21, 15), "when at 9:15 pm");
+        assert_no_match(from_ymdhm(2001, 5, 22, 21, 15), "when at 9:15 am");
+    }
+
+    #[test]
+    fn matches_days_from_month_end() {
+        assert_matches(from_ymdhm(2001, 2, 28, 12, 0), "when day last");
+        assert_matches(from_ymdhm(2004, 2, 29, 12, 0), "when day last");
+        assert_no_match(from_ymdhm(2004, 2, 28, 12, 0), "when day last");
+        assert_matches(from_ymdhm(2001, 4, 30, 12, 0), "when day last");
+        assert_no_match(from_ymdhm(2001, 5, 30, 12, 0), "when day last");
+
+        // Counting back, the last day is -1
+        assert_matches(from_ymdhm(2001, 5, 29, 12, 0), "when day -3");
+        assert_matches(from_ymdhm(2004, 2, 27, 12, 0), "when day -3");
+        assert_matches(from_ymdhm(2001, 6, 28, 12, 0), "when day -3 to -1");
+        assert_no_match(from_ymdhm(2001, 6, 27, 12, 0), "when day -3 to -1");
+
+        // Days counted from either end add up
+        assert_matches(from_ymdhm(2001, 6, 1, 12, 0), "when day 1 or last");
+        assert_matches(from_ymdhm(2001, 6, 30, 12, 0), "when day 1 or last");
+        assert_no_match(from_ymdhm(2001, 6, 15, 12, 0), "when day 1 or last");
+    }
+
+    #[test]
+    fn matches_nth_weekdays() {
+        let patch_tuesday = "when weekday second tue";
+
+        // Tuesday, 08/05/2001 and Tuesday, 14/05/2002
+        assert_matches(from_ymdhm(2001, 5, 8, 12, 0), patch_tuesday);
+        assert_matches(from_ymdhm(2002, 5, 14, 12, 0), patch_tuesday);
+        assert_no_match(from_ymdhm(2001, 5, 15, 12, 0), patch_tuesday);
+        assert_no_match(from_ymdhm(2001, 5, 9, 12, 0), patch_tuesday);
+
+        // Friday, 25/05/2001 was the last one of its month, 30/03/2001 the fifth and last
+        assert_matches(from_ymdhm(2001, 5, 25, 12, 0), "when weekday last fri");
+        assert_no_match(from_ymdhm(2001, 5, 18, 12, 0), "when weekday last fri");
+        assert_matches(from_ymdhm(2001, 3, 30, 12, 0), "when weekday last fri");
+        assert_matches(from_ymdhm(2001, 3, 30, 12, 0), "when weekday fifth fri");
+
+        // Plain and nth weekdays add up
+        assert_matches(
+            from_ymdhm(2001, 5, 26, 12, 0),
+            "when weekday sat or first mon",
+        );
+        assert_matches(
+            from_ymdhm(2001, 5, 7, 12, 0),
+            "when weekday sat or first mon",
+        );
+        assert_no_match(
+            from_ymdhm(2001, 5, 14, 12, 0),
+            "when weekday sat or first mon",
+        );
+    }
+
+    #[test]
+    fn matches_either_day_or_weekday() {
+        let cron_like = "when day 1, weekday mon, combine or";
+
+        // Tuesday, 01/05/2001 and Monday, 07/05/2001
+        assert_matches(from_ymdhm(2001, 5, 1, 12, 0), cron_like);
+        assert_matches(from_ymdhm(2001, 5, 7, 12, 0), cron_like);
+        assert_no_match(from_ymdhm(2001, 5, 8, 12, 0), cron_like);
+        assert_no_match(from_ymdhm(2001, 5, 1, 12, 0), "when day 1, weekday mon");
+
+        // With only one of them restricted, the other one doesn't match everything
+        assert_no_match(from_ymdhm(2001, 5, 8, 12, 0), "when day 1, combine or");
+        assert_no_match(
+            from_ymdhm(2001, 5, 8, 12, 0),
+            "when weekday mon, combine or",
+        );
+    }
+
+    #[test]
+    fn matches_in_schedule_timezone() {
+        let schedule = "when hour 9, weekday mon to fri in America/Sao_Paulo";
+
+        // Monday, 24/05/2021, when São Paulo was three hours behind UTC
+        assert_matches(from_ymdhm(2021, 5, 24, 12, 30), schedule);
+        assert_no_match(from_ymdhm(2021, 5, 24, 9, 30), schedule);
+        // Saturday, 29/05/2021 02h00 UTC is still Friday in São Paulo
+        assert_no_match(
+            from_ymdhm(2021, 5, 29, 2, 0),
+            "when weekday sat in America/Sao_Paulo",
+        );
+        assert_matches(
+            from_ymdhm(2021, 5, 29, 2, 0),
+            "when weekday fri in America/Sao_Paulo",
+        );
+    }
+
+    #[test]
+    fn matches_around_dst_transitions() {
+        let half_past = |hour, gap, overlap| Schedule {
+            hour_spec: smallvec![DateTimePart::Single(Hour::new(hour).unwrap())],
+            minute_spec: smallvec![DateTimePart::Single(Minute::new(30).unwrap())],
+            timezone: Some(Tz::America__New_York),
+            dst_policy: DstPolicy { gap, overlap },
+            ..Schedule::new()
+        };
+
+        // 14/03/2021, New York went from 02h00 EST straight to 03h00 EDT (07h00 UTC)
+        let skip = half_past(2, GapPolicy::Skip, OverlapPolicy::Once);
+        let next_valid = half_past(2, GapPolicy::NextValid, OverlapPolicy::Once);
+        assert!(!skip.matches(from_ymdhm(2021, 3, 14, 7, 0)));
+        assert!(next_valid.matches(from_ymdhm(2021, 3, 14, 7, 0)));
+        assert!(!next_valid.matches(from_ymdhm(2021, 3, 14, 7, 1)));
+
+        // 07/11/2021, New York went from 02h00 EDT back to 01h00 EST, so 01h30
+        // happened at both 05h30 and 06h30 UTC
+        let once = half_past(1, GapPolicy::Skip, OverlapPolicy::Once);
+        let twice = half_past(1, GapPolicy::Skip, OverlapPolicy::Twice);
+        assert!(once.matches(from_ymdhm(2021, 11, 7, 5, 30)));
+        assert!(!once.matches(from_ymdhm(2021, 11, 7, 6, 30)));
+        assert!(twice.matches(from_ymdhm(2021, 11, 7, 5, 30)));
+        assert!(twice.matches(from_ymdhm(2021, 11, 7, 6, 30)));
+
+        // 31/10/2021, Berlin went from 03h00 CEST back to 02h00 CET (01h00 UTC)
+        let berlin = Schedule {
+            hour_spec: smallvec![DateTimePart::Single(Hour::new(2).unwrap())],
+            timezone: Some(Tz::Europe__Berlin),
+            ..Schedule::new()
+        };
+        assert!(berlin.matches(from_ymdhm(2021, 10, 31, 0, 10)));
+        assert!(!berlin.matches(from_ymdhm(2021, 10, 31, 1, 10)));
+    }
 }
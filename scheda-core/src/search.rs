@@ -0,0 +1,1135 @@
+use chrono::{
+    DateTime, Datelike, Duration, LocalResult, Month, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Timelike,
+};
+use num_traits::FromPrimitive;
+
+use crate::{
+    dst::{self, GapPolicy, OverlapPolicy},
+    schedule::contains_date_time,
+    DateTimePart, Hour, Minute, Schedule, Second, Year,
+};
+
+/// How many years a search may cover before giving up.
+///
+/// The Gregorian calendar repeats itself every 400 years, so a schedule that
+/// doesn't fire within that window never will.
+pub(crate) const SEARCH_HORIZON_YEARS: i32 = 400;
+
+/// Lazily yields every instant matching a [`Schedule`], in ascending order.
+///
+/// Created by [`Schedule::upcoming`].
+pub struct Upcoming<'a, Tz: TimeZone> {
+    schedule: &'a Schedule,
+    cursor: Option<DateTime<Tz>>,
+}
+
+impl<'a, Tz: TimeZone> Iterator for Upcoming<'a, Tz> {
+    type Item = DateTime<Tz>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cursor = self.cursor.take()?;
+        let next = self.schedule.next_after(cursor)?;
+
+        self.cursor = Some(next.clone());
+
+        Some(next)
+    }
+}
+
+/// Lazily yields every instant matching a [`Schedule`], in descending order.
+///
+/// Created by [`Schedule::preceding`].
+pub struct Preceding<'a, Tz: TimeZone> {
+    schedule: &'a Schedule,
+    cursor: Option<DateTime<Tz>>,
+}
+
+impl<'a, Tz: TimeZone> Iterator for Preceding<'a, Tz> {
+    type Item = DateTime<Tz>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cursor = self.cursor.take()?;
+        let previous = self.schedule.prev_before(cursor)?;
+
+        self.cursor = Some(previous.clone());
+
+        Some(previous)
+    }
+}
+
+impl Schedule {
+    /// Finds the first instant strictly after `date_time` that matches this schedule.
+    ///
+    /// Returns `None` if the schedule can't ever fire again, e.g. `when day 31, month feb`,
+    /// or if it's exhausted, e.g. `when on 2026-12-24` once that date has passed.
+    pub fn next_after<Tz: TimeZone>(&self, date_time: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        match self.timezone {
+            Some(timezone) => self
+                .next_after_local(date_time.with_timezone(&timezone))
+                .map(|found| found.with_timezone(&date_time.timezone())),
+            None => self.next_after_local(date_time),
+        }
+    }
+
+    /// Searches for the next occurrence in the time zone `date_time` is in,
+    /// following the [`DstPolicy`](crate::DstPolicy)
+    fn next_after_local<Tz: TimeZone>(&self, date_time: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let time_zone = date_time.timezone();
+        let until_year = date_time.year() + SEARCH_HORIZON_YEARS;
+        let until_year = self
+            .last_year()
+            .map_or(until_year, |last| last.min(until_year));
+        let policy = self.dst_policy;
+
+        // Searching forward from the first pass through repeated local times
+        // would never get to their second pass
+        let repeated = match policy.overlap {
+            OverlapPolicy::Twice if !dst::is_repeat(&date_time) => dst::repeated_minutes(
+                &time_zone,
+                date_time.naive_local(),
+            )
+            .and_then(|(first, last)| {
+                let candidate = self.next_naive_after(first - Duration::seconds(1), until_year)?;
+
+                (candidate < last + Duration::minutes(1))
+                    .then(|| time_zone.from_local_datetime(&candidate).latest())
+                    .flatten()
+            }),
+            _ => None,
+        };
+
+        let mut from = date_time.naive_local();
+
+        let next = loop {
+            let Some(candidate) = self.next_naive_after(from, until_year) else {
+                break None;
+            };
+
+            match time_zone.from_local_datetime(&candidate) {
+                LocalResult::Single(found) => break Some(found),
+                // The candidate happens twice, e.g. when clocks are set back
+                LocalResult::Ambiguous(earliest, latest) => {
+                    if earliest > date_time {
+                        break Some(earliest);
+                    }
+                    if policy.overlap == OverlapPolicy::Twice && latest > date_time {
+                        break Some(latest);
+                    }
+                }
+                // The candidate was skipped over by the time zone, e.g. when clocks are set forward
+                LocalResult::None => {
+                    if policy.gap == GapPolicy::NextValid {
+                        let shifted = dst::first_after_skipped(&time_zone, candidate);
+
+                        if let Some(found) = shifted.filter(|found| *found > date_time) {
+                            break Some(found);
+                        }
+                    }
+                }
+            }
+
+            from = candidate;
+        };
+
+        match (next, repeated) {
+            (Some(next), Some(repeated)) => Some(next.min(repeated)),
+            (next, repeated) => next.or(repeated),
+        }
+    }
+
+    /// Lazily iterates over every instant after `from` that matches this schedule.
+    pub fn upcoming<Tz: TimeZone>(&self, from: DateTime<Tz>) -> Upcoming<'_, Tz> {
+        Upcoming {
+            schedule: self,
+            cursor: Some(from),
+        }
+    }
+
+    /// Finds the last instant strictly before `date_time` that matches this schedule.
+    ///
+    /// Called with the current time, this is when the schedule last fired, which
+    /// tells whether a run was missed, e.g. after some downtime.
+    ///
+    /// Returns `None` if the schedule never fired before `date_time`.
+    pub fn prev_before<Tz: TimeZone>(&self, date_time: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        match self.timezone {
+            Some(timezone) => self
+                .prev_before_local(date_time.with_timezone(&timezone))
+                .map(|found| found.with_timezone(&date_time.timezone())),
+            None => self.prev_before_local(date_time),
+        }
+    }
+
+    /// Searches for the previous occurrence in the time zone `date_time` is in,
+    /// following the [`DstPolicy`](crate::DstPolicy)
+    fn prev_before_local<Tz: TimeZone>(&self, date_time: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let time_zone = date_time.timezone();
+        let since_year = date_time.year() - SEARCH_HORIZON_YEARS;
+        let since_year = self
+            .first_year()
+            .map_or(since_year, |first| first.max(since_year));
+        let policy = self.dst_policy;
+
+        // Searching backward from the second pass through repeated local times
+        // would skip over their whole first pass
+        let repeated = if dst::is_repeat(&date_time) {
+            dst::repeated_minutes(&time_zone, date_time.naive_local()).and_then(|(first, last)| {
+                let candidate = self.prev_naive_before(last + Duration::minutes(1), since_year)?;
+
+                (candidate >= first)
+                    .then(|| time_zone.from_local_datetime(&candidate).earliest())
+                    .flatten()
+            })
+        } else {
+            None
+        };
+
+        let mut from = date_time.naive_local();
+
+        let previous = loop {
+            let Some(candidate) = self.prev_naive_before(from, since_year) else {
+                break None;
+            };
+
+            match time_zone.from_local_datetime(&candidate) {
+                LocalResult::Single(found) => break Some(found),
+                // The candidate happens twice, e.g. when clocks are set back
+                LocalResult::Ambiguous(earliest, latest) => {
+                    if policy.overlap == OverlapPolicy::Twice && latest < date_time {
+                        break Some(latest);
+                    }
+                    if earliest < date_time {
+                        break Some(earliest);
+                    }
+                }
+                // The candidate was skipped over by the time zone, e.g. when clocks are set forward
+                LocalResult::None => {
+                    if policy.gap == GapPolicy::NextValid {
+                        let shifted = dst::first_after_skipped(&time_zone, candidate);
+
+                        if let Some(found) = shifted.filter(|found| *found < date_time) {
+                            break Some(found);
+                        }
+                    }
+                }
+            }
+
+            from = candidate;
+        };
+
+        match (previous, repeated) {
+            (Some(previous), Some(repeated)) => Some(previous.max(repeated)),
+            (previous, repeated) => previous.or(repeated),
+        }
+    }
+
+    /// Lazily iterates backwards over every instant before `from` that matches this schedule.
+    pub fn preceding<Tz: TimeZone>(&self, from: DateTime<Tz>) -> Preceding<'_, Tz> {
+        Preceding {
+            schedule: self,
+            cursor: Some(from),
+        }
+    }
+
+    /// Finds the first matching second strictly after `from`, skipping over
+    /// whole years, months, days, hours and minutes whenever they don't match.
+    pub(crate) fn next_naive_after(
+        &self,
+        from: NaiveDateTime,
+        until_year: i32,
+    ) -> Option<NaiveDateTime> {
+        let mut date = from.date();
+        let mut earliest = Some((from.hour(), from.minute(), from.second() + 1));
+        let either_day = self.either_day();
+        // Keys of the dates whose times of day all turned out to be excluded,
+        // which would otherwise be searched second by second again
+        let mut empty_days = Vec::new();
+
+        loop {
+            if date.year() > until_year {
+                return None;
+            }
+
+            if !self.contains_year(date) {
+                date = NaiveDate::from_ymd_opt(date.year() + 1, 1, 1)?;
+                earliest = None;
+                continue;
+            }
+
+            // One-off dates are few and far between, so they're jumped to directly
+            if !self.contains_date(date) {
+                date = self
+                    .date_spec
+                    .iter()
+                    .map(|on| on.date)
+                    .filter(|on| *on > date)
+                    .min()?;
+                earliest = None;
+                continue;
+            }
+
+            if !self.contains_month(Month::from_u32(date.month())?) {
+                date = first_day_of_next_month(date)?;
+                earliest = None;
+                continue;
+            }
+
+            if self.matches_date(date, either_day) && !self.excludes(date, &[]) {
+                // Only whole days can tell other dates apart
+                let key = earliest
+                    .is_none()
+                    .then(|| self.times_of_day_key(date))
+                    .flatten();
+
+                if key.as_ref().is_none_or(|key| !empty_days.contains(key)) {
+                    let (hour, minute, second) = earliest.unwrap_or((0, 0, 0));
+
+                    if let Some(time) = self.next_time_of_day(date, hour, minute, second) {
+                        return Some(date.and_time(time));
+                    }
+                    empty_days.extend(key);
+                }
+            }
+
+            date = date.succ_opt()?;
+            earliest = None;
+        }
+    }
+
+    /// Finds the last matching second strictly before `from`, skipping over
+    /// whole years, months, days, hours and minutes whenever they don't match.
+    fn prev_naive_before(&self, from: NaiveDateTime, since_year: i32) -> Option<NaiveDateTime> {
+        // The second `from` falls in only counts if `from` is past its start
+        let latest = from - Duration::nanoseconds(1);
+
+        let mut date = latest.date();
+        let mut latest = Some((latest.hour(), latest.minute(), latest.second()));
+        let either_day = self.either_day();
+        let mut empty_days = Vec::new();
+
+        loop {
+            if date.year() < since_year {
+                return None;
+            }
+
+            if !self.contains_year(date) {
+                date = NaiveDate::from_ymd_opt(date.year() - 1, 12, 31)?;
+                latest = None;
+                continue;
+            }
+
+            if !self.contains_date(date) {
+                date = self
+                    .date_spec
+                    .iter()
+                    .map(|on| on.date)
+                    .filter(|on| *on < date)
+                    .max()?;
+                latest = None;
+                continue;
+            }
+
+            if !self.contains_month(Month::from_u32(date.month())?) {
+                date = first_day_of_month(date)?.pred_opt()?;
+                latest = None;
+                continue;
+            }
+
+            if self.matches_date(date, either_day) && !self.excludes(date, &[]) {
+                let key = latest
+                    .is_none()
+                    .then(|| self.times_of_day_key(date))
+                    .flatten();
+
+                if key.as_ref().is_none_or(|key| !empty_days.contains(key)) {
+                    let (hour, minute, second) = latest.unwrap_or((23, 59, 59));
+
+                    if let Some(time) = self.prev_time_of_day(date, hour, minute, second) {
+                        return Some(date.and_time(time));
+                    }
+                    empty_days.extend(key);
+                }
+            }
+
+            date = date.pred_opt()?;
+            latest = None;
+        }
+    }
+
+    /// The first year the schedule may fire in, if its year or one-off date
+    /// specs limit it
+    pub(crate) fn first_year(&self) -> Option<i32> {
+        let first_named = self.year_spec.iter().map(|part| match part {
+            DateTimePart::Single(year)
+            | DateTimePart::Range { starting: year, .. }
+            | DateTimePart::Step { starting: year, .. } => *year,
+            DateTimePart::WrappingRange { .. } => Year::MIN,
+        });
+        let first_dated = self.date_spec.iter().map(|on| on.date.year());
+
+        [
+            first_named.min().map(|year| i32::from(year.get())),
+            first_dated.min(),
+        ]
+        .into_iter()
+        .flatten()
+        .max()
+    }
+
+    /// The last year the schedule may fire in, if its year or one-off date
+    /// specs limit it
+    pub(crate) fn last_year(&self) -> Option<i32> {
+        let last_named = self.year_spec.iter().map(|part| match part {
+            DateTimePart::Single(year) | DateTimePart::Range { ending: year, .. } => *year,
+            // Steps go on until the end of the calendar
+            DateTimePart::WrappingRange { .. } | DateTimePart::Step { .. } => Year::MAX,
+        });
+        let last_dated = self.date_spec.iter().map(|on| on.date.year());
+
+        [
+            last_named.max().map(|year| i32::from(year.get())),
+            last_dated.max(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    /// Whether any of the exact times of day, if there are any, are within `hour`
+    fn has_time_in_hour(&self, hour: u32) -> bool {
+        self.time_spec.is_empty()
+            || self
+                .time_spec
+                .iter()
+                .any(|time| u32::from(time.hour.get()) == hour)
+    }
+
+    /// Whether `hour:minute` on `date` satisfies the hour, minute, time-of-day
+    /// and one-off date specs, without being ruled out as a whole by an exception
+    fn matches_time(&self, date: NaiveDate, hour: u32, minute: u32) -> bool {
+        let hour = Hour::new(hour as u8).unwrap();
+        let minute = || Minute::new(minute as u8).unwrap();
+
+        self.contains_minute(minute())
+            && self.contains_time(&hour, &minute())
+            && contains_date_time(&self.date_spec, date, &hour, &minute())
+            && !self.excludes(date, &[hour.get().into(), minute().get().into()])
+    }
+
+    /// Whether `hour:minute:second` on `date` satisfies the second spec and
+    /// isn't ruled out by an exception
+    fn matches_second(&self, date: NaiveDate, hour: u32, minute: u32, second: u32) -> bool {
+        self.contains_second(Second::new(second as u8).unwrap())
+            && !self.excludes(date, &[hour, minute, second])
+    }
+
+    /// Finds the first matching time of day on `date` at or after `hour:minute:second`
+    fn next_time_of_day(
+        &self,
+        date: NaiveDate,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> Option<NaiveTime> {
+        (hour..24)
+            .filter(|&hour| {
+                self.contains_hour(Hour::new(hour as u8).unwrap())
+                    && self.has_time_in_hour(hour)
+                    && !self.excludes(date, &[hour])
+            })
+            .find_map(|matching_hour| {
+                let first_minute = if matching_hour == hour { minute } else { 0 };
+
+                (first_minute..60)
+                    .filter(|&minute| self.matches_time(date, matching_hour, minute))
+                    .find_map(|matching_minute| {
+                        let is_first = (matching_hour, matching_minute) == (hour, minute);
+                        let first_second = if is_first { second } else { 0 };
+
+                        (first_second..60)
+                            .find(|&second| {
+                                self.matches_second(date, matching_hour, matching_minute, second)
+                            })
+                            .and_then(|second| {
+                                NaiveTime::from_hms_opt(matching_hour, matching_minute, second)
+                            })
+                    })
+            })
+    }
+
+    /// Finds the last matching time of day on `date` at or before `hour:minute:second`
+    fn prev_time_of_day(
+        &self,
+        date: NaiveDate,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> Option<NaiveTime> {
+        (0..=hour)
+            .rev()
+            .filter(|&hour| {
+                self.contains_hour(Hour::new(hour as u8).unwrap())
+                    && self.has_time_in_hour(hour)
+                    && !self.excludes(date, &[hour])
+            })
+            .find_map(|matching_hour| {
+                let last_minute = if matching_hour == hour { minute } else { 59 };
+
+                (0..=last_minute)
+                    .rev()
+                    .filter(|&minute| self.matches_time(date, matching_hour, minute))
+                    .find_map(|matching_minute| {
+                        let is_last = (matching_hour, matching_minute) == (hour, minute);
+                        let last_second = if is_last { second } else { 59 };
+
+                        (0..=last_second)
+                            .rev()
+                            .find(|&second| {
+                                self.matches_second(date, matching_hour, matching_minute, second)
+                            })
+                            .and_then(|second| {
+                                NaiveTime::from_hms_opt(matching_hour, matching_minute, second)
+                            })
+                    })
+            })
+    }
+}
+
+fn first_day_of_month(date: NaiveDate) -> Option<NaiveDate> {
+    date.with_day(1)
+}
+
+fn first_day_of_next_month(date: NaiveDate) -> Option<NaiveDate> {
+    match date.month() {
+        12 => NaiveDate::from_ymd_opt(date.year() + 1, 1, 1),
+        month => NaiveDate::from_ymd_opt(date.year(), month + 1, 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+    use chrono_tz::Tz;
+    use scheda_parser::Parser;
+    use smallvec::smallvec;
+
+    use crate::{DateTimePart, DstPolicy, GapPolicy, Hour, Minute, OverlapPolicy, Schedule};
+
+    fn from_ymdhm(year: i32, month: u32, day: u32, hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, min, 0)
+            .unwrap()
+    }
+
+    fn next_after(schedule: &str, date_time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        Parser::new(schedule)
+            .parse_schedule()
+            .unwrap()
+            .next_after(date_time)
+    }
+
+    #[test]
+    fn finds_next_occurrence() {
+        // Tuesday, 22/05/2001 20h00
+        let d05_22_2001 = from_ymdhm(2001, 5, 22, 20, 0);
+
+        assert_eq!(
+            next_after("when every 15 minutes", d05_22_2001),
+            Some(from_ymdhm(2001, 5, 22, 20, 15))
+        );
+        assert_eq!(
+            next_after("when hour 20, minute 0", d05_22_2001),
+            Some(from_ymdhm(2001, 5, 23, 20, 0))
+        );
+        assert_eq!(
+            next_after("when weekday fri, hour 9, minute 30", d05_22_2001),
+            Some(from_ymdhm(2001, 5, 25, 9, 30))
+        );
+        assert_eq!(
+            next_after("when month jan, day 1, hour 0, minute 0", d05_22_2001),
+            Some(from_ymdhm(2002, 1, 1, 0, 0))
+        );
+        assert_eq!(
+            next_after("when day 31, hour 12, minute 0", d05_22_2001),
+            Some(from_ymdhm(2001, 5, 31, 12, 0))
+        );
+        assert_eq!(
+            next_after("when month jun, day 31", from_ymdhm(2001, 5, 31, 12, 0)),
+            None
+        );
+        // Seconds are dropped: the next occurrence is always on a whole minute
+        assert_eq!(
+            next_after(
+                "when every minute",
+                d05_22_2001 + chrono::Duration::seconds(42)
+            ),
+            Some(from_ymdhm(2001, 5, 22, 20, 1))
+        );
+    }
+
+    #[test]
+    fn searches_times_of_day() {
+        let schedule = "when at 14:30 or 18:00";
+
+        assert_eq!(
+            next_after(schedule, from_ymdhm(2001, 5, 22, 20, 0)),
+            Some(from_ymdhm(2001, 5, 23, 14, 30))
+        );
+        assert_eq!(
+            next_after(schedule, from_ymdhm(2001, 5, 23, 14, 30)),
+            Some(from_ymdhm(2001, 5, 23, 18, 0))
+        );
+        assert_eq!(
+            prev_before(schedule, from_ymdhm(2001, 5, 23, 14, 30)),
+            Some(from_ymdhm(2001, 5, 22, 18, 0))
+        );
+        assert_eq!(
+            next_after("when at 14:30, hour 18", from_ymdhm(2001, 5, 22, 20, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn searches_days_from_month_end() {
+        let schedule = "when day last, hour 0, minute 0";
+
+        assert_eq!(
+            next_after(schedule, from_ymdhm(2004, 1, 31, 0, 0)),
+            Some(from_ymdhm(2004, 2, 29, 0, 0))
+        );
+        assert_eq!(
+            next_after(schedule, from_ymdhm(2004, 2, 29, 0, 0)),
+            Some(from_ymdhm(2004, 3, 31, 0, 0))
+        );
+        assert_eq!(
+            prev_before(schedule, from_ymdhm(2001, 3, 1, 0, 0)),
+            Some(from_ymdhm(2001, 2, 28, 0, 0))
+        );
+        assert_eq!(
+            next_after(
+                "when day -3, month feb, hour 9, minute 0",
+                from_ymdhm(2003, 3, 1, 0, 0)
+            ),
+            Some(from_ymdhm(2004, 2, 27, 9, 0))
+        );
+    }
+
+    #[test]
+    fn searches_nth_weekdays() {
+        let patch_tuesday = "when weekday second tue, hour 18, minute 0";
+
+        assert_eq!(
+            next_after(patch_tuesday, from_ymdhm(2001, 5, 22, 20, 0)),
+            Some(from_ymdhm(2001, 6, 12, 18, 0))
+        );
+        assert_eq!(
+            prev_before(patch_tuesday, from_ymdhm(2001, 5, 22, 20, 0)),
+            Some(from_ymdhm(2001, 5, 8, 18, 0))
+        );
+        // February 2009 had no fifth Sunday, March did
+        assert_eq!(
+            next_after(
+                "when weekday fifth sun, hour 0, minute 0",
+                from_ymdhm(2009, 2, 1, 0, 0)
+            ),
+            Some(from_ymdhm(2009, 3, 29, 0, 0))
+        );
+    }
+
+    #[test]
+    fn searches_either_day_or_weekday() {
+        let cron_like = "when day 1 or 15, weekday fri, hour 0, minute 0, combine or";
+
+        // Tuesday, 22/05/2001 20h00
+        let d05_22_2001 = from_ymdhm(2001, 5, 22, 20, 0);
+        let upcoming = Parser::new(cron_like)
+            .parse_schedule()
+            .unwrap()
+            .upcoming(d05_22_2001)
+            .take(4)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            upcoming,
+            [
+                from_ymdhm(2001, 5, 25, 0, 0),
+                from_ymdhm(2001, 6, 1, 0, 0),
+                from_ymdhm(2001, 6, 8, 0, 0),
+                from_ymdhm(2001, 6, 15, 0, 0),
+            ]
+        );
+        assert_eq!(
+            prev_before(cron_like, d05_22_2001),
+            Some(from_ymdhm(2001, 5, 18, 0, 0))
+        );
+    }
+
+    fn prev_before(schedule: &str, date_time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        Parser::new(schedule)
+            .parse_schedule()
+            .unwrap()
+            .prev_before(date_time)
+    }
+
+    #[test]
+    fn finds_previous_occurrence() {
+        // Tuesday, 22/05/2001 20h00
+        let d05_22_2001 = from_ymdhm(2001, 5, 22, 20, 0);
+
+        assert_eq!(
+            prev_before("when every 15 minutes", d05_22_2001),
+            Some(from_ymdhm(2001, 5, 22, 19, 45))
+        );
+        // The current minute counts once we're past its start
+        assert_eq!(
+            prev_before(
+                "when hour 20, minute 0",
+                d05_22_2001 + chrono::Duration::seconds(1)
+            ),
+            Some(d05_22_2001)
+        );
+        assert_eq!(
+            prev_before("when hour 20, minute 0", d05_22_2001),
+            Some(from_ymdhm(2001, 5, 21, 20, 0))
+        );
+        assert_eq!(
+            prev_before("when weekday fri, hour 9, minute 30", d05_22_2001),
+            Some(from_ymdhm(2001, 5, 18, 9, 30))
+        );
+        assert_eq!(
+            prev_before("when month dec, day 31, hour 23", d05_22_2001),
+            Some(from_ymdhm(2000, 12, 31, 23, 59))
+        );
+        assert_eq!(
+            prev_before("when day 29, month feb, hour 0, minute 0", d05_22_2001),
+            Some(from_ymdhm(2000, 2, 29, 0, 0))
+        );
+        assert_eq!(prev_before("when day 30, month feb", d05_22_2001), None);
+    }
+
+    #[test]
+    fn searches_seconds() {
+        // Tuesday, 22/05/2001 20h00
+        let d05_22_2001 = from_ymdhm(2001, 5, 22, 20, 0);
+        let seconds = |seconds| chrono::Duration::seconds(seconds);
+
+        assert_eq!(
+            next_after("when every 10 seconds", d05_22_2001),
+            Some(d05_22_2001 + seconds(10))
+        );
+        assert_eq!(
+            next_after("when second 45", d05_22_2001 + seconds(50)),
+            Some(from_ymdhm(2001, 5, 22, 20, 1) + seconds(45))
+        );
+        assert_eq!(
+            next_after(
+                "when hour 20, minute 0, second 0 or 30",
+                d05_22_2001 + seconds(30)
+            ),
+            Some(from_ymdhm(2001, 5, 23, 20, 0))
+        );
+        assert_eq!(
+            prev_before("when every 10 seconds", d05_22_2001),
+            Some(from_ymdhm(2001, 5, 22, 19, 59) + seconds(50))
+        );
+        assert_eq!(
+            prev_before("when second 0 or 30", d05_22_2001 + seconds(15)),
+            Some(d05_22_2001)
+        );
+
+        // 14/03/2021, New York went from 02h00 EST straight to 03h00 EDT (07h00 UTC)
+        assert_eq!(
+            next_after(
+                "when hour 2, minute 30, second 15 in America/New_York",
+                from_ymdhm(2021, 3, 14, 0, 0)
+            ),
+            Some(from_ymdhm(2021, 3, 14, 7, 0))
+        );
+    }
+
+    #[test]
+    fn searches_years_and_one_off_dates() {
+        // Tuesday, 22/05/2001 20h00
+        let d05_22_2001 = from_ymdhm(2001, 5, 22, 20, 0);
+
+        assert_eq!(
+            next_after(
+                "when year 2027 to 2028, month jan, day 1, at 0:00",
+                d05_22_2001
+            ),
+            Some(from_ymdhm(2027, 1, 1, 0, 0))
+        );
+        assert_eq!(
+            prev_before("when year every 4 from 1994, at 12:00", d05_22_2001),
+            Some(from_ymdhm(1998, 12, 31, 12, 0))
+        );
+
+        let migration = "when on 2026-12-24 23:00 or 2026-12-31, hour 9 or 23, minute 0";
+        assert_eq!(
+            next_after(migration, d05_22_2001),
+            Some(from_ymdhm(2026, 12, 24, 23, 0))
+        );
+        assert_eq!(
+            next_after(migration, from_ymdhm(2026, 12, 24, 23, 0)),
+            Some(from_ymdhm(2026, 12, 31, 9, 0))
+        );
+        assert_eq!(
+            prev_before(migration, from_ymdhm(2030, 1, 1, 0, 0)),
+            Some(from_ymdhm(2026, 12, 31, 23, 0))
+        );
+
+        // Once their last date has passed, schedules are exhausted for good
+        assert_eq!(next_after(migration, from_ymdhm(2026, 12, 31, 23, 0)), None);
+        assert_eq!(next_after("when year 2001, month jan", d05_22_2001), None);
+        assert_eq!(prev_before(migration, d05_22_2001), None);
+    }
+
+    #[test]
+    fn iterates_over_preceding_occurrences() {
+        let schedule = Parser::new("when weekday mon to fri, hour 9 or 17, minute 0")
+            .parse_schedule()
+            .unwrap();
+
+        // Monday, 28/05/2001 12h00
+        let from = from_ymdhm(2001, 5, 28, 12, 0);
+
+        let preceding: Vec<_> = schedule.preceding(from).take(3).collect();
+
+        assert_eq!(
+            preceding,
+            [
+                from_ymdhm(2001, 5, 28, 9, 0),
+                from_ymdhm(2001, 5, 25, 17, 0),
+                from_ymdhm(2001, 5, 25, 9, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn searches_across_wrapping_ranges() {
+        // Tuesday, 22/05/2001 20h00
+        let d05_22_2001 = from_ymdhm(2001, 5, 22, 20, 0);
+        let schedule = "when weekday fri to mon, hour 22 to 1, minute 0";
+
+        assert_eq!(
+            next_after(schedule, d05_22_2001),
+            Some(from_ymdhm(2001, 5, 25, 0, 0))
+        );
+        assert_eq!(
+            prev_before(schedule, d05_22_2001),
+            Some(from_ymdhm(2001, 5, 21, 23, 0))
+        );
+    }
+
+    #[test]
+    fn searches_in_schedule_timezone() {
+        let schedule = "when hour 9, minute 0 in Europe/Berlin";
+
+        // Berlin is one hour ahead of UTC in winter and two hours ahead in summer
+        assert_eq!(
+            next_after(schedule, from_ymdhm(2021, 1, 4, 12, 0)),
+            Some(from_ymdhm(2021, 1, 5, 8, 0))
+        );
+        assert_eq!(
+            next_after(schedule, from_ymdhm(2021, 7, 5, 12, 0)),
+            Some(from_ymdhm(2021, 7, 6, 7, 0))
+        );
+        assert_eq!(
+            prev_before(schedule, from_ymdhm(2021, 7, 5, 12, 0)),
+            Some(from_ymdhm(2021, 7, 5, 7, 0))
+        );
+
+        // Results come back in the time zone they were asked in
+        let offset = FixedOffset::east_opt(9 * 3600).unwrap();
+        let from = offset.with_ymd_and_hms(2021, 1, 4, 12, 0, 0).unwrap();
+        let next = Parser::new(schedule)
+            .parse_schedule()
+            .unwrap()
+            .next_after(from)
+            .unwrap();
+
+        assert_eq!(next.offset(), &offset);
+        assert_eq!(next, offset.with_ymd_and_hms(2021, 1, 4, 17, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn handles_rare_and_impossible_schedules() {
+        assert_eq!(
+            next_after(
+                "when day 29, month feb, hour 0, minute 0",
+                from_ymdhm(2001, 1, 1, 0, 0)
+            ),
+            Some(from_ymdhm(2004, 2, 29, 0, 0))
+        );
+        // 2100 is not a leap year
+        assert_eq!(
+            next_after(
+                "when day 29, month feb, hour 0, minute 0",
+                from_ymdhm(2096, 3, 1, 0, 0)
+            ),
+            Some(from_ymdhm(2104, 2, 29, 0, 0))
+        );
+
+        assert_eq!(
+            next_after("when day 31, month feb", from_ymdhm(2001, 1, 1, 0, 0)),
+            None
+        );
+        assert_eq!(
+            next_after(
+                "when day 31, month apr or jun",
+                from_ymdhm(2001, 1, 1, 0, 0)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn gives_up_when_exceptions_rule_out_everything() {
+        // Without remembering which dates turned out empty, this would search
+        // every second of each date over the whole horizon
+        let schedule = "when second 30, except (when second 30)";
+        assert_eq!(next_after(schedule, from_ymdhm(2001, 1, 1, 0, 0)), None);
+        assert_eq!(prev_before(schedule, from_ymdhm(2001, 1, 1, 0, 0)), None);
+        assert!(!Parser::new(schedule)
+            .parse_schedule()
+            .unwrap()
+            .is_satisfiable());
+
+        // Dates some exception doesn't apply on are still searched
+        assert_eq!(
+            next_after(
+                "when second 30, except (when second 30, day 1 to 30)",
+                from_ymdhm(2001, 1, 1, 0, 0)
+            )
+            .map(|next| next.to_string()),
+            Some("2001-01-31 00:00:30 UTC".into())
+        );
+        assert_eq!(
+            prev_before(
+                "when second 30, except (when second 30, day 2 to 31)",
+                from_ymdhm(2001, 3, 15, 0, 0)
+            )
+            .map(|prev| prev.to_string()),
+            Some("2001-03-01 23:59:30 UTC".into())
+        );
+    }
+
+    #[test]
+    fn iterates_over_upcoming_occurrences() {
+        let schedule = Parser::new("when weekday mon to fri, hour 9 or 17, minute 0")
+            .parse_schedule()
+            .unwrap();
+
+        // Friday, 25/05/2001 12h00 in UTC-3
+        let offset = FixedOffset::west_opt(3 * 3600).unwrap();
+        let from = offset.with_ymd_and_hms(2001, 5, 25, 12, 0, 0).unwrap();
+
+        let upcoming: Vec<_> = schedule.upcoming(from).take(3).collect();
+
+        assert_eq!(
+            upcoming,
+            [
+                offset.with_ymd_and_hms(2001, 5, 25, 17, 0, 0).unwrap(),
+                offset.with_ymd_and_hms(2001, 5, 28, 9, 0, 0).unwrap(),
+                offset.with_ymd_and_hms(2001, 5, 28, 17, 0, 0).unwrap(),
+            ]
+        );
+
+        let impossible = Parser::new("when day 30, month feb")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(impossible.upcoming(from).next(), None);
+    }
+
+    /// `hour <hour>` with the given minutes, in `timezone`
+    fn daily(
+        timezone: Tz,
+        hour: u8,
+        minutes: DateTimePart<Minute>,
+        gap: GapPolicy,
+        overlap: OverlapPolicy,
+    ) -> Schedule {
+        Schedule {
+            hour_spec: smallvec![DateTimePart::Single(Hour::new(hour).unwrap())],
+            minute_spec: smallvec![minutes],
+            timezone: Some(timezone),
+            dst_policy: DstPolicy { gap, overlap },
+            ..Schedule::new()
+        }
+    }
+
+    #[test]
+    fn follows_dst_policy_in_new_york() {
+        let half_past = || DateTimePart::Single(Minute::new(30).unwrap());
+
+        // 14/03/2021, clocks went from 02h00 EST straight to 03h00 EDT (07h00 UTC)
+        let skip = daily(
+            Tz::America__New_York,
+            2,
+            half_past(),
+            GapPolicy::Skip,
+            OverlapPolicy::Once,
+        );
+        assert_eq!(
+            skip.next_after(from_ymdhm(2021, 3, 13, 12, 0)),
+            Some(from_ymdhm(2021, 3, 15, 6, 30))
+        );
+
+        let next_valid = daily(
+            Tz::America__New_York,
+            2,
+            half_past(),
+            GapPolicy::NextValid,
+            OverlapPolicy::Once,
+        );
+        assert_eq!(
+            next_valid
+                .upcoming(from_ymdhm(2021, 3, 13, 12, 0))
+                .take(2)
+                .collect::<Vec<_>>(),
+            [
+                from_ymdhm(2021, 3, 14, 7, 0),
+                from_ymdhm(2021, 3, 15, 6, 30)
+            ]
+        );
+        assert_eq!(
+            next_valid.prev_before(from_ymdhm(2021, 3, 15, 0, 0)),
+            Some(from_ymdhm(2021, 3, 14, 7, 0))
+        );
+
+        // 07/11/2021, clocks went from 02h00 EDT back to 01h00 EST, so 01h30
+        // happened at both 05h30 and 06h30 UTC
+        let once = daily(
+            Tz::America__New_York,
+            1,
+            half_past(),
+            GapPolicy::Skip,
+            OverlapPolicy::Once,
+        );
+        assert_eq!(
+            once.upcoming(from_ymdhm(2021, 11, 6, 12, 0))
+                .take(2)
+                .collect::<Vec<_>>(),
+            [
+                from_ymdhm(2021, 11, 7, 5, 30),
+                from_ymdhm(2021, 11, 8, 6, 30)
+            ]
+        );
+        assert_eq!(
+            once.prev_before(from_ymdhm(2021, 11, 7, 12, 0)),
+            Some(from_ymdhm(2021, 11, 7, 5, 30))
+        );
+
+        let twice = daily(
+            Tz::America__New_York,
+            1,
+            half_past(),
+            GapPolicy::Skip,
+            OverlapPolicy::Twice,
+        );
+        assert_eq!(
+            twice
+                .upcoming(from_ymdhm(2021, 11, 6, 12, 0))
+                .take(3)
+                .collect::<Vec<_>>(),
+            [
+                from_ymdhm(2021, 11, 7, 5, 30),
+                from_ymdhm(2021, 11, 7, 6, 30),
+                from_ymdhm(2021, 11, 8, 6, 30)
+            ]
+        );
+        assert_eq!(
+            twice
+                .preceding(from_ymdhm(2021, 11, 7, 12, 0))
+                .take(2)
+                .collect::<Vec<_>>(),
+            [
+                from_ymdhm(2021, 11, 7, 6, 30),
+                from_ymdhm(2021, 11, 7, 5, 30)
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_dst_policy_in_berlin() {
+        let quarters = || DateTimePart::Step {
+            starting: Minute::new(0).unwrap(),
+            step: NonZeroU8::new(15).unwrap(),
+        };
+
+        // 28/03/2021, clocks went from 02h00 CET straight to 03h00 CEST (01h00 UTC):
+        // the four skipped runs happen once, as soon as the clocks moved forward
+        let next_valid = daily(
+            Tz::Europe__Berlin,
+            2,
+            quarters(),
+            GapPolicy::NextValid,
+            OverlapPolicy::Once,
+        );
+        assert_eq!(
+            next_valid
+                .upcoming(from_ymdhm(2021, 3, 27, 23, 0))
+                .take(2)
+                .collect::<Vec<_>>(),
+            [from_ymdhm(2021, 3, 28, 1, 0), from_ymdhm(2021, 3, 29, 0, 0)]
+        );
+
+        // 31/10/2021, clocks went from 03h00 CEST back to 02h00 CET (01h00 UTC)
+        let first_pass = [
+            from_ymdhm(2021, 10, 31, 0, 0),
+            from_ymdhm(2021, 10, 31, 0, 15),
+            from_ymdhm(2021, 10, 31, 0, 30),
+            from_ymdhm(2021, 10, 31, 0, 45),
+        ];
+        let second_pass = [
+            from_ymdhm(2021, 10, 31, 1, 0),
+            from_ymdhm(2021, 10, 31, 1, 15),
+            from_ymdhm(2021, 10, 31, 1, 30),
+            from_ymdhm(2021, 10, 31, 1, 45),
+        ];
+
+        let once = daily(
+            Tz::Europe__Berlin,
+            2,
+            quarters(),
+            GapPolicy::Skip,
+            OverlapPolicy::Once,
+        );
+        assert_eq!(
+            once.upcoming(from_ymdhm(2021, 10, 30, 23, 50))
+                .take(5)
+                .collect::<Vec<_>>(),
+            [&first_pass[..], &[from_ymdhm(2021, 11, 1, 1, 0)]].concat()
+        );
+        // Halfway through the second pass, the last run was in the first one
+        assert_eq!(
+            once.prev_before(from_ymdhm(2021, 10, 31, 1, 10)),
+            Some(from_ymdhm(2021, 10, 31, 0, 45))
+        );
+
+        let twice = daily(
+            Tz::Europe__Berlin,
+            2,
+            quarters(),
+            GapPolicy::Skip,
+            OverlapPolicy::Twice,
+        );
+        let both_passes = [first_pass, second_pass].concat();
+        assert_eq!(
+            twice
+                .upcoming(from_ymdhm(2021, 10, 30, 23, 50))
+                .take(8)
+                .collect::<Vec<_>>(),
+            both_passes
+        );
+        assert_eq!(
+            twice
+                .preceding(from_ymdhm(2021, 10, 31, 2, 0))
+                .take(8)
+                .collect::<Vec<_>>(),
+            both_passes.into_iter().rev().collect::<Vec<_>>()
+        );
+    }
+}
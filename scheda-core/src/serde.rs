@@ -0,0 +1,230 @@
+//! Serializes the numeric date-time parts as the numbers they stand for,
+//! rather than however they're stored, e.g. `Hour` 9 as `9`, and checks the
+//! ranges of [`DateTimePart`]s and the years of [`AbsoluteDate`]s the way the
+//! parser does.
+//!
+//! Everything else derives its implementations behind the `serde` feature.
+
+use std::num::NonZeroU8;
+
+use ::serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::{
+    AbsoluteDate, DateTimePart, Error, Hour, Minute, MonthDay, MonthDayFromEnd, Second, TimeOfDay,
+    WellFormedRange, Year,
+};
+
+macro_rules! serde_as_int {
+    ($type:ty, $int:ty, $expected:literal, $new:expr) => {
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.get().serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = <$int>::deserialize(deserializer)?;
+
+                $new(value).ok_or_else(|| {
+                    de::Error::invalid_value(de::Unexpected::Unsigned(value.into()), &$expected)
+                })
+            }
+        }
+    };
+}
+
+serde_as_int!(Year, u16, "a year between 1 and 9999", Year::new);
+serde_as_int!(Hour, u8, "an hour between 0 and 23", Hour::new);
+serde_as_int!(Minute, u8, "a minute between 0 and 59", Minute::new);
+serde_as_int!(Second, u8, "a second between 0 and 59", Second::new);
+serde_as_int!(
+    MonthDay,
+    u8,
+    "a day of the month between 1 and 31",
+    MonthDay::from_u8
+);
+serde_as_int!(
+    MonthDayFromEnd,
+    u8,
+    "a number of days from the end of the month between 1 and 31",
+    MonthDayFromEnd::from_u8
+);
+
+/// A [`DateTimePart`] as it's serialized, before its range is checked
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum UncheckedPart<T> {
+    Single(T),
+    Range { starting: T, ending: T },
+    WrappingRange { starting: T, ending: T },
+    Step { starting: T, step: NonZeroU8 },
+}
+
+impl<'de, T: Deserialize<'de> + WellFormedRange> Deserialize<'de> for DateTimePart<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let malformed = |reason| de::Error::custom(Error::MalformedRange(reason));
+
+        Ok(match UncheckedPart::deserialize(deserializer)? {
+            UncheckedPart::Single(single) => DateTimePart::Single(single),
+            UncheckedPart::Range { starting, ending } => {
+                let range = starting..ending;
+
+                if !T::is_well_formed(&range) {
+                    let flipped = range.end..range.start;
+
+                    return Err(malformed(match T::is_well_formed(&flipped) {
+                        true => "range's starting point is bigger than its end",
+                        false => {
+                            "range starts and ends at the same value, which is written on its own"
+                        }
+                    }));
+                }
+
+                DateTimePart::Range {
+                    starting: range.start,
+                    ending: range.end,
+                }
+            }
+            UncheckedPart::WrappingRange { starting, ending } => {
+                let flipped = ending..starting;
+
+                if !T::WRAPS_AROUND || !T::is_well_formed(&flipped) {
+                    return Err(malformed(
+                        "wrapping range has to start after its end, in a cycle that wraps around",
+                    ));
+                }
+
+                DateTimePart::WrappingRange {
+                    starting: flipped.end,
+                    ending: flipped.start,
+                }
+            }
+            UncheckedPart::Step { starting, step } => DateTimePart::Step { starting, step },
+        })
+    }
+}
+
+/// An [`AbsoluteDate`] as it's serialized, before its year is checked
+#[derive(Deserialize)]
+struct UncheckedDate {
+    date: NaiveDate,
+    time: Option<TimeOfDay>,
+}
+
+impl<'de> Deserialize<'de> for AbsoluteDate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let UncheckedDate { date, time } = UncheckedDate::deserialize(deserializer)?;
+
+        if Year::of(date).is_none() {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Signed(date.year().into()),
+                &"a date between the years 1 and 9999",
+            ));
+        }
+
+        Ok(AbsoluteDate { date, time })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scheda_parser::Parser;
+
+    use crate::{Schedule, ScheduleExpr};
+
+    fn round_trip(schedule: &str) -> String {
+        let json = serde_json::to_string(&Parser::new(schedule).parse_schedule().unwrap()).unwrap();
+
+        serde_json::from_str::<Schedule>(&json).unwrap().to_string()
+    }
+
+    #[test]
+    fn round_trips_structured_schedules() {
+        for schedule in [
+            "when",
+            "when month nov to feb, day 1 or last, hour 9, minute every 15 from 5",
+            "when weekday mon to fri or second sat, at 14:30 or 18:00, combine or",
+            "when hour 2, minute 30, dst skip, dst twice in America/New_York",
+            "when minute 0, second every 10",
+            "when year 2027 to 2028, on 2026-12-24 23:00 or 2027-01-01",
+            "when hour 9, except month dec, except (when weekday sat, second 0 to 29)",
+        ] {
+            assert_eq!(round_trip(schedule), schedule);
+        }
+    }
+
+    #[test]
+    fn round_trips_expressions() {
+        let input = "((when hour 9) or (when weekday sat in UTC)) but not (when month dec)";
+        let expr = Parser::new(input).parse_expr().unwrap();
+        let json = serde_json::to_string(&expr).unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<ScheduleExpr>(&json)
+                .unwrap()
+                .to_string(),
+            input
+        );
+    }
+
+    #[test]
+    fn serializes_parts_as_their_values() {
+        let schedule = Parser::new("when hour 22 to 4, weekday last fri, day -3 in Europe/Berlin")
+            .parse_schedule()
+            .unwrap();
+        let json = serde_json::to_value(&schedule).unwrap();
+
+        assert_eq!(
+            json["hour_spec"],
+            serde_json::json!([{ "wrapping_range": { "starting": 22, "ending": 4 } }])
+        );
+        assert_eq!(
+            json["nth_weekday_spec"],
+            serde_json::json!([{ "ordinal": "last", "weekday": "Fri" }])
+        );
+        assert_eq!(json["month_end_spec"], serde_json::json!([{ "single": 3 }]));
+        assert_eq!(json["timezone"], "Europe/Berlin");
+    }
+
+    #[test]
+    fn fills_in_missing_specs_and_rejects_invalid_values() {
+        let schedule: Schedule =
+            serde_json::from_str(r#"{ "hour_spec": [{ "single": 9 }] }"#).unwrap();
+        assert_eq!(schedule.to_string(), "when hour 9");
+
+        for json in [
+            r#"{ "hour_spec": [{ "single": 24 }] }"#,
+            r#"{ "minute_spec": [{ "single": 60 }] }"#,
+            r#"{ "second_spec": [{ "single": 60 }] }"#,
+            r#"{ "day_spec": [{ "single": 0 }] }"#,
+            r#"{ "year_spec": [{ "single": 10000 }] }"#,
+            r#"{ "date_spec": [{ "date": "2026-02-30" }] }"#,
+            r#"{ "date_spec": [{ "date": "0000-01-01" }] }"#,
+            r#"{ "timezone": "Mars/Olympus_Mons" }"#,
+            r#"{ "day_spec": [{ "range": { "starting": 20, "ending": 10 } }] }"#,
+            r#"{ "hour_spec": [{ "range": { "starting": 9, "ending": 9 } }] }"#,
+            r#"{ "year_spec": [{ "range": { "starting": 2028, "ending": 2027 } }] }"#,
+            r#"{ "hour_spec": [{ "wrapping_range": { "starting": 4, "ending": 22 } }] }"#,
+            r#"{ "day_spec": [{ "wrapping_range": { "starting": 20, "ending": 10 } }] }"#,
+        ] {
+            assert!(serde_json::from_str::<Schedule>(json).is_err(), "{json}");
+        }
+
+        let error = |json| {
+            serde_json::from_str::<Schedule>(json)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(
+            error(r#"{ "day_spec": [{ "range": { "starting": 20, "ending": 10 } }] }"#)
+                .contains("starting point is bigger than its end")
+        );
+        assert!(
+            error(r#"{ "hour_spec": [{ "range": { "starting": 9, "ending": 9 } }] }"#)
+                .contains("starts and ends at the same value")
+        );
+    }
+}
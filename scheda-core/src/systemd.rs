@@ -0,0 +1,374 @@
+//! Writes schedules out as systemd calendar events, as used by `OnCalendar=`.
+//!
+//! Events are written in the normalized form `systemd-analyze calendar` prints,
+//! e.g. `Mon..Fri *-*-* 09:00:00 Europe/Berlin`. Anything that can't be said
+//! that way, such as `combine or`, is an [`Error::Unsupported`].
+
+use chrono::Weekday;
+use num_traits::FromPrimitive;
+
+use crate::{
+    cron::runs, schedule::contains_element, AsU8, DateTimePart, DstPolicy, Error, MonthDayFromEnd,
+    Ordinal, Result, Schedule, Year,
+};
+
+/// Writes a component whose values go from `min` to `max`, e.g. `01..05,10` or `00/15`
+fn systemd_field<T: AsU8>(parts: &[DateTimePart<T>], min: u8, max: u8) -> String {
+    if parts.is_empty() {
+        return "*".into();
+    }
+
+    let parts: Vec<_> = parts
+        .iter()
+        .map(|part| match part {
+            DateTimePart::Single(single) => format!("{:02}", single.as_u8()),
+            DateTimePart::Range { starting, ending } => {
+                format!("{:02}..{:02}", starting.as_u8(), ending.as_u8())
+            }
+            DateTimePart::WrappingRange { starting, ending } => format!(
+                "{:02}..{max:02},{min:02}..{:02}",
+                starting.as_u8(),
+                ending.as_u8()
+            ),
+            DateTimePart::Step { starting, step } => format!("{:02}/{step}", starting.as_u8()),
+        })
+        .collect();
+
+    parts.join(",")
+}
+
+/// Writes the year component, e.g. `2027..2028` or `2026/2`
+fn systemd_years(parts: &[DateTimePart<Year>]) -> String {
+    if parts.is_empty() {
+        return "*".into();
+    }
+
+    let parts: Vec<_> = parts
+        .iter()
+        .map(|part| match part {
+            DateTimePart::Single(single) => format!("{:04}", single.get()),
+            DateTimePart::Range { starting, ending } => {
+                format!("{:04}..{:04}", starting.get(), ending.get())
+            }
+            DateTimePart::WrappingRange { starting, ending } => format!(
+                "{:04}..{:04},{:04}..{:04}",
+                starting.get(),
+                Year::MAX.get(),
+                Year::MIN.get(),
+                ending.get()
+            ),
+            DateTimePart::Step { starting, step } => format!("{:04}/{step}", starting.get()),
+        })
+        .collect();
+
+    parts.join(",")
+}
+
+/// Writes every value in `values` as a component, e.g. `09,12..14`
+fn systemd_list(values: impl IntoIterator<Item = u8>) -> String {
+    let runs: Vec<_> = runs(values)
+        .into_iter()
+        .map(|(start, end)| {
+            if start == end {
+                format!("{start:02}")
+            } else {
+                format!("{start:02}..{end:02}")
+            }
+        })
+        .collect();
+
+    runs.join(",")
+}
+
+/// Writes every weekday `contains` holds for, Monday first, e.g. `Mon..Fri,Sun`
+fn systemd_weekdays(contains: impl Fn(Weekday) -> bool) -> String {
+    let weekday = |idx| Weekday::from_u8(idx).unwrap();
+
+    let runs: Vec<_> = runs((0..7).filter(|&idx| contains(weekday(idx))))
+        .into_iter()
+        .map(|(start, end)| match end - start {
+            0 => weekday(start).to_string(),
+            1 => format!("{},{}", weekday(start), weekday(end)),
+            _ => format!("{}..{}", weekday(start), weekday(end)),
+        })
+        .collect();
+
+    runs.join(",")
+}
+
+impl Schedule {
+    /// Writes this schedule as a systemd calendar event, e.g. `Mon..Fri *-*-* 09:00:00`.
+    ///
+    /// Fails with [`Error::Unsupported`] if systemd has no way of saying the
+    /// same thing, e.g. for schedules firing on either a day or a weekday.
+    pub fn to_systemd(&self) -> Result<String> {
+        if self.dst_policy != DstPolicy::default() {
+            return Err(Error::Unsupported(
+                "systemd has no way of changing how DST transitions are handled".into(),
+            ));
+        }
+        if self.either_day() {
+            return Err(Error::Unsupported(
+                "systemd only fires when both the day and the weekday match".into(),
+            ));
+        }
+        if !self.exceptions.is_empty() {
+            return Err(Error::Unsupported(
+                "systemd calendar events have no exceptions".into(),
+            ));
+        }
+
+        let (weekdays, days) = if self.nth_weekday_spec.is_empty() {
+            let weekdays = (!self.weekday_spec.is_empty()).then(|| {
+                systemd_weekdays(|weekday| contains_element(&self.weekday_spec, &weekday))
+            });
+
+            (weekdays, self.systemd_days()?)
+        } else {
+            self.systemd_nth_weekdays()?
+        };
+
+        let restricts_times = !self.hour_spec.is_empty()
+            || !self.minute_spec.is_empty()
+            || !self.time_spec.is_empty();
+        let date = match self.date_spec.as_slice() {
+            [] => format!(
+                "{}-{}{days}",
+                systemd_years(&self.year_spec),
+                systemd_field(&self.month_spec, 1, 12)
+            ),
+            [on] if self.year_spec.is_empty()
+                && self.month_spec.is_empty()
+                && !self.restricts_days()
+                && self.nth_weekday_spec.is_empty()
+                && (on.time.is_none() || !restricts_times) =>
+            {
+                on.date.format("%Y-%m-%d").to_string()
+            }
+            _ => {
+                return Err(Error::Unsupported(
+                    "systemd can only fire on a single one-off date, without any other \
+                     years, months, days or, if it has a time, times"
+                        .into(),
+                ))
+            }
+        };
+
+        let (hours, minutes) = if let Some(time) = self.date_spec.first().and_then(|on| on.time) {
+            (
+                format!("{:02}", time.hour.get()),
+                format!("{:02}", time.minute.get()),
+            )
+        } else if self.time_spec.is_empty() {
+            (
+                systemd_field(&self.hour_spec, 0, 23),
+                systemd_field(&self.minute_spec, 0, 59),
+            )
+        } else {
+            let (hours, minutes) = self.times_as_grid().ok_or_else(|| {
+                Error::Unsupported(
+                    "systemd can only fire on the same minutes of every hour it fires on".into(),
+                )
+            })?;
+
+            (systemd_list(hours), systemd_list(minutes))
+        };
+
+        // Calendar events without seconds fire on the first one, like schedules
+        let seconds = if self.second_spec.is_empty() {
+            "00".into()
+        } else {
+            systemd_field(&self.second_spec, 0, 59)
+        };
+
+        let mut event = String::new();
+        if let Some(weekdays) = weekdays {
+            event += &weekdays;
+            event += " ";
+        }
+        event += &format!("{date} {hours}:{minutes}:{seconds}");
+        if let Some(timezone) = &self.timezone {
+            event += " ";
+            event += timezone.name();
+        }
+
+        Ok(event)
+    }
+
+    /// The days of the month, along with the `-` or `~` that comes before them
+    fn systemd_days(&self) -> Result<String> {
+        if self.month_end_spec.is_empty() {
+            return Ok(format!("-{}", systemd_field(&self.day_spec, 1, 31)));
+        }
+
+        if !self.day_spec.is_empty() {
+            return Err(Error::Unsupported(
+                "systemd can't count days from both ends of the month at once".into(),
+            ));
+        }
+
+        let days: Vec<_> = (1..=31)
+            .rev()
+            .filter(|&days| {
+                let from_end = MonthDayFromEnd::from_u8(days).unwrap();
+
+                self.month_end_spec
+                    .iter()
+                    .any(|part| part.contains(&from_end))
+            })
+            .collect();
+
+        Ok(match days.as_slice() {
+            // Repeating every day from the `first`-to-last day of the month
+            [first, .., 1] if usize::from(*first) == days.len() => format!("~{first:02}/1"),
+            days => {
+                let days: Vec<_> = days.iter().map(|days| format!("{days:02}")).collect();
+
+                format!("~{}", days.join(","))
+            }
+        })
+    }
+
+    /// The weekdays and days of the month nth weekdays stand for, e.g. `Tue`
+    /// and `-08..14` for the second Tuesday of the month
+    fn systemd_nth_weekdays(&self) -> Result<(Option<String>, String)> {
+        let ordinal = self.nth_weekday_spec[0].ordinal;
+
+        if self.restricts_days()
+            || !self.weekday_spec.is_empty()
+            || self
+                .nth_weekday_spec
+                .iter()
+                .any(|nth_weekday| nth_weekday.ordinal != ordinal)
+        {
+            return Err(Error::Unsupported(
+                "systemd can only express nth weekdays that are all the same occurrence \
+                 within the month, without any other days"
+                    .into(),
+            ));
+        }
+
+        let days = match ordinal {
+            Ordinal::First => "-01..07",
+            Ordinal::Second => "-08..14",
+            Ordinal::Third => "-15..21",
+            Ordinal::Fourth => "-22..28",
+            Ordinal::Fifth => "-29..31",
+            Ordinal::Last => "~07/1",
+        };
+        let weekdays = systemd_weekdays(|weekday| {
+            self.nth_weekday_spec
+                .iter()
+                .any(|nth_weekday| nth_weekday.weekday == weekday)
+        });
+
+        Ok((Some(weekdays), days.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scheda_parser::Parser;
+
+    fn to_systemd(schedule: &str) -> Result<String, &'static str> {
+        Parser::new(schedule)
+            .parse_schedule()
+            .unwrap()
+            .to_systemd()
+            .map_err(|err| err.code())
+    }
+
+    #[test]
+    fn writes_calendar_events() {
+        assert_eq!(to_systemd("when").unwrap(), "*-*-* *:*:00");
+        assert_eq!(to_systemd("when minute 0").unwrap(), "*-*-* *:00:00");
+        assert_eq!(
+            to_systemd("when hour 9, minute 0, weekday mon to fri").unwrap(),
+            "Mon..Fri *-*-* 09:00:00"
+        );
+        assert_eq!(
+            to_systemd("when day 1, hour 0, minute 0").unwrap(),
+            "*-*-01 00:00:00"
+        );
+        assert_eq!(
+            to_systemd("when every 15 minutes, weekday sat to mon, month nov to feb").unwrap(),
+            "Mon,Sat,Sun *-11..12,01..02-* *:00/15:00"
+        );
+        assert_eq!(
+            to_systemd("when at 9:15 or 17:15 or 10:15 in Europe/Berlin").unwrap(),
+            "*-*-* 09..10,17:15:00 Europe/Berlin"
+        );
+        assert_eq!(
+            to_systemd("when every 10 seconds, hour 9").unwrap(),
+            "*-*-* 09:*:00/10"
+        );
+    }
+
+    #[test]
+    fn writes_years_and_one_off_dates() {
+        assert_eq!(
+            to_systemd("when year 2027 to 2028, month jan, day 1, at 0:00").unwrap(),
+            "2027..2028-01-01 00:00:00"
+        );
+        assert_eq!(
+            to_systemd("when year every 4 from 2028, weekday mon, hour 9, minute 0").unwrap(),
+            "Mon 2028/4-*-* 09:00:00"
+        );
+        assert_eq!(
+            to_systemd("when on 2026-12-24 23:00").unwrap(),
+            "2026-12-24 23:00:00"
+        );
+        assert_eq!(
+            to_systemd("when on 2026-12-24, weekday thu, every 30 minutes").unwrap(),
+            "Thu 2026-12-24 *:00/30:00"
+        );
+
+        assert_eq!(
+            to_systemd("when on 2026-12-24 or 2026-12-31"),
+            Err("unsupported")
+        );
+        assert_eq!(
+            to_systemd("when on 2026-12-24 23:00, hour 23"),
+            Err("unsupported")
+        );
+    }
+
+    #[test]
+    fn writes_days_from_month_end() {
+        assert_eq!(
+            to_systemd("when month feb, day -3, at 0:00").unwrap(),
+            "*-02~03 00:00:00"
+        );
+        assert_eq!(
+            to_systemd("when day -7 to last, weekday mon, at 0:00").unwrap(),
+            "Mon *-*~07/1 00:00:00"
+        );
+        assert_eq!(
+            to_systemd("when weekday last fri or last sat, at 18:00").unwrap(),
+            "Fri,Sat *-*~07/1 18:00:00"
+        );
+        assert_eq!(
+            to_systemd("when weekday second tue, at 18:00").unwrap(),
+            "Tue *-*-08..14 18:00:00"
+        );
+    }
+
+    #[test]
+    fn refuses_what_systemd_cant_express() {
+        assert_eq!(
+            to_systemd("when day 1, weekday mon, combine or"),
+            Err("unsupported")
+        );
+        assert_eq!(to_systemd("when day 1 or last"), Err("unsupported"));
+        assert_eq!(
+            to_systemd("when weekday second tue or last fri"),
+            Err("unsupported")
+        );
+        assert_eq!(to_systemd("when at 9:15 or 10:30"), Err("unsupported"));
+        assert_eq!(to_systemd("when hour 2, dst skip"), Err("unsupported"));
+        assert_eq!(
+            to_systemd("when hour 9, except month dec"),
+            Err("unsupported")
+        );
+    }
+}
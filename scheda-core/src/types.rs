@@ -1,84 +1,292 @@
-use std::{num::NonZeroU8, ops::Range};
+use std::{cmp::Ordering, num::NonZeroU8};
 
-use crate::{as_u8::AsU8, Error, Result};
+use chrono::{Datelike, NaiveDate, Weekday};
 
-#[derive(Debug)]
-pub struct Hour(NonZeroU8);
+use crate::{as_u8::AsU8, BoundedU8, Error, Result};
 
-impl Hour {
-    pub fn new(val: u8) -> Option<Self> {
-        (val <= 24).then(|| {
-            // Safety: val+1 can never be zero once val is unsigned
-            Self(unsafe { NonZeroU8::new_unchecked(val + 1) })
-        })
+/// Defines a newtype around a [`BoundedU8`] from `$min` to `$max`, built by `$new`
+macro_rules! bounded_u8 {
+    ($(#[$attr:meta])* $name:ident($min:literal, $max:literal), $new:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(pub(crate) BoundedU8<$min, $max>);
+
+        impl $name {
+            pub const MIN: Self = Self(BoundedU8::MIN);
+            pub const MAX: Self = Self(BoundedU8::MAX);
+
+            pub fn $new(val: u8) -> Option<Self> {
+                BoundedU8::new(val).map(Self)
+            }
+
+            pub const fn get(&self) -> u8 {
+                self.0.get()
+            }
+        }
+    };
+}
+
+bounded_u8!(Hour(0, 23), new);
+bounded_u8!(Minute(0, 59), new);
+bounded_u8!(Second(0, 59), new);
+bounded_u8!(MonthDay(1, 31), from_u8);
+bounded_u8!(
+    /// A day of the month counted back from its end, where 1 is the last day.
+    ///
+    /// E.g. `when day last` or `when day -3`
+    MonthDayFromEnd(1, 31),
+    from_u8
+);
+
+impl MonthDayFromEnd {
+    /// Where `date` falls in its month, counting back from the end
+    pub fn of(date: NaiveDate) -> Self {
+        Self::from_u8(days_in_month(date) - date.day() as u8 + 1).unwrap()
+    }
+}
+
+/// A year of the Gregorian calendar, from 1 to 9999 like in ISO 8601.
+///
+/// E.g. `when year 2027 to 2028`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Year(pub(crate) u16);
+
+impl Year {
+    pub const MIN: Self = Self(1);
+    pub const MAX: Self = Self(9999);
+
+    pub fn new(val: u16) -> Option<Self> {
+        (Self::MIN.0..=Self::MAX.0)
+            .contains(&val)
+            .then_some(Self(val))
     }
 
-    pub fn get(&self) -> u8 {
-        // Cannot underflow
-        self.0.get() - 1
+    /// The year `date` falls in, if it's one a schedule can name
+    pub fn of(date: NaiveDate) -> Option<Self> {
+        u16::try_from(date.year()).ok().and_then(Self::new)
     }
+
+    pub const fn get(&self) -> u16 {
+        self.0
+    }
+}
+
+/// How many days the month `date` is in has
+pub(crate) fn days_in_month(date: NaiveDate) -> u8 {
+    match date.month() {
+        2 if NaiveDate::from_ymd_opt(date.year(), 2, 29).is_some() => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Which occurrence of a weekday within its month
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Ordinal {
+    First,
+    Second,
+    Third,
+    Fourth,
+    Fifth,
+    Last,
+}
+
+impl Ordinal {
+    /// Which occurrence of its weekday `date` is, counting from the start of the month
+    pub fn of(date: NaiveDate) -> Self {
+        match (date.day() - 1) / 7 {
+            0 => Self::First,
+            1 => Self::Second,
+            2 => Self::Third,
+            3 => Self::Fourth,
+            _ => Self::Fifth,
+        }
+    }
+}
+
+/// A weekday that only matches on one of its occurrences within the month.
+///
+/// E.g. `when weekday second tue or last fri`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NthWeekday {
+    pub ordinal: Ordinal,
+    pub weekday: Weekday,
 }
 
-#[derive(Debug)]
-pub struct Minute(NonZeroU8);
+/// [`Weekday`] isn't ordered, so weekdays are put in the order they're
+/// counted in by [`AsU8`], from Sunday
+impl Ord for NthWeekday {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.ordinal, self.weekday.as_u8()).cmp(&(other.ordinal, other.weekday.as_u8()))
+    }
+}
 
-impl Minute {
-    pub fn new(val: u8) -> Option<Self> {
-        (val <= 60).then(|| {
-            // Safety: val+1 can never be zero once val is unsigned
-            Self(unsafe { NonZeroU8::new_unchecked(val + 1) })
-        })
+impl PartialOrd for NthWeekday {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
+}
 
-    pub fn get(&self) -> u8 {
-        // Cannot underflow
-        self.0.get() - 1
+impl NthWeekday {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        date.weekday() == self.weekday
+            && match self.ordinal {
+                Ordinal::Last => date.day() as u8 + 7 > days_in_month(date),
+                nth => Ordinal::of(date) == nth,
+            }
     }
 }
 
-#[derive(Debug)]
-pub struct MonthDay(u8);
+/// An hour and a minute that only match together.
+///
+/// E.g. `when at 14:30 or 18:00`, which unlike `hour 14 or 18, minute 0 or 30`
+/// doesn't also fire at 14:00 and 18:30
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeOfDay {
+    pub hour: Hour,
+    pub minute: Minute,
+}
 
-impl MonthDay {
-    pub fn from_u8(day: u8) -> Option<Self> {
-        (day > 0 && day <= 31).then_some(Self(day))
+impl TimeOfDay {
+    pub fn is(&self, hour: &Hour, minute: &Minute) -> bool {
+        self.hour.get() == hour.get() && self.minute.get() == minute.get()
     }
+}
 
-    pub fn get(&self) -> u8 {
-        self.0
+/// A single date, optionally at a time of day of its own.
+///
+/// E.g. `when on 2026-12-24 23:00`, which fires once and then never again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AbsoluteDate {
+    pub date: NaiveDate,
+    pub time: Option<TimeOfDay>,
+}
+
+impl AbsoluteDate {
+    pub fn is(&self, date: NaiveDate, hour: &Hour, minute: &Minute) -> bool {
+        self.date == date && self.time.is_none_or(|time| time.is(hour, minute))
     }
 }
 
+/// How the day and weekday specs of a schedule are combined.
+///
+/// Cron ORs them when both are restricted, so that `0 0 1 * mon` fires on the
+/// first of the month and on every Monday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum DayCombination {
+    /// Both have to match, e.g. `when day 13, weekday fri` only fires on Friday the 13th
+    #[default]
+    And,
+    /// Either has to match when both are restricted, e.g. `when day 1, weekday mon, combine or`
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DateTimePartKind {
+    Year,
     Month,
     Day,
     Hour,
     Minute,
+    Second,
     Every,
     Weekday,
+    At,
+    On,
+    Combine,
+    Dst,
+    Except,
 }
 
 impl DateTimePartKind {
     pub fn parse(value: &str) -> Result<Self> {
         match value {
+            _x if value.eq_ignore_ascii_case("year") => Ok(Self::Year),
             _x if value.eq_ignore_ascii_case("month") => Ok(Self::Month),
             _x if value.eq_ignore_ascii_case("day") => Ok(Self::Day),
             _x if value.eq_ignore_ascii_case("weekday") => Ok(Self::Weekday),
             _x if value.eq_ignore_ascii_case("hour") => Ok(Self::Hour),
             _x if value.eq_ignore_ascii_case("minute") => Ok(Self::Minute),
+            _x if value.eq_ignore_ascii_case("second") => Ok(Self::Second),
             _x if value.eq_ignore_ascii_case("every") => Ok(Self::Every),
+            _x if value.eq_ignore_ascii_case("at") => Ok(Self::At),
+            _x if value.eq_ignore_ascii_case("on") => Ok(Self::On),
+            _x if value.eq_ignore_ascii_case("combine") => Ok(Self::Combine),
+            _x if value.eq_ignore_ascii_case("dst") => Ok(Self::Dst),
+            _x if value.eq_ignore_ascii_case("except") => Ok(Self::Except),
             _ => Err(Error::UnknownDateTimePart(value.into())),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 /// Either a single date-time part or a range of them.
 ///
 /// E.g. `when day 2` or `when weekday Thu to Fri`
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum DateTimePart<T> {
     Single(T),
-    Range { starting: T, ending: T },
+    Range {
+        starting: T,
+        ending: T,
+    },
+    /// A range that goes past the end of its cycle and starts over.
+    ///
+    /// E.g. `when hour 22 to 4` or `when weekday fri to mon`
+    WrappingRange {
+        starting: T,
+        ending: T,
+    },
+    /// Every `step`-th value, counting up from `starting`.
+    ///
+    /// E.g. `when every 15 minutes` or `when month every 3 from feb`
+    Step {
+        starting: T,
+        step: NonZeroU8,
+    },
+}
+
+impl<T> DateTimePart<T> {
+    pub fn is_step(&self) -> bool {
+        matches!(self, DateTimePart::Step { .. })
+    }
+
+    /// The same part over `f` of its values
+    pub(crate) fn map<U>(self, f: impl Fn(T) -> U) -> DateTimePart<U> {
+        match self {
+            DateTimePart::Single(single) => DateTimePart::Single(f(single)),
+            DateTimePart::Range { starting, ending } => DateTimePart::Range {
+                starting: f(starting),
+                ending: f(ending),
+            },
+            DateTimePart::WrappingRange { starting, ending } => DateTimePart::WrappingRange {
+                starting: f(starting),
+                ending: f(ending),
+            },
+            DateTimePart::Step { starting, step } => DateTimePart::Step {
+                starting: f(starting),
+                step,
+            },
+        }
+    }
 }
 
 impl<T: AsU8> DateTimePart<T> {
@@ -88,6 +296,31 @@ impl<T: AsU8> DateTimePart<T> {
             DateTimePart::Range { starting, ending } => {
                 (starting.as_u8()..=ending.as_u8()).contains(&other.as_u8())
             }
+            DateTimePart::WrappingRange { starting, ending } => {
+                other.as_u8() >= starting.as_u8() || other.as_u8() <= ending.as_u8()
+            }
+            DateTimePart::Step { starting, step } => other
+                .as_u8()
+                .checked_sub(starting.as_u8())
+                .is_some_and(|offset| offset % step.get() == 0),
+        }
+    }
+}
+
+/// Years don't fit in a `u8`, so they're compared as they are instead of
+/// through [`AsU8`]
+impl DateTimePart<Year> {
+    pub fn contains(&self, other: &Year) -> bool {
+        match self {
+            DateTimePart::Single(single) => single == other,
+            DateTimePart::Range { starting, ending } => (starting..=ending).contains(&other),
+            DateTimePart::WrappingRange { starting, ending } => {
+                other >= starting || other <= ending
+            }
+            DateTimePart::Step { starting, step } => other
+                .get()
+                .checked_sub(starting.get())
+                .is_some_and(|offset| offset % u16::from(step.get()) == 0),
         }
     }
 }
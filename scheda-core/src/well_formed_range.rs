@@ -2,37 +2,87 @@ use std::ops::Range;
 
 use chrono::{Month, Weekday};
 
-use crate::{as_u8::AsU8, types::MonthDay, Hour, Minute};
+use crate::{as_u8::AsU8, types::MonthDay, Hour, Minute, MonthDayFromEnd, Second, Year};
 
 pub trait WellFormedRange: Sized {
+    /// Whether a range starting after its end may wrap around the end of the
+    /// cycle, e.g. `hour 22 to 4`
+    const WRAPS_AROUND: bool;
+
+    /// Where the cycle starts, which is also where steps count from unless
+    /// told otherwise
+    const FIRST: Self;
+
     fn is_well_formed(range: &Range<Self>) -> bool;
 }
 
+impl WellFormedRange for Year {
+    const WRAPS_AROUND: bool = false;
+    const FIRST: Self = Year::MIN;
+
+    fn is_well_formed(range: &Range<Self>) -> bool {
+        range.start < range.end
+    }
+}
+
 impl WellFormedRange for Month {
+    const WRAPS_AROUND: bool = true;
+    const FIRST: Self = Month::January;
+
     fn is_well_formed(range: &Range<Self>) -> bool {
         range.start.number_from_month() < range.end.number_from_month()
     }
 }
 
 impl WellFormedRange for MonthDay {
+    const WRAPS_AROUND: bool = false;
+    const FIRST: Self = MonthDay::MIN;
+
+    fn is_well_formed(range: &std::ops::Range<Self>) -> bool {
+        range.start.as_u8() < range.end.as_u8()
+    }
+}
+
+impl WellFormedRange for MonthDayFromEnd {
+    const WRAPS_AROUND: bool = false;
+    const FIRST: Self = MonthDayFromEnd::MAX;
+
     fn is_well_formed(range: &std::ops::Range<Self>) -> bool {
         range.start.as_u8() < range.end.as_u8()
     }
 }
 
 impl WellFormedRange for Hour {
+    const WRAPS_AROUND: bool = true;
+    const FIRST: Self = Hour::MIN;
+
     fn is_well_formed(range: &std::ops::Range<Self>) -> bool {
         range.start.as_u8() < range.end.as_u8()
     }
 }
 
 impl WellFormedRange for Minute {
+    const WRAPS_AROUND: bool = true;
+    const FIRST: Self = Minute::MIN;
+
+    fn is_well_formed(range: &std::ops::Range<Self>) -> bool {
+        range.start.as_u8() < range.end.as_u8()
+    }
+}
+
+impl WellFormedRange for Second {
+    const WRAPS_AROUND: bool = true;
+    const FIRST: Self = Second::MIN;
+
     fn is_well_formed(range: &std::ops::Range<Self>) -> bool {
         range.start.as_u8() < range.end.as_u8()
     }
 }
 
 impl WellFormedRange for Weekday {
+    const WRAPS_AROUND: bool = true;
+    const FIRST: Self = Weekday::Sun;
+
     fn is_well_formed(range: &std::ops::Range<Self>) -> bool {
         range.start.num_days_from_sunday() < range.end.num_days_from_sunday()
     }
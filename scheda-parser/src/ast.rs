@@ -0,0 +1,102 @@
+//! The syntax tree of a schedule, before its atoms are given any meaning.
+//!
+//! Every node keeps the span of the input it was parsed from, so that later
+//! stages can point back at it.
+
+use scheda_core::{DateTimePartKind, Span};
+
+/// `when <clause>, <clause>, ... [in <time zone>]`
+#[derive(Debug)]
+pub struct Schedule<'a> {
+    pub clauses: Vec<Clause<'a>>,
+    pub timezone: Option<Atom<'a>>,
+}
+
+/// Schedules combined with `and`, `or`, `but not` and `not`, each between
+/// parentheses, e.g. `(when weekday mon to fri, at 9:00) or (when at 12:00)`
+#[derive(Debug)]
+pub enum Expr<'a> {
+    Schedule(Schedule<'a>),
+    And(Box<Expr<'a>>, Box<Expr<'a>>),
+    Or(Box<Expr<'a>>, Box<Expr<'a>>),
+    Not(Box<Expr<'a>>),
+}
+
+/// A date-time part and the values it may take, e.g. `month jan or mar to may`
+#[derive(Debug)]
+pub struct Clause<'a> {
+    pub kind: DateTimePartKind,
+    pub items: Vec<Item<'a>>,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum Item<'a> {
+    /// `jan`
+    Single(Atom<'a>),
+    /// `jan to mar`
+    Range {
+        starting: Atom<'a>,
+        ending: Atom<'a>,
+        span: Span,
+    },
+    /// `15 minutes` or `hour`, only found in `every` clauses
+    Step {
+        count: Option<Atom<'a>>,
+        unit: Atom<'a>,
+    },
+    /// `every 15` or `every 3 from feb`, a step through a clause's own values
+    Stepped {
+        step: Atom<'a>,
+        from: Option<Atom<'a>>,
+        span: Span,
+    },
+    /// `second tue` or `last fri`, only found in `weekday` clauses
+    Nth {
+        ordinal: Atom<'a>,
+        weekday: Atom<'a>,
+        span: Span,
+    },
+    /// `14:30` or `2:30 pm`, only found in `at` clauses
+    Time {
+        hour: Atom<'a>,
+        minute: Atom<'a>,
+        meridiem: Option<Atom<'a>>,
+        span: Span,
+    },
+    /// `2026-12-24`, `2026-12-24 23:00` or `2026-12-24T23:00`, only found in
+    /// `on` clauses
+    Date {
+        date: Atom<'a>,
+        time: Option<(Atom<'a>, Atom<'a>)>,
+        span: Span,
+    },
+    /// `month dec` or `(when weekday sat, hour 9)`, only found in `except` clauses
+    Nested { schedule: Schedule<'a>, span: Span },
+}
+
+/// A single word or number
+#[derive(Debug, Clone)]
+pub struct Atom<'a> {
+    pub text: &'a str,
+    pub span: Span,
+}
+
+impl Item<'_> {
+    pub fn span(&self) -> Span {
+        match self {
+            Item::Single(atom) => atom.span.clone(),
+            Item::Range { span, .. }
+            | Item::Stepped { span, .. }
+            | Item::Nth { span, .. }
+            | Item::Time { span, .. }
+            | Item::Date { span, .. }
+            | Item::Nested { span, .. } => span.clone(),
+            Item::Step { count, unit } => {
+                let start = count.as_ref().unwrap_or(unit).span.start;
+
+                start..unit.span.end
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Serializes schedules as the `when` syntax they're parsed from, e.g.
+//! `"when hour 9, weekday mon to fri"`, for use with `#[serde(with)]`:
+//!
+//! ```no-rust
+//! #[derive(Serialize, Deserialize)]
+//! struct Job {
+//!     #[serde(with = "scheda_parser::compact")]
+//!     schedule: Schedule,
+//! }
+//! ```
+//!
+//! This is much terser than the structured form `scheda-core`'s own `serde`
+//! feature derives, at the cost of parsing schedules again when they're read.
+
+use serde::{de, Deserialize, Deserializer, Serializer};
+
+use scheda_core::{Error, Schedule};
+
+use crate::Parser;
+
+pub fn serialize<S: Serializer>(schedule: &Schedule, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(schedule)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Schedule, D::Error> {
+    let schedule = String::deserialize(deserializer)?;
+
+    Parser::new(&schedule)
+        .parse_schedule()
+        .map_err(|err| match err {
+            Error::Parse(diagnostics) => {
+                let errors: Vec<_> = diagnostics
+                    .iter()
+                    .map(|diagnostic| diagnostic.report.to_string())
+                    .collect();
+
+                de::Error::custom(errors.join("; "))
+            }
+            err => de::Error::custom(err),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use scheda_core::Schedule;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct Job {
+        #[serde(with = "crate::compact")]
+        schedule: Schedule,
+    }
+
+    #[test]
+    fn round_trips_schedules_as_strings() {
+        let json = r#"{"schedule":"when weekday mon to fri, hour 9, minute 30 in Europe/Berlin"}"#;
+        let job: Job = serde_json::from_str(json).unwrap();
+
+        assert_eq!(job.schedule.hour_spec.len(), 1);
+        assert_eq!(serde_json::to_string(&job).unwrap(), json);
+    }
+
+    #[test]
+    fn reports_every_parse_error() {
+        let err = serde_json::from_str::<Job>(r#"{"schedule":"when hour 25, month foo"}"#)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "expected an hour between 0 and 23, found `25`; \
+             expected a month, found `foo` at line 1 column 38"
+        );
+    }
+}
@@ -0,0 +1,820 @@
+//! Imports crontab expressions, such as `*/5 * * * 1-5`, into [`Schedule`]s.
+//!
+//! Both five-field (`minute hour day month weekday`) and six-field expressions,
+//! which start with seconds, are understood, as are the `@daily`-style macros.
+//! Besides lists, ranges, steps and names, days may be `L` for the last day of
+//! the month, and weekdays may be `5L` or `2#3` for the last Friday or the third
+//! Tuesday of the month.
+
+use std::{fmt::Debug, num::NonZeroU8};
+
+use num_traits::FromPrimitive;
+use scheda_core::{
+    chrono::{Month, Weekday},
+    AsU8, DateTimePart, DayCombination, Diagnostic, Error, Hour, Minute, MonthDay, MonthDayFromEnd,
+    NthWeekday, Ordinal, Result, Schedule, Second, Span, WellFormedRange,
+};
+
+use crate::{malformed_range, Spanned};
+
+/// The values a field may take
+pub(crate) struct Field {
+    /// How the field is called in error messages
+    pub(crate) name: &'static str,
+    pub(crate) min: u8,
+    pub(crate) max: u8,
+    /// How many distinct values the field has, e.g. 7 weekdays for `0` to `7`
+    pub(crate) len: u8,
+    /// Names standing for `min`, `min + 1` and so on
+    pub(crate) names: &'static [&'static str],
+}
+
+/// A value cron expressions can describe
+pub(crate) trait CronValue: AsU8 + WellFormedRange + Debug {
+    const FIELD: Field;
+
+    /// Builds a value already checked to be within [`Self::FIELD`]
+    fn from_cron(value: u8) -> Self;
+}
+
+impl CronValue for Second {
+    const FIELD: Field = Field {
+        name: "second",
+        min: 0,
+        max: 59,
+        len: 60,
+        names: &[],
+    };
+
+    fn from_cron(value: u8) -> Self {
+        Second::new(value).unwrap()
+    }
+}
+
+impl CronValue for Minute {
+    const FIELD: Field = Field {
+        name: "minute",
+        min: 0,
+        max: 59,
+        len: 60,
+        names: &[],
+    };
+
+    fn from_cron(value: u8) -> Self {
+        Minute::new(value).unwrap()
+    }
+}
+
+impl CronValue for Hour {
+    const FIELD: Field = Field {
+        name: "hour",
+        min: 0,
+        max: 23,
+        len: 24,
+        names: &[],
+    };
+
+    fn from_cron(value: u8) -> Self {
+        Hour::new(value).unwrap()
+    }
+}
+
+impl CronValue for MonthDay {
+    const FIELD: Field = Field {
+        name: "day of the month",
+        min: 1,
+        max: 31,
+        len: 31,
+        names: &[],
+    };
+
+    fn from_cron(value: u8) -> Self {
+        MonthDay::from_u8(value).unwrap()
+    }
+}
+
+impl CronValue for Month {
+    const FIELD: Field = Field {
+        name: "month",
+        min: 1,
+        max: 12,
+        len: 12,
+        names: &[
+            "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+        ],
+    };
+
+    fn from_cron(value: u8) -> Self {
+        Month::from_u8(value).unwrap()
+    }
+}
+
+impl CronValue for Weekday {
+    const FIELD: Field = Field {
+        name: "weekday",
+        min: 0,
+        max: 7,
+        len: 7,
+        names: &["sun", "mon", "tue", "wed", "thu", "fri", "sat"],
+    };
+
+    fn from_cron(value: u8) -> Self {
+        // Both 0 and 7 are Sunday
+        [
+            Weekday::Sun,
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+        ][usize::from(value % 7)]
+    }
+}
+
+/// One comma-separated piece of a field, e.g. `1-5` in `0,1-5`
+pub(crate) enum Part {
+    /// `*` or `?`
+    Every,
+    /// `5` or `mon`
+    Single(u8),
+    /// `1-5`
+    Range(u8, u8),
+    /// `*/15`, `5/15` or `0-30/15`
+    Step {
+        starting: u8,
+        ending: Option<u8>,
+        step: NonZeroU8,
+    },
+}
+
+/// A piece of the expression along with where it starts
+#[derive(Clone, Copy)]
+pub(crate) struct Piece<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) start: usize,
+}
+
+impl<'a> Piece<'a> {
+    /// The whitespace-separated words of `expression`
+    pub(crate) fn words(expression: &'a str) -> Vec<Piece<'a>> {
+        let whole = Piece {
+            text: expression,
+            start: 0,
+        };
+
+        expression
+            .split_whitespace()
+            .map(|text| whole.sub(text))
+            .collect()
+    }
+
+    pub(crate) fn span(&self) -> Span {
+        self.start..self.start + self.text.len()
+    }
+
+    /// The piece of `self` that `sub`, a slice of its text, stands for
+    pub(crate) fn sub(&self, sub: &'a str) -> Piece<'a> {
+        Piece {
+            text: sub,
+            start: self.start + (sub.as_ptr() as usize - self.text.as_ptr() as usize),
+        }
+    }
+
+    pub(crate) fn split(&self, separator: char) -> impl Iterator<Item = Piece<'a>> + '_ {
+        self.text.split(separator).map(|text| self.sub(text))
+    }
+
+    pub(crate) fn split_once(&self, separator: &str) -> Option<(Piece<'a>, Piece<'a>)> {
+        let (before, after) = self.text.split_once(separator)?;
+
+        Some((self.sub(before), self.sub(after)))
+    }
+}
+
+pub(crate) fn parse_value(field: &Field, piece: Piece) -> Spanned<u8> {
+    let named = field
+        .names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(piece.text))
+        .map(|idx| field.min + idx as u8);
+
+    named
+        .or_else(|| piece.text.parse().ok())
+        .filter(|value| (field.min..=field.max).contains(value))
+        .ok_or_else(|| {
+            Error::InvalidValue {
+                expected: format!("a {} between {} and {}", field.name, field.min, field.max)
+                    .into(),
+                found: piece.text.into(),
+            }
+            .at(piece.span())
+        })
+}
+
+/// Parses one comma-separated piece of a field, where ranges are `starting<range>ending`,
+/// e.g. `1-5` in cron
+pub(crate) fn parse_part(field: &Field, piece: Piece, range: &str) -> Spanned<Part> {
+    let (base, step) = match piece.split_once("/") {
+        Some((base, step)) => {
+            let parsed = step.text.parse().ok().and_then(NonZeroU8::new);
+            let step = parsed.ok_or_else(|| {
+                Error::InvalidValue {
+                    expected: "a step, such as `15`".into(),
+                    found: step.text.into(),
+                }
+                .at(step.span())
+            })?;
+
+            (base, Some(step))
+        }
+        None => (piece, None),
+    };
+
+    if matches!(base.text, "*" | "?") {
+        return Ok(match step {
+            Some(step) => Part::Step {
+                starting: field.min,
+                ending: None,
+                step,
+            },
+            None => Part::Every,
+        });
+    }
+
+    let (starting, ending) = match base.split_once(range) {
+        Some((starting, ending)) => (
+            parse_value(field, starting)?,
+            Some(parse_value(field, ending)?),
+        ),
+        None => (parse_value(field, base)?, None),
+    };
+
+    Ok(match (ending, step) {
+        (None, None) => Part::Single(starting),
+        (Some(ending), None) => Part::Range(starting, ending),
+        (ending, Some(step)) => Part::Step {
+            starting,
+            ending,
+            step,
+        },
+    })
+}
+
+/// Turns a parsed piece into date-time parts, where no parts at all stand for every value
+pub(crate) fn lower<T: CronValue>(part: Part, span: Span) -> Spanned<Vec<DateTimePart<T>>> {
+    let field = T::FIELD;
+
+    match part {
+        Part::Every => Ok(Vec::new()),
+        Part::Single(value) => Ok(vec![DateTimePart::Single(T::from_cron(value))]),
+        // Cron doesn't wrap around, so `5-2` is a mistake rather than Friday to Tuesday
+        Part::Range(starting, ending) if ending < starting => Err(malformed_range().at(span)),
+        Part::Range(starting, ending) if ending - starting + 1 >= field.len => Ok(Vec::new()),
+        Part::Range(starting, ending) => {
+            let range = T::from_cron(starting)..T::from_cron(ending);
+
+            if range.start.as_u8() == range.end.as_u8() {
+                return Ok(vec![DateTimePart::Single(range.start)]);
+            }
+
+            if T::is_well_formed(&range) {
+                return Ok(vec![DateTimePart::Range {
+                    starting: range.start,
+                    ending: range.end,
+                }]);
+            }
+
+            // Only values standing for the same one as `min` get here, e.g.
+            // `5-7` for Friday to Sunday
+            let flipped = range.end..range.start;
+
+            if T::WRAPS_AROUND && T::is_well_formed(&flipped) {
+                return Ok(vec![DateTimePart::WrappingRange {
+                    starting: flipped.end,
+                    ending: flipped.start,
+                }]);
+            }
+
+            Err(malformed_range().at(span))
+        }
+        Part::Step {
+            starting,
+            ending: None,
+            step,
+        } if starting == field.min && step.get() == 1 => Ok(Vec::new()),
+        // Steps go on through values standing for the same one as `min`,
+        // e.g. `5/2` for Friday and Sunday
+        Part::Step {
+            starting,
+            ending: None,
+            step,
+        } if field.max >= field.min + field.len => lower(
+            Part::Step {
+                starting,
+                ending: Some(field.max),
+                step,
+            },
+            span,
+        ),
+        Part::Step {
+            starting,
+            ending: None,
+            step,
+        } => Ok(vec![DateTimePart::Step {
+            starting: T::from_cron(starting),
+            step,
+        }]),
+        Part::Step {
+            starting,
+            ending: Some(ending),
+            step,
+        } => {
+            if ending < starting {
+                return Err(malformed_range().at(span));
+            }
+
+            if step.get() == 1 {
+                return lower(Part::Range(starting, ending), span);
+            }
+
+            let values: Vec<_> = (starting..=ending).step_by(step.get().into()).collect();
+
+            // Ranges stepped through to the end of the cycle, such as `1-31/2`,
+            // are plain steps
+            let stepped = DateTimePart::Step {
+                starting: T::from_cron(starting),
+                step,
+            };
+            let fires = |value: &T| {
+                values
+                    .iter()
+                    .any(|&other| T::from_cron(other).as_u8() == value.as_u8())
+            };
+            if (field.min..=field.max)
+                .map(T::from_cron)
+                .all(|value| fires(&value) == stepped.contains(&value))
+            {
+                return Ok(vec![stepped]);
+            }
+
+            // Steps never stop before the end of the cycle, so other bounded
+            // ones are spelled out value by value, once each
+            let mut seen = Vec::new();
+            Ok(values
+                .into_iter()
+                .map(T::from_cron)
+                .filter(|value| {
+                    let is_new = !seen.contains(&value.as_u8());
+                    seen.push(value.as_u8());
+
+                    is_new
+                })
+                .map(DateTimePart::Single)
+                .collect())
+        }
+    }
+}
+
+/// Parses a whole comma-separated field, where ranges are `starting<range>ending`
+pub(crate) fn parse_field<T: CronValue>(
+    piece: Piece,
+    range: &str,
+) -> Spanned<Vec<DateTimePart<T>>> {
+    let mut parts = Vec::new();
+
+    for item in piece.split(',') {
+        let lowered = lower(parse_part(&T::FIELD, item, range)?, item.span())?;
+
+        // A wildcard anywhere in the list makes the whole field match everything
+        if lowered.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        parts.extend(lowered);
+    }
+
+    Ok(parts)
+}
+
+/// Parses a seconds field, where every second has to be spelled out, since
+/// schedules without a `second` clause only fire on the first one
+pub(crate) fn parse_seconds(piece: Piece, range: &str) -> Spanned<Vec<DateTimePart<Second>>> {
+    let parts = parse_field(piece, range)?;
+
+    Ok(match parts.as_slice() {
+        [] => vec![DateTimePart::Range {
+            starting: Second::MIN,
+            ending: Second::MAX,
+        }],
+        [DateTimePart::Single(second)] if *second == Second::MIN => Vec::new(),
+        _ => parts,
+    })
+}
+
+/// Parses the day-of-the-month field, where `L` is the last day of the month
+fn parse_days(schedule: &mut Schedule, piece: Piece) -> Spanned<()> {
+    for item in piece.split(',') {
+        if item.text.eq_ignore_ascii_case("l") {
+            schedule
+                .month_end_spec
+                .push(DateTimePart::Single(MonthDayFromEnd::from_u8(1).unwrap()));
+            continue;
+        }
+
+        if item.text.contains(['w', 'W', 'l', 'L']) {
+            return Err(Error::Unsupported(
+                "neither the nearest weekday (`W`) nor offsets from the last day (`L-3`) are supported"
+                    .into(),
+            )
+            .at(item.span()));
+        }
+
+        let parts = parse_field::<MonthDay>(item, "-")?;
+        if parts.is_empty() {
+            schedule.day_spec.clear();
+            schedule.month_end_spec.clear();
+            return Ok(());
+        }
+
+        schedule.day_spec.extend(parts);
+    }
+
+    Ok(())
+}
+
+/// Parses the weekday field, where `5L` is the last Friday of the month and
+/// `2#3` its third Tuesday
+fn parse_weekdays(schedule: &mut Schedule, piece: Piece) -> Spanned<()> {
+    for item in piece.split(',') {
+        let nth_weekday = if let Some((weekday, nth)) = item.split_once("#") {
+            let ordinal = match nth.text {
+                "1" => Ordinal::First,
+                "2" => Ordinal::Second,
+                "3" => Ordinal::Third,
+                "4" => Ordinal::Fourth,
+                "5" => Ordinal::Fifth,
+                _ => {
+                    return Err(Error::InvalidValue {
+                        expected: "an occurrence between 1 and 5".into(),
+                        found: nth.text.into(),
+                    }
+                    .at(nth.span()))
+                }
+            };
+
+            Some((weekday, ordinal))
+        } else if item.text.len() > 1 && item.text.ends_with(['l', 'L']) {
+            Some((item.sub(&item.text[..item.text.len() - 1]), Ordinal::Last))
+        } else {
+            None
+        };
+
+        if let Some((weekday, ordinal)) = nth_weekday {
+            schedule.nth_weekday_spec.push(NthWeekday {
+                ordinal,
+                weekday: Weekday::from_cron(parse_value(&Weekday::FIELD, weekday)?),
+            });
+            continue;
+        }
+
+        let parts = parse_field::<Weekday>(item, "-")?;
+        if parts.is_empty() {
+            schedule.weekday_spec.clear();
+            schedule.nth_weekday_spec.clear();
+            return Ok(());
+        }
+
+        schedule.weekday_spec.extend(parts);
+    }
+
+    Ok(())
+}
+
+/// What the `@`-macros stand for
+fn expand_macro(piece: Piece) -> Spanned<&'static str> {
+    match piece.text {
+        x if x.eq_ignore_ascii_case("@yearly") || x.eq_ignore_ascii_case("@annually") => {
+            Ok("0 0 1 1 *")
+        }
+        x if x.eq_ignore_ascii_case("@monthly") => Ok("0 0 1 * *"),
+        x if x.eq_ignore_ascii_case("@weekly") => Ok("0 0 * * 0"),
+        x if x.eq_ignore_ascii_case("@daily") || x.eq_ignore_ascii_case("@midnight") => {
+            Ok("0 0 * * *")
+        }
+        x if x.eq_ignore_ascii_case("@hourly") => Ok("0 * * * *"),
+        x if x.eq_ignore_ascii_case("@reboot") => Err(Error::Unsupported(
+            "`@reboot` runs on startup rather than at a point in time, so it has no schedule"
+                .into(),
+        )
+        .at(piece.span())),
+        other => Err(Error::InvalidValue {
+            expected: "a macro, such as `@daily` or `@hourly`".into(),
+            found: other.into(),
+        }
+        .at(piece.span())),
+    }
+}
+
+/// Parses a five- or six-field cron expression, or one of its `@` macros, into a [`Schedule`].
+///
+/// Like [`Parser::parse_schedule`](crate::Parser::parse_schedule), every field
+/// is checked, and the returned [`Error::Parse`] lists every error found.
+/// Whatever a [`Schedule`] can't represent is reported as [`Error::Unsupported`].
+///
+/// As in cron, a schedule restricting both its days of the month and its
+/// weekdays fires on either of them, unless one of them starts with `*`.
+pub fn parse(expression: &str) -> Result<Schedule> {
+    let fields = Piece::words(expression);
+
+    match fields.as_slice() {
+        [single] if single.text.starts_with('@') => {
+            let expansion =
+                expand_macro(*single).map_err(|diagnostic| Error::Parse(vec![diagnostic]))?;
+
+            parse(expansion)
+        }
+        [seconds, rest @ ..] if rest.len() == 5 => parse_fields(Some(*seconds), rest),
+        fields if fields.len() == 5 => parse_fields(None, fields),
+        fields => Err(Error::Parse(vec![Error::InvalidSyntax(
+            format!("expected 5 or 6 fields, found {}", fields.len()).into(),
+        )
+        .at(0..expression.len())])),
+    }
+}
+
+fn parse_fields(seconds: Option<Piece>, fields: &[Piece]) -> Result<Schedule> {
+    let [minutes, hours, days, months, weekdays] = fields else {
+        unreachable!("cron expressions are split into five fields besides seconds");
+    };
+
+    let mut schedule = Schedule::new();
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+    match seconds
+        .map(|seconds| parse_seconds(seconds, "-"))
+        .transpose()
+    {
+        Ok(parts) => schedule.second_spec.extend(parts.into_iter().flatten()),
+        Err(diagnostic) => diagnostics.push(diagnostic),
+    }
+
+    match parse_field(*minutes, "-") {
+        Ok(parts) => schedule.minute_spec.extend(parts),
+        Err(diagnostic) => diagnostics.push(diagnostic),
+    }
+    match parse_field(*hours, "-") {
+        Ok(parts) => schedule.hour_spec.extend(parts),
+        Err(diagnostic) => diagnostics.push(diagnostic),
+    }
+    if let Err(diagnostic) = parse_days(&mut schedule, *days) {
+        diagnostics.push(diagnostic);
+    }
+    match parse_field(*months, "-") {
+        Ok(parts) => schedule.month_spec.extend(parts),
+        Err(diagnostic) => diagnostics.push(diagnostic),
+    }
+    if let Err(diagnostic) = parse_weekdays(&mut schedule, *weekdays) {
+        diagnostics.push(diagnostic);
+    }
+
+    if !diagnostics.is_empty() {
+        return Err(Error::Parse(diagnostics));
+    }
+
+    let starts_with_wildcard = |piece: &Piece| piece.text.starts_with(['*', '?']);
+    if !starts_with_wildcard(days) && !starts_with_wildcard(weekdays) {
+        schedule.day_combination = DayCombination::Or;
+
+        // A field spelling out every value, e.g. `0-6`, still gets ORed, and
+        // so matches every day on its own
+        let every_day = schedule.day_spec.is_empty() && schedule.month_end_spec.is_empty();
+        let every_weekday =
+            schedule.weekday_spec.is_empty() && schedule.nth_weekday_spec.is_empty();
+        if every_day || every_weekday {
+            schedule.day_spec.clear();
+            schedule.month_end_spec.clear();
+            schedule.weekday_spec.clear();
+            schedule.nth_weekday_spec.clear();
+        }
+    }
+
+    Ok(schedule)
+}
+
+#[cfg(test)]
+mod tests {
+    use scheda_core::{
+        chrono::{DateTime, Duration, TimeZone, Utc},
+        DayCombination, Error,
+    };
+
+    use super::parse;
+
+    fn from_ymdhm(year: i32, month: u32, day: u32, hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, min, 0)
+            .unwrap()
+    }
+
+    fn upcoming(expression: &str, from: DateTime<Utc>, count: usize) -> Vec<DateTime<Utc>> {
+        parse(expression)
+            .unwrap()
+            .upcoming(from)
+            .take(count)
+            .collect()
+    }
+
+    fn parse_errors(expression: &str) -> Vec<(&'static str, &str)> {
+        match parse(expression) {
+            Err(Error::Parse(diagnostics)) => diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.code(), &expression[diagnostic.span.clone()]))
+                .collect(),
+            other => panic!("expected parse errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_cron_expressions() {
+        // Tuesday, 22/05/2001 20h00
+        let d05_22_2001 = from_ymdhm(2001, 5, 22, 20, 0);
+
+        assert_eq!(
+            upcoming("*/5 * * * 1-5", d05_22_2001, 2),
+            [
+                from_ymdhm(2001, 5, 22, 20, 5),
+                from_ymdhm(2001, 5, 22, 20, 10)
+            ]
+        );
+        assert_eq!(
+            upcoming("0 9,17 * * mon-fri", d05_22_2001, 3),
+            [
+                from_ymdhm(2001, 5, 23, 9, 0),
+                from_ymdhm(2001, 5, 23, 17, 0),
+                from_ymdhm(2001, 5, 24, 9, 0)
+            ]
+        );
+        assert_eq!(
+            upcoming("30 0-12/6 * JAN,jul *", d05_22_2001, 3),
+            [
+                from_ymdhm(2001, 7, 1, 0, 30),
+                from_ymdhm(2001, 7, 1, 6, 30),
+                from_ymdhm(2001, 7, 1, 12, 30)
+            ]
+        );
+        // Weekdays run from 0 to 7, both being Sunday
+        assert_eq!(
+            upcoming("0 0 * * 5-7", d05_22_2001, 3),
+            [
+                from_ymdhm(2001, 5, 25, 0, 0),
+                from_ymdhm(2001, 5, 26, 0, 0),
+                from_ymdhm(2001, 5, 27, 0, 0)
+            ]
+        );
+        assert_eq!(
+            upcoming("0 0 L * *", d05_22_2001, 2),
+            [from_ymdhm(2001, 5, 31, 0, 0), from_ymdhm(2001, 6, 30, 0, 0)]
+        );
+        assert_eq!(
+            upcoming("0 18 * * 2#2,5L", d05_22_2001, 2),
+            [
+                from_ymdhm(2001, 5, 25, 18, 0),
+                from_ymdhm(2001, 6, 12, 18, 0)
+            ]
+        );
+        // Six fields start with seconds
+        assert_eq!(
+            upcoming("0 0 12 * * *", d05_22_2001, 1),
+            [from_ymdhm(2001, 5, 23, 12, 0)]
+        );
+        assert_eq!(
+            upcoming("*/20 0 21 * * *", d05_22_2001, 3),
+            [
+                from_ymdhm(2001, 5, 22, 21, 0),
+                from_ymdhm(2001, 5, 22, 21, 0) + Duration::seconds(20),
+                from_ymdhm(2001, 5, 22, 21, 0) + Duration::seconds(40)
+            ]
+        );
+        // Ranges stepped through to the end of the cycle are steps like any other
+        assert_eq!(
+            parse("0 0 1-31/2 * *").unwrap().to_string(),
+            parse("0 0 */2 * *").unwrap().to_string()
+        );
+        assert_eq!(
+            parse("0 0 3-31/5 * *").unwrap().to_string(),
+            parse("0 0 3/5 * *").unwrap().to_string()
+        );
+        assert_eq!(
+            parse("0 0 3-25/5 * *").unwrap().to_string(),
+            "when day 3 or 8 or 13 or 18 or 23, hour 0, minute 0"
+        );
+        // Weekday steps go on to 7, which is Sunday again
+        assert_eq!(
+            parse("0 0 * * 5/2").unwrap().to_string(),
+            "when weekday fri or sun, hour 0, minute 0"
+        );
+        assert_eq!(
+            upcoming("0 0 * * 5/2", d05_22_2001, 3),
+            [
+                from_ymdhm(2001, 5, 25, 0, 0),
+                from_ymdhm(2001, 5, 27, 0, 0),
+                from_ymdhm(2001, 6, 1, 0, 0)
+            ]
+        );
+        assert_eq!(
+            parse("0 0 * * */2").unwrap().to_string(),
+            "when weekday every 2, hour 0, minute 0"
+        );
+        assert_eq!(
+            parse("* 0 21 * * *").unwrap().to_string(),
+            "when hour 21, minute 0, second 0 to 59"
+        );
+    }
+
+    #[test]
+    fn parses_macros() {
+        let d05_22_2001 = from_ymdhm(2001, 5, 22, 20, 0);
+
+        assert_eq!(
+            upcoming("@hourly", d05_22_2001, 1),
+            [from_ymdhm(2001, 5, 22, 21, 0)]
+        );
+        assert_eq!(
+            upcoming("@daily", d05_22_2001, 1),
+            [from_ymdhm(2001, 5, 23, 0, 0)]
+        );
+        assert_eq!(
+            upcoming("@weekly", d05_22_2001, 1),
+            [from_ymdhm(2001, 5, 27, 0, 0)]
+        );
+        assert_eq!(
+            upcoming("@monthly", d05_22_2001, 1),
+            [from_ymdhm(2001, 6, 1, 0, 0)]
+        );
+        assert_eq!(
+            upcoming("@annually", d05_22_2001, 1),
+            [from_ymdhm(2002, 1, 1, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn combines_days_like_cron() {
+        assert_eq!(
+            parse("0 0 1 * mon").unwrap().day_combination,
+            DayCombination::Or
+        );
+        assert_eq!(
+            parse("0 0 */2 * mon").unwrap().day_combination,
+            DayCombination::And
+        );
+        assert_eq!(
+            parse("0 0 1 * *").unwrap().day_combination,
+            DayCombination::And
+        );
+
+        // Tuesday, 01/05/2001 and Monday, 07/05/2001
+        assert_eq!(
+            upcoming("0 0 1 * mon", from_ymdhm(2001, 4, 30, 12, 0), 2),
+            [from_ymdhm(2001, 5, 1, 0, 0), from_ymdhm(2001, 5, 7, 0, 0)]
+        );
+
+        // Every weekday spelled out is still ORed with the day
+        assert_eq!(
+            upcoming("0 0 1 * 0-6", from_ymdhm(2001, 4, 30, 12, 0), 2),
+            [from_ymdhm(2001, 5, 1, 0, 0), from_ymdhm(2001, 5, 2, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn reports_unsupported_and_invalid_fields() {
+        assert_eq!(parse_errors("@reboot"), [("unsupported", "@reboot")]);
+        assert_eq!(
+            parse_errors("@fortnightly"),
+            [("invalid-value", "@fortnightly")]
+        );
+        assert_eq!(parse_errors("0 0 15W * *"), [("unsupported", "15W")]);
+        assert_eq!(parse_errors("* * *"), [("invalid-syntax", "* * *")]);
+        assert_eq!(parse_errors("60 0 12 * * *"), [("invalid-value", "60")]);
+        // Reversed ranges don't wrap around, whatever the field
+        assert_eq!(
+            parse_errors("0 22-2 * 11-2 5-2"),
+            [
+                ("malformed-range", "22-2"),
+                ("malformed-range", "11-2"),
+                ("malformed-range", "5-2")
+            ]
+        );
+
+        // Every bad field is reported at once
+        assert_eq!(
+            parse_errors("60 */0 20-10 foo 1#6"),
+            [
+                ("invalid-value", "60"),
+                ("invalid-value", "0"),
+                ("malformed-range", "20-10"),
+                ("invalid-value", "foo"),
+                ("invalid-value", "6")
+            ]
+        );
+    }
+}
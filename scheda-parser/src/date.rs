@@ -0,0 +1,22 @@
+use scheda_core::{chrono::NaiveDate, Year};
+
+use super::AtomParse;
+
+impl AtomParse for Year {
+    const EXPECTED: &'static str = "a year between 1 and 9999";
+
+    fn parse_atom(val: &str) -> Option<Self> {
+        val.trim().parse().ok().and_then(Year::new)
+    }
+}
+
+impl AtomParse for NaiveDate {
+    const EXPECTED: &'static str = "a date, such as `2026-12-24`";
+
+    fn parse_atom(val: &str) -> Option<Self> {
+        // Dates fall within the same years as the `year` clause
+        NaiveDate::parse_from_str(val, "%Y-%m-%d")
+            .ok()
+            .filter(|date| Year::of(*date).is_some())
+    }
+}
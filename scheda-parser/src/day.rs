@@ -1,11 +1,51 @@
-use scheda_core::MonthDay;
+use scheda_core::{DayCombination, MonthDay, MonthDayFromEnd};
 
-use super::AtomParse;
+use crate::{ast::Item, AtomParse};
 
 impl AtomParse for MonthDay {
+    const EXPECTED: &'static str = "a day of the month";
+    const BOUNDS: Option<(u8, u8)> = Some((MonthDay::MIN.get(), MonthDay::MAX.get()));
+
     fn parse_atom(val: &str) -> Option<Self> {
         let parsed = val.parse().ok()?;
 
         Self::from_u8(parsed)
     }
 }
+
+impl AtomParse for MonthDayFromEnd {
+    const EXPECTED: &'static str =
+        "a day counted from the end of the month, such as `last` or `-3`";
+
+    fn parse_atom(val: &str) -> Option<Self> {
+        if val.eq_ignore_ascii_case("last") {
+            return Self::from_u8(1);
+        }
+
+        let parsed = val.strip_prefix('-')?.parse().ok()?;
+
+        Self::from_u8(parsed)
+    }
+}
+
+impl AtomParse for DayCombination {
+    const EXPECTED: &'static str = "`and` or `or`";
+
+    fn parse_atom(val: &str) -> Option<Self> {
+        match val {
+            x if x.eq_ignore_ascii_case("and") => Some(DayCombination::And),
+            x if x.eq_ignore_ascii_case("or") => Some(DayCombination::Or),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a `day` item counts from the end of the month, e.g. `last` or `-3 to -1`
+pub(crate) fn counts_from_month_end(item: &Item) -> bool {
+    let first = match item {
+        Item::Single(atom) | Item::Range { starting: atom, .. } => atom,
+        _ => return false,
+    };
+
+    first.text.eq_ignore_ascii_case("last") || first.text.starts_with('-')
+}
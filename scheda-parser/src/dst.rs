@@ -0,0 +1,28 @@
+use scheda_core::{GapPolicy, OverlapPolicy};
+
+use crate::AtomParse;
+
+impl AtomParse for GapPolicy {
+    const EXPECTED: &'static str = "`skip` or `shift`";
+
+    fn parse_atom(val: &str) -> Option<Self> {
+        match val {
+            x if x.eq_ignore_ascii_case("skip") => Some(GapPolicy::Skip),
+            x if x.eq_ignore_ascii_case("shift") => Some(GapPolicy::NextValid),
+            _ => None,
+        }
+    }
+}
+
+impl AtomParse for OverlapPolicy {
+    // Only ever reported after failing to parse a `GapPolicy` too
+    const EXPECTED: &'static str = "`skip`, `shift`, `once` or `twice`";
+
+    fn parse_atom(val: &str) -> Option<Self> {
+        match val {
+            x if x.eq_ignore_ascii_case("once") => Some(OverlapPolicy::Once),
+            x if x.eq_ignore_ascii_case("twice") => Some(OverlapPolicy::Twice),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use scheda_core::{DateTimePartKind, Diagnostic, Error, Span};
+
+use crate::{
+    ast::{Atom, Clause, Expr, Item, Schedule},
+    lexer::{tokenize, Token},
+    Spanned,
+};
+
+/// A recursive-descent parser turning tokens into an [`ast::Schedule`](Schedule)
+pub struct Grammar<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, Span)>,
+    position: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Grammar<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            tokens: tokenize(source),
+            position: 0,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<(Token, Span)> {
+        self.tokens.get(self.position).cloned()
+    }
+
+    fn text(&self, span: &Span) -> &'a str {
+        &self.source[span.clone()]
+    }
+
+    fn atom(&self, span: Span) -> Atom<'a> {
+        Atom {
+            text: self.text(&span),
+            span,
+        }
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(
+            self.peek(),
+            Some((Token::Word, span)) if self.text(&span).eq_ignore_ascii_case(keyword)
+        )
+    }
+
+    fn at_end_of_clause(&self) -> bool {
+        matches!(self.peek(), None | Some((Token::Comma | Token::RParen, _)))
+            || self.at_keyword("in")
+    }
+
+    /// An error for whatever token comes next
+    fn unexpected(&self, expected: &str) -> Diagnostic {
+        match self.peek() {
+            Some((_, span)) => Error::InvalidSyntax(
+                format!("expected {expected}, found `{}`", self.text(&span)).into(),
+            )
+            .at(span),
+            None => Error::InvalidSyntax(format!("expected {expected}, found end of input").into())
+                .at(self.source.len()..self.source.len()),
+        }
+    }
+
+    /// Skips ahead to the next comma, which is where a new clause starts,
+    /// stepping over any nested schedule in the way
+    fn recover(&mut self) {
+        let mut depth = 0;
+
+        while depth > 0 || !self.at_end_of_clause() {
+            match self.peek() {
+                Some((Token::LParen, _)) => depth += 1,
+                Some((Token::RParen, _)) => depth -= 1,
+                None => return,
+                _ => {}
+            }
+
+            self.position += 1;
+        }
+    }
+
+    /// Parses the whole input.
+    ///
+    /// A clause that fails to parse is left out of the returned tree, and
+    /// parsing picks back up at the next comma, so that every error is reported.
+    pub fn parse_schedule(mut self) -> (Schedule<'a>, Vec<Diagnostic>) {
+        if let Err(diagnostic) = self.eat_when() {
+            let schedule = Schedule {
+                clauses: Vec::new(),
+                timezone: None,
+            };
+
+            return (schedule, vec![diagnostic]);
+        }
+
+        let schedule = self.parse_clauses();
+
+        if self.peek().is_some() {
+            self.diagnostics.push(self.unexpected("end of input"));
+        }
+
+        (schedule, self.diagnostics)
+    }
+
+    /// Parses the whole input as schedules combined with `and`, `or` and
+    /// `not`, or as a single schedule, which needs no parentheses.
+    ///
+    /// Errors within a schedule are recovered from as in [`Self::parse_schedule`],
+    /// but parsing stops at the first one in how schedules are combined.
+    pub fn parse_expr(mut self) -> (Expr<'a>, Vec<Diagnostic>) {
+        if !self.at_keyword("not") && !matches!(self.peek(), Some((Token::LParen, _))) {
+            let (schedule, diagnostics) = self.parse_schedule();
+
+            return (Expr::Schedule(schedule), diagnostics);
+        }
+
+        let expr = match self.parse_or() {
+            Ok(expr) if self.peek().is_none() => expr,
+            result => {
+                let diagnostic = result
+                    .err()
+                    .unwrap_or_else(|| self.unexpected("`and`, `or`, `but not` or end of input"));
+                self.diagnostics.push(diagnostic);
+
+                Expr::Schedule(Schedule {
+                    clauses: Vec::new(),
+                    timezone: None,
+                })
+            }
+        };
+
+        (expr, self.diagnostics)
+    }
+
+    /// `<operand> and <operand> or <operand> ...`, where `and` binds tighter
+    fn parse_or(&mut self) -> Spanned<Expr<'a>> {
+        let mut expr = self.parse_and()?;
+
+        while self.at_keyword("or") {
+            self.position += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+
+        Ok(expr)
+    }
+
+    /// `<operand> and <operand> but not <operand> ...`
+    fn parse_and(&mut self) -> Spanned<Expr<'a>> {
+        let mut expr = self.parse_operand()?;
+
+        loop {
+            let negated = if self.at_keyword("and") {
+                false
+            } else if self.at_keyword("but") {
+                self.position += 1;
+
+                if !self.at_keyword("not") {
+                    return Err(self.unexpected("`not`"));
+                }
+
+                true
+            } else {
+                return Ok(expr);
+            };
+            self.position += 1;
+
+            let mut operand = self.parse_operand()?;
+            if negated {
+                operand = Expr::Not(Box::new(operand));
+            }
+
+            expr = Expr::And(Box::new(expr), Box::new(operand));
+        }
+    }
+
+    /// `not <operand>`, `(when ...)` or a whole expression between parentheses
+    fn parse_operand(&mut self) -> Spanned<Expr<'a>> {
+        if self.at_keyword("not") {
+            self.position += 1;
+            return Ok(Expr::Not(Box::new(self.parse_operand()?)));
+        }
+
+        let Some((Token::LParen, _)) = self.peek() else {
+            return Err(self.unexpected("`(`"));
+        };
+        self.position += 1;
+
+        let expr = if self.at_keyword("when") {
+            self.position += 1;
+            Expr::Schedule(self.parse_clauses())
+        } else {
+            self.parse_or()?
+        };
+
+        let Some((Token::RParen, _)) = self.peek() else {
+            return Err(self.unexpected("`)`"));
+        };
+        self.position += 1;
+
+        Ok(expr)
+    }
+
+    /// Parses the clauses following a `when`, along with their time zone
+    fn parse_clauses(&mut self) -> Schedule<'a> {
+        let mut schedule = Schedule {
+            clauses: Vec::new(),
+            timezone: None,
+        };
+
+        // A bare `when` has no clauses, and so matches every minute
+        let mut at_clause =
+            !matches!(self.peek(), None | Some((Token::RParen, _))) && !self.at_keyword("in");
+
+        while at_clause {
+            match self.parse_clause() {
+                Ok(clause) => schedule.clauses.push(clause),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.recover();
+                }
+            }
+
+            // Clauses end at a comma, at the time zone or at the end of the input
+            at_clause = matches!(self.peek(), Some((Token::Comma, _)));
+            if at_clause {
+                self.position += 1;
+            }
+        }
+
+        if self.at_keyword("in") {
+            self.position += 1;
+
+            match self.parse_timezone() {
+                Ok(timezone) => schedule.timezone = Some(timezone),
+                Err(diagnostic) => self.diagnostics.push(diagnostic),
+            }
+        }
+
+        schedule
+    }
+
+    /// Parses a single `except` item, either a whole nested schedule between
+    /// parentheses or a single clause, which is short for one
+    fn parse_nested(&mut self) -> Spanned<Item<'a>> {
+        let Some((Token::LParen, open)) = self.peek() else {
+            let clause = self.parse_clause()?;
+            let span = clause.span.clone();
+            let schedule = Schedule {
+                clauses: vec![clause],
+                timezone: None,
+            };
+
+            return Ok(Item::Nested { schedule, span });
+        };
+        // Errors rewind to the opening parenthesis, so that recovering skips
+        // the whole nested schedule
+        let start = self.position;
+        self.position += 1;
+
+        if !self.at_keyword("when") {
+            let diagnostic = self.unexpected("`when`");
+            self.position = start;
+            return Err(diagnostic);
+        }
+        self.position += 1;
+
+        let schedule = self.parse_clauses();
+
+        let Some((Token::RParen, close)) = self.peek() else {
+            let diagnostic = self.unexpected("`)`");
+            self.position = start;
+            return Err(diagnostic);
+        };
+        self.position += 1;
+
+        Ok(Item::Nested {
+            schedule,
+            span: open.start..close.end,
+        })
+    }
+
+    fn parse_timezone(&mut self) -> Spanned<Atom<'a>> {
+        match self.peek() {
+            Some((Token::Word | Token::Path, span)) => {
+                self.position += 1;
+
+                Ok(Atom {
+                    text: self.text(&span),
+                    span,
+                })
+            }
+            _ => Err(self.unexpected("a time zone, such as `America/Sao_Paulo`")),
+        }
+    }
+
+    fn eat_when(&mut self) -> Spanned<()> {
+        if self.at_keyword("when") {
+            self.position += 1;
+            return Ok(());
+        }
+
+        let start = self
+            .peek()
+            .map_or(self.source.len(), |(_, span)| span.start);
+        let when = self.tokens.iter().position(|(token, span)| {
+            *token == Token::Word && self.text(span).eq_ignore_ascii_case("when")
+        });
+
+        Err(match when {
+            // `when` is there, but something comes before it
+            Some(idx) => {
+                let end = self.tokens[idx - 1].1.end;
+
+                Error::InvalidSyntax(
+                    format!("expected `when`, found `{}`", &self.source[start..end]).into(),
+                )
+                .at(start..end)
+            }
+            None => {
+                let end = self.tokens.last().map_or(start, |(_, span)| span.end);
+
+                Error::MissingWhenStmt.at(start..end)
+            }
+        })
+    }
+
+    fn parse_clause(&mut self) -> Spanned<Clause<'a>> {
+        let (kind, kind_span) = match self.peek() {
+            Some((Token::Word, span)) => {
+                let kind = DateTimePartKind::parse(self.text(&span))
+                    .map_err(|err| err.at(span.clone()))?;
+                self.position += 1;
+
+                (kind, span)
+            }
+            _ => return Err(self.unexpected("a date-time part, such as `month` or `hour`")),
+        };
+
+        let mut items = vec![self.parse_item(kind)?];
+
+        loop {
+            if self.at_end_of_clause() {
+                break;
+            }
+
+            if !self.at_keyword("or") {
+                return Err(self.unexpected("`,` or `or`"));
+            }
+            self.position += 1;
+
+            items.push(self.parse_item(kind)?);
+        }
+
+        let end = items.last().map_or(kind_span.end, |item| item.span().end);
+
+        Ok(Clause {
+            kind,
+            items,
+            span: kind_span.start..end,
+        })
+    }
+
+    fn parse_item(&mut self, kind: DateTimePartKind) -> Spanned<Item<'a>> {
+        if kind == DateTimePartKind::Every {
+            let count = match self.peek() {
+                Some((Token::Number, _)) => Some(self.parse_atom()?),
+                _ => None,
+            };
+
+            return match self.peek() {
+                Some((Token::Word, _)) => Ok(Item::Step {
+                    count,
+                    unit: self.parse_atom()?,
+                }),
+                _ => Err(self.unexpected("`seconds`, `minutes`, `hours` or `days`")),
+            };
+        }
+
+        if kind == DateTimePartKind::At {
+            let (hour, minute) = self.parse_hour_and_minute()?;
+            let meridiem = if self.at_keyword("am") || self.at_keyword("pm") {
+                Some(self.parse_atom()?)
+            } else {
+                None
+            };
+            let span = hour.span.start..meridiem.as_ref().unwrap_or(&minute).span.end;
+
+            return Ok(Item::Time {
+                hour,
+                minute,
+                meridiem,
+                span,
+            });
+        }
+
+        if kind == DateTimePartKind::Except {
+            return self.parse_nested();
+        }
+
+        if kind == DateTimePartKind::On {
+            let Some((Token::Date, span)) = self.peek() else {
+                return Err(self.unexpected("a date, such as `2026-12-24`"));
+            };
+            self.position += 1;
+
+            let text = self.text(&span);
+            let date_end = span.start + text.find('T').unwrap_or(text.len());
+            let date = self.atom(span.start..date_end);
+
+            // Like in ISO 8601, the time may come right after a `T`
+            let time = if date_end < span.end {
+                let colon = span.start + text.find(':').unwrap();
+
+                Some((
+                    self.atom(date_end + 1..colon),
+                    self.atom(colon + 1..span.end),
+                ))
+            } else if matches!(self.peek(), Some((Token::Number, _))) {
+                Some(self.parse_hour_and_minute()?)
+            } else {
+                None
+            };
+            let end = time.as_ref().map_or(&date, |(_, minute)| minute).span.end;
+            let span = date.span.start..end;
+
+            return Ok(Item::Date { date, time, span });
+        }
+
+        if let Some((_, every)) = self.peek().filter(|_| self.at_keyword("every")) {
+            self.position += 1;
+
+            let step = self.parse_number("a step, such as `15`")?;
+            let from = if self.at_keyword("from") {
+                self.position += 1;
+                Some(self.parse_atom()?)
+            } else {
+                None
+            };
+            let span = every.start..from.as_ref().unwrap_or(&step).span.end;
+
+            return Ok(Item::Stepped { step, from, span });
+        }
+
+        let starting = self.parse_atom()?;
+
+        // Anything but `to` or the end of the item after a weekday's first
+        // atom makes that atom an ordinal, as in `second tue`
+        if kind == DateTimePartKind::Weekday
+            && !self.at_end_of_clause()
+            && !self.at_keyword("to")
+            && !self.at_keyword("or")
+        {
+            let weekday = self.parse_atom()?;
+            let span = starting.span.start..weekday.span.end;
+
+            return Ok(Item::Nth {
+                ordinal: starting,
+                weekday,
+                span,
+            });
+        }
+
+        if !self.at_keyword("to") {
+            return Ok(Item::Single(starting));
+        }
+        self.position += 1;
+
+        let ending = self.parse_atom()?;
+        let span = starting.span.start..ending.span.end;
+
+        Ok(Item::Range {
+            starting,
+            ending,
+            span,
+        })
+    }
+
+    /// Parses the `14:30` in `at 14:30` or `on 2026-12-24 14:30`
+    fn parse_hour_and_minute(&mut self) -> Spanned<(Atom<'a>, Atom<'a>)> {
+        let hour = self.parse_number("a time of day, such as `14:30`")?;
+
+        if !matches!(self.peek(), Some((Token::Colon, _))) {
+            return Err(self.unexpected("`:`"));
+        }
+        self.position += 1;
+
+        let minute = self.parse_number("minutes")?;
+
+        Ok((hour, minute))
+    }
+
+    fn parse_number(&mut self, expected: &str) -> Spanned<Atom<'a>> {
+        match self.peek() {
+            Some((Token::Number, span)) => {
+                self.position += 1;
+
+                Ok(Atom {
+                    text: self.text(&span),
+                    span,
+                })
+            }
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    fn parse_atom(&mut self) -> Spanned<Atom<'a>> {
+        match self.peek() {
+            Some((Token::Number | Token::Word, span)) => {
+                self.position += 1;
+
+                Ok(Atom {
+                    text: self.text(&span),
+                    span,
+                })
+            }
+            _ => Err(self.unexpected("a value")),
+        }
+    }
+}
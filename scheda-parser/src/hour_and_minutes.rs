@@ -1,25 +1,30 @@
-use scheda_core::{Hour, Minute};
+use scheda_core::{Hour, Minute, Second};
 
 use super::AtomParse;
 
-fn parse_ranged(val: &str, max: u8) -> Option<u8> {
-    let idx = val.parse().ok()?;
-
-    (idx <= max).then_some(idx)
-}
-
 impl AtomParse for Hour {
-    fn parse_atom(val: &str) -> Option<Self> {
-        let trimmed = val.trim();
+    const EXPECTED: &'static str = "an hour";
+    const BOUNDS: Option<(u8, u8)> = Some((Hour::MIN.get(), Hour::MAX.get()));
 
-        parse_ranged(trimmed, 24).and_then(Hour::new)
+    fn parse_atom(val: &str) -> Option<Self> {
+        val.trim().parse().ok().and_then(Hour::new)
     }
 }
 
 impl AtomParse for Minute {
+    const EXPECTED: &'static str = "a minute";
+    const BOUNDS: Option<(u8, u8)> = Some((Minute::MIN.get(), Minute::MAX.get()));
+
     fn parse_atom(val: &str) -> Option<Self> {
-        let trimmed = val.trim();
+        val.trim().parse().ok().and_then(Minute::new)
+    }
+}
 
-        parse_ranged(trimmed, 60).and_then(Minute::new)
+impl AtomParse for Second {
+    const EXPECTED: &'static str = "a second";
+    const BOUNDS: Option<(u8, u8)> = Some((Second::MIN.get(), Second::MAX.get()));
+
+    fn parse_atom(val: &str) -> Option<Self> {
+        val.trim().parse().ok().and_then(Second::new)
     }
 }
@@ -0,0 +1,116 @@
+use logos::Logos;
+use scheda_core::Span;
+
+#[derive(Logos, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    #[token(",")]
+    Comma,
+
+    #[token(":")]
+    Colon,
+
+    /// Parentheses around nested schedules, as in `except (when weekday sat, hour 9)`
+    #[token("(")]
+    LParen,
+
+    #[token(")")]
+    RParen,
+
+    /// Negative numbers only make sense as days counted from the end of the month
+    #[regex("-?[0-9]+")]
+    Number,
+
+    /// ISO 8601 dates, such as `2026-12-24`, which may also have a time of
+    /// day after a `T`, as in `2026-12-24T23:00`
+    #[regex("[0-9]+-[0-9]+-[0-9]+(T[0-9]+:[0-9]+)?")]
+    Date,
+
+    /// Keywords, date-time part identifiers and named atoms, such as `jan`
+    #[regex("[A-Za-z][A-Za-z0-9_]*")]
+    Word,
+
+    /// Slash-separated names, such as the time zone `America/Sao_Paulo`
+    #[regex("[A-Za-z][A-Za-z0-9_+-]*(/[A-Za-z0-9_+-]+)+")]
+    Path,
+
+    #[error]
+    #[regex(r"\s+", logos::skip)]
+    Error,
+}
+
+/// Splits `source` into tokens, dropping whitespace
+pub fn tokenize(source: &str) -> Vec<(Token, Span)> {
+    Token::lexer(source).spanned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, Token};
+
+    #[test]
+    fn tokenizes_schedules() {
+        let tokens: Vec<_> = tokenize("when month jan  to\tmar,hour 1")
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect();
+
+        assert_eq!(
+            tokens,
+            [
+                Token::Word,
+                Token::Word,
+                Token::Word,
+                Token::Word,
+                Token::Word,
+                Token::Comma,
+                Token::Word,
+                Token::Number
+            ]
+        );
+
+        assert_eq!(tokenize("whenever"), [(Token::Word, 0..8)]);
+        assert_eq!(
+            tokenize("2:30pm"),
+            [
+                (Token::Number, 0..1),
+                (Token::Colon, 1..2),
+                (Token::Number, 2..4),
+                (Token::Word, 4..6)
+            ]
+        );
+        assert_eq!(
+            tokenize("in America/Port-au-Prince"),
+            [(Token::Word, 0..2), (Token::Path, 3..25)]
+        );
+        assert_eq!(
+            tokenize("on 2026-12-24 23:00 or 2027-01-01T09:30, day -3"),
+            [
+                (Token::Word, 0..2),
+                (Token::Date, 3..13),
+                (Token::Number, 14..16),
+                (Token::Colon, 16..17),
+                (Token::Number, 17..19),
+                (Token::Word, 20..22),
+                (Token::Date, 23..39),
+                (Token::Comma, 39..40),
+                (Token::Word, 41..44),
+                (Token::Number, 45..47)
+            ]
+        );
+        assert_eq!(
+            tokenize("except (when hour 9)"),
+            [
+                (Token::Word, 0..6),
+                (Token::LParen, 7..8),
+                (Token::Word, 8..12),
+                (Token::Word, 13..17),
+                (Token::Number, 18..19),
+                (Token::RParen, 19..20)
+            ]
+        );
+        assert_eq!(
+            tokenize("month @"),
+            [(Token::Word, 0..5), (Token::Error, 6..7)]
+        );
+    }
+}
@@ -1,86 +1,172 @@
-use std::ops::Range;
+use std::{num::NonZeroU8, ops::Range};
 
+pub mod ast;
+#[cfg(feature = "serde")]
+pub mod compact;
+pub mod cron;
+mod date;
 mod day;
+mod dst;
+mod grammar;
 mod hour_and_minutes;
+mod lexer;
 mod month;
+pub mod systemd;
 mod weekday;
 
-use scheda_core::{DateTimePart, DateTimePartKind, Error, Result, Schedule, WellFormedRange};
+use ast::{Atom, Clause, Item};
+use grammar::Grammar;
+use lexer::{tokenize, Token};
+use scheda_core::{
+    chrono_tz::Tz, AbsoluteDate, AsU8, DateTimePart, DateTimePartKind, Diagnostic, Error,
+    GapPolicy, Hour, Minute, MonthDay, NthWeekday, OverlapPolicy, Result, Schedule, ScheduleExpr,
+    Second, Span, Spec, TimeOfDay, Warning, WellFormedRange,
+};
 
 /// Attempts to parse a single "atom"
 pub trait AtomParse: Sized {
+    /// How this atom is described in error messages, e.g. `"a month"`
+    const EXPECTED: &'static str;
+
+    /// The smallest and largest numbers this atom can be written as, if any,
+    /// so that numbers outside of them are reported as out of range
+    const BOUNDS: Option<(u8, u8)> = None;
+
     fn parse_atom(val: &str) -> Option<Self>;
 }
 
+/// The result of parsing one piece of the input, pointing at it on failure
+type Spanned<T> = std::result::Result<T, Diagnostic>;
+
 pub struct Parser<'a> {
     input: &'a str,
     schedule: Schedule,
+    /// The steps of `every` clauses, which are only ANDed with the rest of the
+    /// schedule once every clause is parsed
+    steps: Vec<(DateTimePartKind, NonZeroU8, Span)>,
+    /// Errors found so far, including those within nested schedules
+    diagnostics: Vec<Diagnostic>,
+    /// The span of the item each part of the schedule came from, by spec and index
+    spans: Vec<(Spec, usize, Span)>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Parser<'a> {
         Self {
-            input: input.trim(),
+            input,
             schedule: Schedule::new(),
+            steps: Vec::new(),
+            diagnostics: Vec::new(),
+            spans: Vec::new(),
         }
     }
 }
 
 impl<'a> Parser<'a> {
-    fn eat_when(&mut self) -> Result<()> {
-        let (before_when, remaining) = self
-            .input
-            .split_once("when")
-            .ok_or(Error::MissingWhenStmt)?;
-
-        // TODO: treat this with the error enum
-        assert!(before_when.is_empty());
+    /// Parses the input into its syntax tree, without checking any of its values
+    pub fn parse_ast(&self) -> Result<ast::Schedule<'a>> {
+        let (schedule, diagnostics) = Grammar::new(self.input).parse_schedule();
 
-        self.input = remaining;
+        if !diagnostics.is_empty() {
+            return Err(Error::Parse(diagnostics));
+        }
 
-        Ok(())
+        Ok(schedule)
     }
 
-    fn parse_kind<'b>(&'a self, value: &'b str) -> Result<(DateTimePartKind, &'b str)> {
-        // TODO: treat error
-        let (identifier, rest) = value.split_once(' ').ok_or(Error::InvalidSyntax(
-            "expected a date-time part identifier, such as `month` or `hour`".into(),
-        ))?;
+    fn parse_atom<T: AtomParse>(&self, atom: &Atom) -> Spanned<T> {
+        T::parse_atom(atom.text).ok_or_else(|| {
+            let found = atom.text.into();
 
-        DateTimePartKind::parse(identifier).map(|kind| (kind, rest))
+            match T::BOUNDS {
+                Some((min, max)) if atom.text.parse::<i64>().is_ok() => Error::OutOfRange {
+                    expected: T::EXPECTED.into(),
+                    min,
+                    max,
+                    found,
+                },
+                _ => Error::InvalidValue {
+                    expected: T::EXPECTED.into(),
+                    found,
+                },
+            }
+            .at(atom.span.clone())
+        })
     }
 
-    fn parse_atom_or_range<T: AtomParse + WellFormedRange>(
-        &mut self,
-        value: &str,
-    ) -> Result<DateTimePart<T>> {
-        match parse_range(value) {
-            Ok(range) => Ok(DateTimePart::Range {
-                starting: range.start,
-                ending: range.end,
-            }),
-            Err(_) => {
-                // TODO: why error handling so bad :C
-                let atom =
-                    T::parse_atom(value).ok_or(Error::InvalidSyntax(value.to_string().into()))?;
-                Ok(DateTimePart::Single(atom))
+    fn parse_atom_or_range<T: AtomParse + WellFormedRange + PartialEq>(
+        &self,
+        item: &Item,
+    ) -> Spanned<DateTimePart<T>> {
+        match item {
+            Item::Single(atom) => self.parse_atom(atom).map(DateTimePart::Single),
+            Item::Range {
+                starting,
+                ending,
+                span,
+            } => {
+                let range = Range {
+                    start: self.parse_atom(starting)?,
+                    end: self.parse_atom(ending)?,
+                };
+
+                range_part(range).map_err(|error| error.at(span.clone()))
             }
+            Item::Stepped { step, from, .. } => Ok(DateTimePart::Step {
+                starting: match from {
+                    Some(from) => self.parse_atom(from)?,
+                    None => T::FIRST,
+                },
+                step: self.parse_step(step, u8::MAX)?,
+            }),
+            Item::Step { .. } => Err(Error::InvalidSyntax(
+                "steps are only allowed in `every` clauses".into(),
+            )
+            .at(item.span())),
+            Item::Nth { .. } => Err(Error::InvalidSyntax(
+                "ordinals are only allowed in `weekday` clauses".into(),
+            )
+            .at(item.span())),
+            Item::Time { .. } => Err(Error::InvalidSyntax(
+                "times of day are only allowed in `at` clauses".into(),
+            )
+            .at(item.span())),
+            Item::Date { .. } => Err(Error::InvalidSyntax(
+                "dates are only allowed in `on` clauses".into(),
+            )
+            .at(item.span())),
+            Item::Nested { .. } => Err(Error::InvalidSyntax(
+                "nested schedules are only allowed in `except` clauses".into(),
+            )
+            .at(item.span())),
         }
     }
 
-    fn parse_spec(&mut self, value: &str) -> Result<()> {
-        let (kind, rest) = self.parse_kind(value)?;
+    fn parse_spec(&mut self, clause: &Clause) -> Spanned<()> {
+        for item in &clause.items {
+            let lengths = Spec::ALL.map(|spec| self.schedule.spec_len(spec));
 
-        for item in rest.split(" or ") {
-            match kind {
+            match clause.kind {
+                DateTimePartKind::Year => {
+                    let part = self.parse_atom_or_range(item)?;
+                    self.schedule.year_spec.push(part);
+                }
                 DateTimePartKind::Month => {
                     let part = self.parse_atom_or_range(item)?;
                     self.schedule.month_spec.push(part);
                 }
+                DateTimePartKind::Day if day::counts_from_month_end(item) => {
+                    let part = self.parse_atom_or_range(item)?;
+                    self.schedule.month_end_spec.push(part);
+                }
                 DateTimePartKind::Day => {
                     let part = self.parse_atom_or_range(item)?;
                     self.schedule.day_spec.push(part);
                 }
+                DateTimePartKind::Weekday if matches!(item, Item::Nth { .. }) => {
+                    let nth_weekday = self.parse_nth_weekday(item)?;
+                    self.schedule.nth_weekday_spec.push(nth_weekday);
+                }
                 DateTimePartKind::Weekday => {
                     let part = self.parse_atom_or_range(item)?;
                     self.schedule.weekday_spec.push(part);
@@ -93,60 +179,563 @@ impl<'a> Parser<'a> {
                     let part = self.parse_atom_or_range(item)?;
                     self.schedule.minute_spec.push(part);
                 }
-                DateTimePartKind::Every => todo!(),
+                DateTimePartKind::Second => {
+                    let part = self.parse_atom_or_range(item)?;
+                    self.schedule.second_spec.push(part);
+                }
+                DateTimePartKind::Every => self.parse_every(item)?,
+                DateTimePartKind::Combine => {
+                    self.schedule.day_combination = match item {
+                        Item::Single(atom) => self.parse_atom(atom)?,
+                        _ => {
+                            return Err(Error::InvalidSyntax("expected `and` or `or`".into())
+                                .at(item.span()))
+                        }
+                    };
+                }
+                DateTimePartKind::At => {
+                    let time = self.parse_time(item)?;
+                    self.schedule.time_spec.push(time);
+                }
+                DateTimePartKind::On => {
+                    let date = self.parse_date(item)?;
+                    self.schedule.date_spec.push(date);
+                }
+                DateTimePartKind::Dst => self.parse_dst(item)?,
+                DateTimePartKind::Except => self.parse_exception(item)?,
+            }
+
+            // Remembers which item each part came from, for warnings to point at
+            for (spec, length) in Spec::ALL.into_iter().zip(lengths) {
+                if self.schedule.spec_len(spec) > length {
+                    self.spans.push((spec, length, item.span()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_timezone(&self, atom: &Atom) -> Spanned<Tz> {
+        atom.text.parse().map_err(|_| {
+            Error::InvalidValue {
+                expected: "an IANA time zone, such as `America/Sao_Paulo`".into(),
+                found: atom.text.into(),
+            }
+            .at(atom.span.clone())
+        })
+    }
+
+    /// Parses a single `every` item, such as `15 minutes` or `day`
+    fn parse_every(&mut self, item: &Item) -> Spanned<()> {
+        let Item::Step { count, unit } = item else {
+            return Err(
+                Error::InvalidSyntax("expected a step, such as `15 minutes`".into())
+                    .at(item.span()),
+            );
+        };
+
+        let (kind, max_step) = match unit.text {
+            x if x.eq_ignore_ascii_case("second") || x.eq_ignore_ascii_case("seconds") => {
+                (DateTimePartKind::Second, 59)
+            }
+            x if x.eq_ignore_ascii_case("minute") || x.eq_ignore_ascii_case("minutes") => {
+                (DateTimePartKind::Minute, 59)
+            }
+            x if x.eq_ignore_ascii_case("hour") || x.eq_ignore_ascii_case("hours") => {
+                (DateTimePartKind::Hour, 23)
+            }
+            x if x.eq_ignore_ascii_case("day") || x.eq_ignore_ascii_case("days") => {
+                (DateTimePartKind::Day, 30)
+            }
+            other => {
+                return Err(Error::InvalidValue {
+                    expected: "`seconds`, `minutes`, `hours` or `days`".into(),
+                    found: other.into(),
+                }
+                .at(unit.span.clone()))
+            }
+        };
+
+        let step = match count {
+            Some(count) => self.parse_step(count, max_step)?,
+            None => NonZeroU8::MIN,
+        };
+
+        self.steps.push((kind, step, item.span()));
+
+        Ok(())
+    }
+
+    fn parse_step(&self, step: &Atom, max_step: u8) -> Spanned<NonZeroU8> {
+        step.text
+            .parse()
+            .ok()
+            .and_then(NonZeroU8::new)
+            .filter(|step| step.get() <= max_step)
+            .ok_or_else(|| {
+                Error::InvalidValue {
+                    expected: format!("a step between 1 and {max_step}").into(),
+                    found: step.text.into(),
+                }
+                .at(step.span.clone())
+            })
+    }
+
+    /// Parses a single `dst` item, which sets either half of the DST policy
+    fn parse_dst(&mut self, item: &Item) -> Spanned<()> {
+        let Item::Single(atom) = item else {
+            return Err(
+                Error::InvalidSyntax("expected `skip`, `shift`, `once` or `twice`".into())
+                    .at(item.span()),
+            );
+        };
+
+        match GapPolicy::parse_atom(atom.text) {
+            Some(gap) => self.schedule.dst_policy.gap = gap,
+            None => self.schedule.dst_policy.overlap = self.parse_atom::<OverlapPolicy>(atom)?,
+        }
+
+        Ok(())
+    }
+
+    /// Parses a single ordinal `weekday` item, such as `second tue`
+    fn parse_nth_weekday(&self, item: &Item) -> Spanned<NthWeekday> {
+        let Item::Nth {
+            ordinal, weekday, ..
+        } = item
+        else {
+            return Err(Error::InvalidSyntax(
+                "expected an ordinal weekday, such as `second tue`".into(),
+            )
+            .at(item.span()));
+        };
+
+        Ok(NthWeekday {
+            ordinal: self.parse_atom(ordinal)?,
+            weekday: self.parse_atom(weekday)?,
+        })
+    }
+
+    /// Parses a single `at` item, such as `14:30` or `2:30 pm`
+    fn parse_time(&self, item: &Item) -> Spanned<TimeOfDay> {
+        let Item::Time {
+            hour,
+            minute,
+            meridiem,
+            ..
+        } = item
+        else {
+            return Err(
+                Error::InvalidSyntax("expected a time of day, such as `14:30`".into())
+                    .at(item.span()),
+            );
+        };
+
+        let time = TimeOfDay {
+            hour: self.parse_atom(hour)?,
+            minute: self.parse_atom(minute)?,
+        };
+
+        let Some(meridiem) = meridiem else {
+            return Ok(time);
+        };
+
+        // 12 am is midnight and 12 pm is noon
+        let hour_of_half_day = match time.hour.get() {
+            12 => 0,
+            hour @ 1..=11 => hour,
+            _ => {
+                return Err(Error::InvalidValue {
+                    expected: "an hour between 1 and 12".into(),
+                    found: hour.text.into(),
+                }
+                .at(hour.span.clone()))
+            }
+        };
+        let afternoon = if meridiem.text.eq_ignore_ascii_case("pm") {
+            12
+        } else {
+            0
+        };
+
+        Ok(TimeOfDay {
+            hour: Hour::new(hour_of_half_day + afternoon).unwrap(),
+            ..time
+        })
+    }
+
+    /// Parses a single `on` item, such as `2026-12-24` or `2026-12-24 23:00`
+    fn parse_date(&self, item: &Item) -> Spanned<AbsoluteDate> {
+        let Item::Date { date, time, .. } = item else {
+            return Err(
+                Error::InvalidSyntax("expected a date, such as `2026-12-24`".into())
+                    .at(item.span()),
+            );
+        };
+
+        let time = match time {
+            Some((hour, minute)) => Some(TimeOfDay {
+                hour: self.parse_atom(hour)?,
+                minute: self.parse_atom(minute)?,
+            }),
+            None => None,
+        };
+
+        Ok(AbsoluteDate {
+            date: self.parse_atom(date)?,
+            time,
+        })
+    }
+
+    /// Parses a nested schedule, reporting its errors alongside this one's
+    fn parse_exception(&mut self, item: &Item) -> Spanned<()> {
+        let Item::Nested { schedule, .. } = item else {
+            return Err(Error::InvalidValue {
+                expected: "a schedule".into(),
+                found: self.input[item.span()].into(),
+            }
+            .at(item.span()));
+        };
+
+        if let Some(timezone) = &schedule.timezone {
+            return Err(Error::InvalidSyntax(
+                "exceptions are matched in the time zone of their schedule".into(),
+            )
+            .at(timezone.span.clone()));
+        }
+
+        match Parser::new(self.input).build(schedule) {
+            Ok(exception) => self.schedule.exceptions.push(exception),
+            Err(diagnostics) => self.diagnostics.extend(diagnostics),
+        }
+
+        Ok(())
+    }
+
+    /// ANDs the steps of `every` clauses with the clause of the field they
+    /// step through, so `every 2 hours, hour 9 to 17` only fires at 10, 12, 14
+    /// and 16 o'clock.
+    fn apply_steps(&mut self) -> Spanned<()> {
+        for (kind, step, span) in std::mem::take(&mut self.steps) {
+            let (spec, name) = match kind {
+                DateTimePartKind::Second => (Spec::Second, "second"),
+                DateTimePartKind::Minute => (Spec::Minute, "minute"),
+                DateTimePartKind::Hour => (Spec::Hour, "hour"),
+                _ => (Spec::Day, "day"),
+            };
+
+            if spec == Spec::Day && !self.schedule.month_end_spec.is_empty() {
+                return Err(Error::InvalidSyntax(
+                    "steps through days can't be combined with days counted from the month's end"
+                        .into(),
+                )
+                .at(span));
+            }
+
+            let replaces_parts = self.schedule.spec_len(spec) > 0 && step.get() > 1;
+            let schedule = &mut self.schedule;
+            let stepped = match spec {
+                Spec::Second => step_through(
+                    &schedule.second_spec,
+                    step,
+                    (0..=59).filter_map(Second::new),
+                )
+                .map(|parts| schedule.second_spec = parts.into_iter().collect()),
+                Spec::Minute => step_through(
+                    &schedule.minute_spec,
+                    step,
+                    (0..=59).filter_map(Minute::new),
+                )
+                .map(|parts| schedule.minute_spec = parts.into_iter().collect()),
+                Spec::Hour => {
+                    step_through(&schedule.hour_spec, step, (0..=23).filter_map(Hour::new))
+                        .map(|parts| schedule.hour_spec = parts.into_iter().collect())
+                }
+                _ => step_through(
+                    &schedule.day_spec,
+                    step,
+                    (1..=31).filter_map(MonthDay::from_u8),
+                )
+                .map(|parts| schedule.day_spec = parts.into_iter().collect()),
+            };
+
+            if stepped.is_none() {
+                return Err(Error::InvalidSyntax(
+                    format!("step never matches the `{name}` clause, unless started `from` elsewhere in it").into(),
+                )
+                .at(span));
+            }
+
+            // The clause's parts were replaced, so warnings can't point at them
+            if replaces_parts {
+                self.spans.retain(|(other, ..)| *other != spec);
             }
         }
 
         Ok(())
     }
 
+    /// `every 2 hours` should fire once every two hours, not on every minute
+    /// of every other hour, so the fields finer than a step are pinned to their
+    /// first value unless they were given explicitly.
+    ///
+    /// Steps given in a field's own clause, e.g. `hour every 2`, pin nothing.
+    fn pin_fields_below_steps(&mut self) {
+        let steps = |kind| self.steps.iter().any(|(other, ..)| *other == kind);
+        let (steps_days, steps_hours, steps_minutes) = (
+            steps(DateTimePartKind::Day),
+            steps(DateTimePartKind::Hour),
+            steps(DateTimePartKind::Minute),
+        );
+
+        // Exact times of day already pin both the hour and the minute
+        if !self.schedule.time_spec.is_empty() {
+            return;
+        }
+
+        if steps_days && !steps_hours && self.schedule.hour_spec.is_empty() {
+            self.schedule
+                .hour_spec
+                .push(DateTimePart::Single(Hour::new(0).unwrap()));
+        }
+
+        if (steps_days || steps_hours) && !steps_minutes && self.schedule.minute_spec.is_empty() {
+            self.schedule
+                .minute_spec
+                .push(DateTimePart::Single(Minute::new(0).unwrap()));
+        }
+    }
+
+    /// Parses the whole input into a [`Schedule`].
+    ///
+    /// Parsing doesn't stop at the first bad date-time part: on failure, the
+    /// returned [`Error::Parse`] lists every error found.
     pub fn parse_schedule(mut self) -> Result<Schedule> {
-        // Eat the leading `when` statement
-        self.eat_when()?;
+        self.parse()
+    }
 
-        // Date-time parts are separated by commas
-        for decl in self.input.split(',') {
-            self.parse_spec(decl.trim())?;
+    /// Parses the whole input into a [`Schedule`], like [`Self::parse_schedule`],
+    /// along with warnings about what it says that's valid but unlikely to be
+    /// meant, such as it never firing. See [`Schedule::analyze`].
+    ///
+    /// Warnings about single values point at them, and the others at the whole
+    /// schedule.
+    pub fn parse_with_warnings(mut self) -> Result<(Schedule, Vec<Diagnostic<Warning>>)> {
+        let schedule = self.parse()?;
+
+        let start = self.input.len() - self.input.trim_start().len();
+        let whole = start..self.input.trim_end().len();
+
+        let mut warnings: Vec<_> = schedule
+            .analyze()
+            .into_iter()
+            .map(|warning| {
+                let part = match warning {
+                    Warning::RedundantPart { spec, index }
+                    | Warning::OverlappingParts { spec, index, .. } => Some((spec, index)),
+                    _ => None,
+                };
+                let span = self
+                    .spans
+                    .iter()
+                    .find(|(spec, index, _)| Some((*spec, *index)) == part)
+                    .map_or(whole.clone(), |(_, _, span)| span.clone());
+
+                warning.at(span)
+            })
+            .collect();
+        warnings.sort_by_key(|warning| warning.span.start);
+
+        Ok((schedule, warnings))
+    }
+
+    fn parse(&mut self) -> Result<Schedule> {
+        let (ast, diagnostics) = Grammar::new(self.input).parse_schedule();
+        self.diagnostics = diagnostics;
+
+        self.build(&ast).map_err(|mut diagnostics| {
+            diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+            Error::Parse(diagnostics)
+        })
+    }
+
+    /// Parses the whole input into a [`ScheduleExpr`], either schedules
+    /// between parentheses combined with `and`, `or`, `but not` and `not`, or
+    /// a single schedule.
+    ///
+    /// Every schedule's errors are reported, as with [`Self::parse_schedule`].
+    pub fn parse_expr(mut self) -> Result<ScheduleExpr> {
+        let (ast, diagnostics) = Grammar::new(self.input).parse_expr();
+        self.diagnostics = diagnostics;
+
+        let expr = self.build_expr(&ast);
+
+        if !self.diagnostics.is_empty() {
+            self.diagnostics
+                .sort_by_key(|diagnostic| diagnostic.span.start);
+            return Err(Error::Parse(self.diagnostics));
+        }
+
+        Ok(expr)
+    }
+
+    fn build_expr(&mut self, ast: &ast::Expr) -> ScheduleExpr {
+        match ast {
+            ast::Expr::Schedule(schedule) => match Parser::new(self.input).build(schedule) {
+                Ok(schedule) => ScheduleExpr::Schedule(schedule),
+                Err(diagnostics) => {
+                    self.diagnostics.extend(diagnostics);
+                    ScheduleExpr::Schedule(Schedule::new())
+                }
+            },
+            ast::Expr::And(left, right) => ScheduleExpr::And(
+                Box::new(self.build_expr(left)),
+                Box::new(self.build_expr(right)),
+            ),
+            ast::Expr::Or(left, right) => ScheduleExpr::Or(
+                Box::new(self.build_expr(left)),
+                Box::new(self.build_expr(right)),
+            ),
+            ast::Expr::Not(expr) => ScheduleExpr::Not(Box::new(self.build_expr(expr))),
         }
+    }
+
+    /// Checks the values of a syntax tree, building it into a [`Schedule`]
+    fn build(&mut self, ast: &ast::Schedule) -> std::result::Result<Schedule, Vec<Diagnostic>> {
+        // Clauses that parsed may still hold bad values, which are worth
+        // reporting alongside any syntax errors
+        for clause in &ast.clauses {
+            if let Err(diagnostic) = self.parse_spec(clause) {
+                self.diagnostics.push(diagnostic);
+            }
+        }
+
+        if let Some(timezone) = &ast.timezone {
+            match self.parse_timezone(timezone) {
+                Ok(timezone) => self.schedule.timezone = Some(timezone),
+                Err(diagnostic) => self.diagnostics.push(diagnostic),
+            }
+        }
+
+        // Pinning needs to know which fields were stepped through
+        self.pin_fields_below_steps();
+
+        if let Err(diagnostic) = self.apply_steps() {
+            self.diagnostics.push(diagnostic);
+        }
+
+        if !self.diagnostics.is_empty() {
+            return Err(std::mem::take(&mut self.diagnostics));
+        }
+
+        Ok(std::mem::take(&mut self.schedule))
+    }
+}
+
+/// The parts of a field matching both its clause's `parts` and every `step`-th
+/// one of its `values`, as single values unless either matches everything, or
+/// `None` if no value matches both
+fn step_through<T: AsU8 + WellFormedRange + Copy>(
+    parts: &[DateTimePart<T>],
+    step: NonZeroU8,
+    values: impl Iterator<Item = T>,
+) -> Option<Vec<DateTimePart<T>>> {
+    let stepped = DateTimePart::Step {
+        starting: T::FIRST,
+        step,
+    };
 
-        Ok(self.schedule)
+    // Stepping through every value doesn't restrict anything
+    if step.get() == 1 {
+        return Some(parts.to_vec());
     }
+    if parts.is_empty() {
+        return Some(vec![stepped]);
+    }
+
+    let matched: Vec<_> = values
+        .filter(|value| stepped.contains(value) && parts.iter().any(|part| part.contains(value)))
+        .map(DateTimePart::Single)
+        .collect();
+
+    (!matched.is_empty()).then_some(matched)
 }
 
-fn nothing() {}
+fn malformed_range() -> Error {
+    Error::MalformedRange("range's starting point is bigger than its end")
+}
 
-fn parse_range<T: AtomParse + WellFormedRange>(input: &str) -> Result<Range<T>> {
-    fn parse_inner<T: AtomParse + WellFormedRange>(input: &str) -> Option<Range<T>> {
-        let mut parts = input.trim().split(' ');
+fn single_point_range() -> Error {
+    Error::MalformedRange("range starts and ends at the same value, which is written on its own")
+}
 
-        let start = T::parse_atom(parts.next()?)?;
+/// The part a range of values stands for, which wraps around the end of the
+/// cycle if it ends before it starts, e.g. `nov to feb`
+fn range_part<T: WellFormedRange + PartialEq>(range: Range<T>) -> Result<DateTimePart<T>> {
+    if T::is_well_formed(&range) {
+        return Ok(DateTimePart::Range {
+            starting: range.start,
+            ending: range.end,
+        });
+    }
 
-        // Eat "to"
-        (parts.next()? == "to").then(nothing)?;
+    // Flipping the bounds tells ranges that wrap around apart from
+    // single-point ones, such as `jan to jan`
+    let flipped = range.end..range.start;
 
-        let end = T::parse_atom(parts.next()?)?;
+    if T::WRAPS_AROUND && T::is_well_formed(&flipped) {
+        return Ok(DateTimePart::WrappingRange {
+            starting: flipped.end,
+            ending: flipped.start,
+        });
+    }
 
-        Some(Range { start, end })
+    if flipped.start == flipped.end {
+        return Err(single_point_range());
     }
 
-    let range = parse_inner(input).ok_or(Error::MalformedRange("incorrect syntax"))?;
+    Err(malformed_range())
+}
+
+/// Parses a standalone range, such as `jan to mar`, or `nov to feb` for one
+/// wrapping around, as in a `when` schedule
+pub fn parse_range<T: AtomParse + WellFormedRange + PartialEq>(
+    input: &str,
+) -> Result<DateTimePart<T>> {
+    let parse_inner = || -> Option<Range<T>> {
+        let tokens = tokenize(input);
+        let text = |(_, span): &(Token, Span)| &input[span.clone()];
+
+        let [start, to, end] = tokens.as_slice() else {
+            return None;
+        };
 
-    // Ensure this range is well-formed
-    T::is_well_formed(&range)
-        .then(nothing)
-        .ok_or(Error::MalformedRange(
-            "range's starting point is bigger than its end",
-        ))?;
+        text(to).eq_ignore_ascii_case("to").then_some(Range {
+            start: T::parse_atom(text(start))?,
+            end: T::parse_atom(text(end))?,
+        })
+    };
 
-    Ok(range)
+    let range = parse_inner().ok_or(Error::MalformedRange("incorrect syntax"))?;
+
+    range_part(range)
 }
 
 #[cfg(test)]
 mod tests {
-    use scheda_core::chrono::Month;
-    use std::ops::Range;
+    use num_traits::FromPrimitive;
+    use proptest::prelude::*;
+    use scheda_core::chrono::{Duration, Month, NaiveDate, TimeZone, Utc, Weekday};
+    use std::{cmp::Ordering, fmt::Debug, num::NonZeroU8, ops::Range};
+
+    use scheda_core::{
+        chrono_tz::Tz, AbsoluteDate, DateTimePart, DateTimePartKind, DayCombination, DstPolicy,
+        Error, GapPolicy, Hour, Minute, MonthDay, MonthDayFromEnd, NthWeekday, Ordinal,
+        OverlapPolicy, Schedule, ScheduleExpr, Second, TimeOfDay, Year,
+    };
 
-    use super::{parse_range, Parser};
+    use super::{ast::Item, cron, parse_range, systemd, Parser};
 
     #[test]
     fn parses_month_specs() {
@@ -160,36 +749,54 @@ mod tests {
     fn parses_month_ranges() {
         assert_eq!(
             parse_range("Jan to Mar").unwrap(),
-            Range {
-                start: Month::January,
-                end: Month::March
+            DateTimePart::Range {
+                starting: Month::January,
+                ending: Month::March
             }
         );
         assert_eq!(
             parse_range("february to 10").unwrap(),
-            Range {
-                start: Month::February,
-                end: Month::October
+            DateTimePart::Range {
+                starting: Month::February,
+                ending: Month::October
             }
         );
         assert_eq!(
             parse_range("Nov to Dec").unwrap(),
-            Range {
-                start: Month::November,
-                end: Month::December
+            DateTimePart::Range {
+                starting: Month::November,
+                ending: Month::December
             }
         );
         assert_eq!(
             parse_range("2 to 10").unwrap(),
-            Range {
-                start: Month::February,
-                end: Month::October
+            DateTimePart::Range {
+                starting: Month::February,
+                ending: Month::October
+            }
+        );
+
+        // Ranges ending before they start wrap around, as in `when` schedules
+        assert_eq!(
+            parse_range("Dec to Feb").unwrap(),
+            DateTimePart::WrappingRange {
+                starting: Month::December,
+                ending: Month::February
+            }
+        );
+        assert_eq!(
+            parse_range("22 to 4").unwrap(),
+            DateTimePart::WrappingRange {
+                starting: Hour::new(22).unwrap(),
+                ending: Hour::new(4).unwrap()
             }
         );
 
-        // Bad: beginning bigger than ending
-        assert!(parse_range::<Month>("10 to 5").is_err());
-        assert!(parse_range::<Month>("Dec to Feb").is_err());
+        // Bad: days of the month don't wrap around
+        assert!(matches!(
+            parse_range::<MonthDay>("20 to 10"),
+            Err(Error::MalformedRange(reason)) if reason.contains("bigger than its end")
+        ));
 
         // Bad: single-point ranges are not allowed
         assert!(parse_range::<Month>("Dec to Dec").is_err());
@@ -199,4 +806,1166 @@ mod tests {
         assert!(parse_range::<Month>("Dec to Tuesday").is_err());
         assert!(parse_range::<Month>("13 to 19").is_err());
     }
+
+    #[test]
+    fn parses_every_specs() {
+        for (input, expected) in [
+            ("when every 15 minutes", "when minute every 15"),
+            (
+                "when every 2 hours, weekday mon to fri",
+                "when weekday mon to fri, hour every 2, minute 0",
+            ),
+            ("when every 3 days", "when day every 3, hour 0, minute 0"),
+            ("when every hour", "when minute 0"),
+            ("when every minute", "when"),
+            (
+                "when every 1 days, hour 9 to 17",
+                "when hour 9 to 17, minute 0",
+            ),
+        ] {
+            assert_eq!(
+                Parser::new(input).parse_schedule().unwrap().to_string(),
+                expected
+            );
+        }
+
+        // Bad: unknown units, zero or oversized steps
+        assert!(Parser::new("when every 3 weeks").parse_schedule().is_err());
+        assert!(Parser::new("when every 0 minutes")
+            .parse_schedule()
+            .is_err());
+        assert!(Parser::new("when every 60 minutes")
+            .parse_schedule()
+            .is_err());
+        assert!(Parser::new("when every few hours")
+            .parse_schedule()
+            .is_err());
+    }
+
+    #[test]
+    fn starts_day_steps_over_every_month() {
+        let from = Utc.with_ymd_and_hms(2024, 1, 27, 12, 0, 0).unwrap();
+        let upcoming = |input: &str| {
+            Parser::new(input)
+                .parse_schedule()
+                .unwrap()
+                .upcoming(from)
+                .take(4)
+                .map(|date_time| date_time.format("%m-%d").to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            upcoming("when every 3 days"),
+            ["01-28", "01-31", "02-01", "02-04"]
+        );
+        assert_eq!(
+            upcoming("when every 2 days"),
+            ["01-29", "01-31", "02-01", "02-03"]
+        );
+    }
+
+    #[test]
+    fn ands_every_with_clauses_of_its_field() {
+        let from = Utc.with_ymd_and_hms(2024, 3, 8, 17, 30, 0).unwrap();
+        let upcoming = |input: &str| {
+            Parser::new(input)
+                .parse_schedule()
+                .unwrap()
+                .upcoming(from)
+                .take(4)
+                .map(|date_time| date_time.format("%d %H:%M").to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            upcoming("when every 2 hours, hour 9 to 17"),
+            ["09 10:00", "09 12:00", "09 14:00", "09 16:00"]
+        );
+        assert_eq!(
+            upcoming("when hour 9 to 17, every 2 hours"),
+            upcoming("when every 2 hours, hour 9 to 17")
+        );
+        assert_eq!(
+            upcoming("when every 15 minutes, minute 30"),
+            ["08 18:30", "08 19:30", "08 20:30", "08 21:30"]
+        );
+        assert_eq!(
+            upcoming("when every 2 hours, every 3 hours"),
+            ["08 18:00", "09 00:00", "09 06:00", "09 12:00"]
+        );
+        assert_eq!(
+            upcoming("when every 10 days, day 5 to 25"),
+            ["11 00:00", "21 00:00", "11 00:00", "21 00:00"]
+        );
+        // Steps through the whole field leave its clause as it is
+        assert_eq!(
+            display("when every hour, hour 9 to 17"),
+            "when hour 9 to 17, minute 0"
+        );
+        assert_eq!(
+            display("when every 2 hours, every 15 minutes"),
+            "when hour every 2, minute every 15"
+        );
+
+        // Nothing would match both
+        assert_eq!(
+            parse_errors("when every 15 minutes, minute 5"),
+            [("invalid-syntax", "15 minutes")]
+        );
+        assert_eq!(
+            parse_errors("when every 2 days, day last"),
+            [("invalid-syntax", "2 days")]
+        );
+        assert!(Parser::new("when every 15 minutes, minute 30")
+            .parse_with_warnings()
+            .unwrap()
+            .1
+            .is_empty());
+    }
+
+    #[test]
+    fn parses_seconds() {
+        let second = |second| Second::new(second).unwrap();
+
+        let schedule = Parser::new("when second 0 or 30").parse_schedule().unwrap();
+        assert_eq!(
+            schedule.second_spec.as_slice(),
+            [
+                DateTimePart::Single(second(0)),
+                DateTimePart::Single(second(30))
+            ]
+        );
+
+        // Stepping through seconds leaves minutes alone
+        let schedule = Parser::new("when every 10 seconds")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(
+            schedule.second_spec.as_slice(),
+            [DateTimePart::Step {
+                starting: second(0),
+                step: NonZeroU8::new(10).unwrap()
+            }]
+        );
+        assert!(schedule.minute_spec.is_empty());
+
+        // `second` only starts a clause of its own after a comma
+        let schedule = Parser::new("when weekday second tue, second 15")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(schedule.nth_weekday_spec.len(), 1);
+        assert_eq!(
+            schedule.second_spec.as_slice(),
+            [DateTimePart::Single(second(15))]
+        );
+
+        assert_eq!(parse_errors("when second 60"), [("out-of-range", "60")]);
+        assert_eq!(
+            parse_errors("when every 60 seconds"),
+            [("invalid-value", "60")]
+        );
+    }
+
+    #[test]
+    fn parses_years_and_one_off_dates() {
+        let year = |year| Year::new(year).unwrap();
+        let date = |month, day| NaiveDate::from_ymd_opt(2026, month, day).unwrap();
+
+        let schedule = Parser::new("when year 2027 to 2028 or every 4 from 2032")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(
+            schedule.year_spec.as_slice(),
+            [
+                DateTimePart::Range {
+                    starting: year(2027),
+                    ending: year(2028)
+                },
+                DateTimePart::Step {
+                    starting: year(2032),
+                    step: NonZeroU8::new(4).unwrap()
+                }
+            ]
+        );
+
+        // The time may come after a space or, as in ISO 8601, a `T`
+        let schedule = Parser::new("when on 2026-12-24 23:00 or 2026-12-25 or 2026-12-31T9:30")
+            .parse_schedule()
+            .unwrap();
+        let time = |hour, minute| TimeOfDay {
+            hour: Hour::new(hour).unwrap(),
+            minute: Minute::new(minute).unwrap(),
+        };
+        assert_eq!(
+            schedule.date_spec.as_slice(),
+            [
+                AbsoluteDate {
+                    date: date(12, 24),
+                    time: Some(time(23, 0))
+                },
+                AbsoluteDate {
+                    date: date(12, 25),
+                    time: None
+                },
+                AbsoluteDate {
+                    date: date(12, 31),
+                    time: Some(time(9, 30))
+                }
+            ]
+        );
+
+        assert_eq!(
+            parse_errors("when year 0, on 2026-02-30"),
+            [("invalid-value", "0"), ("invalid-value", "2026-02-30")]
+        );
+        assert_eq!(
+            parse_errors("when on 0000-01-01"),
+            [("invalid-value", "0000-01-01")]
+        );
+        assert_eq!(
+            parse_errors("when on 2026-12-24T24:00"),
+            [("out-of-range", "24")]
+        );
+        assert_eq!(parse_errors("when on 24/12"), [("invalid-syntax", "24")]);
+        assert_eq!(
+            parse_errors("when year 2028 to 2027"),
+            [("malformed-range", "2028 to 2027")]
+        );
+        assert_eq!(
+            parse_errors("when day 2026-12-24"),
+            [("invalid-syntax", "2026-12-24")]
+        );
+    }
+
+    #[test]
+    fn parses_exceptions() {
+        let hour = |hour| DateTimePart::Single(Hour::new(hour).unwrap());
+
+        // A single clause is short for a nested schedule holding just it, so
+        // an `or` after it carries on that clause
+        let schedule = Parser::new(
+            "when hour 9 or 17, except month dec or jan, except (when weekday sat, hour 17) \
+             or (when day 1) in Europe/Berlin",
+        )
+        .parse_schedule()
+        .unwrap();
+        assert_eq!(schedule.exceptions.len(), 3);
+        assert_eq!(
+            schedule.exceptions[0].month_spec.as_slice(),
+            [
+                DateTimePart::Single(Month::December),
+                DateTimePart::Single(Month::January)
+            ]
+        );
+        assert_eq!(schedule.exceptions[1].hour_spec.as_slice(), [hour(17)]);
+        assert_eq!(schedule.exceptions[1].weekday_spec.len(), 1);
+        assert_eq!(schedule.exceptions[2].day_spec.len(), 1);
+        assert_eq!(schedule.timezone, Some("Europe/Berlin".parse().unwrap()));
+
+        let schedule = Parser::new("when except (when hour 9, except (when minute 0))")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(schedule.exceptions[0].hour_spec.as_slice(), [hour(9)]);
+        assert_eq!(schedule.exceptions[0].exceptions.len(), 1);
+
+        // Errors within nested schedules are reported along with the others
+        assert_eq!(
+            parse_errors("when hour 25, except (when hour 24, minute 60), minute 61"),
+            [
+                ("out-of-range", "25"),
+                ("out-of-range", "24"),
+                ("out-of-range", "60"),
+                ("out-of-range", "61")
+            ]
+        );
+        assert_eq!(
+            parse_errors("when except (when hour 9 in UTC)"),
+            [("invalid-syntax", "UTC")]
+        );
+        assert_eq!(
+            parse_errors("when except (hour 9), hour 25"),
+            [("invalid-syntax", "hour"), ("out-of-range", "25")]
+        );
+        assert_eq!(
+            parse_errors("when except (when hour 9"),
+            [("invalid-syntax", "")]
+        );
+        assert_eq!(
+            parse_errors("when hour (when hour 9)"),
+            [("invalid-syntax", "(")]
+        );
+    }
+
+    fn parse_errors(input: &str) -> Vec<(&'static str, &str)> {
+        match Parser::new(input).parse_schedule() {
+            Err(Error::Parse(diagnostics)) => diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.code(), &input[diagnostic.span.clone()]))
+                .collect(),
+            other => panic!("expected parse errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn reports_errors_with_spans() {
+        assert_eq!(parse_errors("month jan"), [("missing-when", "month jan")]);
+        assert_eq!(
+            parse_errors("at once when month jan"),
+            [("invalid-syntax", "at once")]
+        );
+        assert_eq!(
+            parse_errors("when month jan, hour 25"),
+            [("out-of-range", "25")]
+        );
+        assert_eq!(parse_errors("when hour 24"), [("out-of-range", "24")]);
+        assert_eq!(parse_errors("when minute 60"), [("out-of-range", "60")]);
+        assert_eq!(parse_errors("when month 13"), [("out-of-range", "13")]);
+        assert_eq!(parse_errors("when day 0"), [("out-of-range", "0")]);
+        assert_eq!(
+            Parser::new("when hour 24")
+                .parse_schedule()
+                .unwrap_err()
+                .to_string(),
+            "expected an hour between 0 and 23, found `24`"
+        );
+        assert_eq!(
+            parse_errors("when months jan"),
+            [("unknown-date-time-part", "months")]
+        );
+        assert_eq!(
+            parse_errors("when every 90 minutes"),
+            [("invalid-value", "90")]
+        );
+
+        // Parsing resumes after every comma, so all errors are reported at once
+        assert_eq!(
+            parse_errors("when day 20 to 10, month dec, weekday funday, hour 1 or 2 or 99"),
+            [
+                ("malformed-range", "20 to 10"),
+                ("invalid-value", "funday"),
+                ("out-of-range", "99")
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_errors() {
+        let input = "when month jan,\n     weekday mon to sat or funday";
+
+        let Err(Error::Parse(diagnostics)) = Parser::new(input).parse_schedule() else {
+            panic!("expected a parse error");
+        };
+
+        assert_eq!(
+            diagnostics[0].render(input),
+            "error[invalid-value]: expected a weekday, found `funday`\n \
+             --> 2:28\n  \
+             |\n\
+             2 |      weekday mon to sat or funday\n  \
+             |                            ^^^^^^\n"
+        );
+    }
+
+    #[test]
+    fn reports_warnings_with_spans() {
+        let warnings = |input| {
+            let (_, warnings) = Parser::new(input).parse_with_warnings().unwrap();
+
+            warnings
+                .iter()
+                .map(|warning| (warning.code(), &input[warning.span.clone()]))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            warnings(" when month feb, day 30 "),
+            [("never-fires", "when month feb, day 30")]
+        );
+        assert_eq!(
+            warnings("when month feb, day 29"),
+            [("leap-years-only", "when month feb, day 29")]
+        );
+        assert_eq!(
+            warnings("when hour 1 to 3 or 2 to 5, day 1 or last or 1, minute every 15 or 30"),
+            [
+                ("overlapping-parts", "2 to 5"),
+                ("redundant-part", "1"),
+                ("redundant-part", "30")
+            ]
+        );
+        assert_eq!(
+            warnings("when hour 9, except hour 9"),
+            [("never-fires", "when hour 9, except hour 9")]
+        );
+        assert!(warnings("when hour 9 or 17, except month dec or jan").is_empty());
+
+        let input = "when at 9:00 or 9:00";
+        let (_, warnings) = Parser::new(input).parse_with_warnings().unwrap();
+        assert_eq!(
+            warnings[0].render(input),
+            "warning[redundant-part]: value already matched by the rest of its clause\n \
+             --> 1:17\n  \
+             |\n\
+             1 | when at 9:00 or 9:00\n  \
+             |                 ^^^^\n"
+        );
+    }
+
+    #[test]
+    fn ignores_whitespace() {
+        for input in [
+            "when month jan  to mar",
+            "  when\tmonth jan to\nmar ",
+            "when month jan to mar,hour 1",
+            "WHEN month jan TO mar OR dec",
+        ] {
+            Parser::new(input).parse_schedule().unwrap();
+        }
+
+        assert_eq!(
+            parse_range("Jan \t to  Mar").unwrap(),
+            DateTimePart::Range {
+                starting: Month::January,
+                ending: Month::March
+            }
+        );
+
+        // `when` has to be a word of its own
+        assert_eq!(
+            parse_errors("whenever month jan"),
+            [("missing-when", "whenever month jan")]
+        );
+        assert_eq!(parse_errors("when month jan to"), [("invalid-syntax", "")]);
+        assert_eq!(
+            parse_errors("when month jan feb, hour @"),
+            [("invalid-syntax", "feb"), ("invalid-syntax", "@")]
+        );
+    }
+
+    #[test]
+    fn parses_ast_with_spans() {
+        let input = "when month jan to mar or dec, every 15 minutes";
+        let ast = Parser::new(input).parse_ast().unwrap();
+
+        let [month, every] = ast.clauses.as_slice() else {
+            panic!("expected two clauses");
+        };
+
+        assert_eq!(month.kind, DateTimePartKind::Month);
+        assert_eq!(&input[month.span.clone()], "month jan to mar or dec");
+        assert_eq!(
+            month
+                .items
+                .iter()
+                .map(|item| &input[item.span()])
+                .collect::<Vec<_>>(),
+            ["jan to mar", "dec"]
+        );
+
+        assert_eq!(every.kind, DateTimePartKind::Every);
+        assert!(matches!(
+            &every.items[..],
+            [Item::Step { count: Some(count), unit }] if count.text == "15" && unit.text == "minutes"
+        ));
+    }
+
+    #[test]
+    fn parses_wrapping_ranges() {
+        for input in [
+            "when month nov to feb",
+            "when weekday fri to mon",
+            "when hour 22 to 4",
+            "when minute 45 to 15",
+        ] {
+            Parser::new(input).parse_schedule().unwrap();
+        }
+
+        // Bad: days of the month don't wrap, and single-point ranges still aren't allowed
+        assert_eq!(
+            parse_errors("when day 20 to 10"),
+            [("malformed-range", "20 to 10")]
+        );
+        assert_eq!(
+            parse_errors("when hour 4 to 4, month jan to january"),
+            [
+                ("malformed-range", "4 to 4"),
+                ("malformed-range", "jan to january")
+            ]
+        );
+
+        // Only single-point ranges are left once wrapping ones are told apart
+        let Err(Error::Parse(diagnostics)) = Parser::new("when month jan to jan").parse_schedule()
+        else {
+            panic!("single-point range parsed");
+        };
+        assert_eq!(
+            diagnostics[0].report.to_string(),
+            "malformed range: range starts and ends at the same value, which is written on its own"
+        );
+        assert!(matches!(
+            parse_range::<Month>("dec to dec"),
+            Err(Error::MalformedRange(reason)) if reason.contains("same value")
+        ));
+    }
+
+    #[test]
+    fn parses_timezones() {
+        let schedule = Parser::new("when hour 9, weekday mon to fri in America/Sao_Paulo")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(schedule.timezone, Some(Tz::America__Sao_Paulo));
+
+        let schedule = Parser::new("when hour 9 in UTC").parse_schedule().unwrap();
+        assert_eq!(schedule.timezone, Some(Tz::UTC));
+
+        assert_eq!(
+            parse_errors("when hour 9 in Mars/Olympus_Mons"),
+            [("invalid-value", "Mars/Olympus_Mons")]
+        );
+        assert_eq!(parse_errors("when hour 9 in"), [("invalid-syntax", "")]);
+        assert_eq!(
+            parse_errors("when hour 9 in UTC, minute 0"),
+            [("invalid-syntax", ",")]
+        );
+    }
+
+    #[test]
+    fn parses_times_of_day() {
+        let schedule = Parser::new("when at 14:30 or 2:05 pm or 12:15am or 12:00 PM")
+            .parse_schedule()
+            .unwrap();
+
+        assert_eq!(
+            schedule
+                .time_spec
+                .iter()
+                .map(|time| (time.hour.get(), time.minute.get()))
+                .collect::<Vec<_>>(),
+            [(14, 30), (14, 5), (0, 15), (12, 0)]
+        );
+        // Times of day aren't steps, so nothing else gets pinned
+        assert!(schedule.hour_spec.is_empty() && schedule.minute_spec.is_empty());
+
+        Parser::new("when every 2 days, at 9:00 in Europe/Berlin")
+            .parse_schedule()
+            .unwrap();
+
+        assert_eq!(parse_errors("when at 14"), [("invalid-syntax", "")]);
+        assert_eq!(parse_errors("when at noon"), [("invalid-syntax", "noon")]);
+        assert_eq!(parse_errors("when at 13:00 pm"), [("invalid-value", "13")]);
+        assert_eq!(parse_errors("when at 9:75"), [("out-of-range", "75")]);
+        assert_eq!(parse_errors("when hour 14:30"), [("invalid-syntax", ":")]);
+    }
+
+    #[test]
+    fn parses_days_from_month_end() {
+        let schedule = Parser::new("when day 1 or last or -3 to -2")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(schedule.day_spec.len(), 1);
+        assert_eq!(schedule.month_end_spec.len(), 2);
+
+        assert_eq!(parse_errors("when day -32"), [("invalid-value", "-32")]);
+        assert_eq!(parse_errors("when day -0"), [("invalid-value", "-0")]);
+        assert_eq!(parse_errors("when hour -3"), [("out-of-range", "-3")]);
+        assert_eq!(
+            parse_errors("when day -1 to -5"),
+            [("malformed-range", "-1 to -5")]
+        );
+        // Ranges can't start counting from one end of the month and stop at the other
+        assert_eq!(
+            parse_errors("when day 25 to last"),
+            [("invalid-value", "last")]
+        );
+        assert_eq!(parse_errors("when day -3 to 5"), [("invalid-value", "5")]);
+    }
+
+    #[test]
+    fn parses_nth_weekdays() {
+        let schedule = Parser::new("when weekday second tue or last fri or sat to sun")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(
+            schedule
+                .nth_weekday_spec
+                .iter()
+                .map(|nth_weekday| (nth_weekday.ordinal, nth_weekday.weekday))
+                .collect::<Vec<_>>(),
+            [
+                (Ordinal::Second, Weekday::Tue),
+                (Ordinal::Last, Weekday::Fri)
+            ]
+        );
+        assert_eq!(schedule.weekday_spec.len(), 1);
+
+        assert_eq!(
+            parse_errors("when weekday sixth tue"),
+            [("invalid-value", "sixth")]
+        );
+        assert_eq!(
+            parse_errors("when weekday second tuesday to fri"),
+            [("invalid-syntax", "to")]
+        );
+        assert_eq!(
+            parse_errors("when day second tue"),
+            [("invalid-syntax", "tue")]
+        );
+    }
+
+    #[test]
+    fn parses_day_combinations() {
+        let schedule = Parser::new("when day 1, weekday mon, combine or")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(schedule.day_combination, DayCombination::Or);
+
+        let schedule = Parser::new("when day 13, weekday fri, combine and")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(schedule.day_combination, DayCombination::And);
+
+        assert_eq!(
+            parse_errors("when day 1, weekday mon, combine xor"),
+            [("invalid-value", "xor")]
+        );
+    }
+
+    #[test]
+    fn parses_steps_in_clauses() {
+        let schedule = Parser::new("when minute every 15 from 5, month every 3 from feb")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(
+            schedule.minute_spec.as_slice(),
+            [DateTimePart::Step {
+                starting: Minute::new(5).unwrap(),
+                step: NonZeroU8::new(15).unwrap()
+            }]
+        );
+        assert_eq!(
+            schedule.month_spec.as_slice(),
+            [DateTimePart::Step {
+                starting: Month::February,
+                step: NonZeroU8::new(3).unwrap()
+            }]
+        );
+
+        // Unlike `every 2 hours`, this doesn't pin the minute
+        let schedule = Parser::new("when hour every 2").parse_schedule().unwrap();
+        assert!(schedule.minute_spec.is_empty());
+
+        assert_eq!(parse_errors("when hour every 0"), [("invalid-value", "0")]);
+        assert_eq!(
+            parse_errors("when weekday every 2 from someday"),
+            [("invalid-value", "someday")]
+        );
+        assert_eq!(
+            parse_errors("when every 15 from 5 minutes"),
+            [("invalid-syntax", "5")]
+        );
+    }
+
+    #[test]
+    fn parses_empty_schedules_and_dst_policies() {
+        assert_eq!(
+            Parser::new("when").parse_schedule().unwrap(),
+            Schedule::new()
+        );
+        assert_eq!(
+            Parser::new("when in UTC")
+                .parse_schedule()
+                .unwrap()
+                .timezone,
+            Some(Tz::UTC)
+        );
+        assert_eq!(parse_errors("when, hour 1"), [("invalid-syntax", ",")]);
+
+        let schedule = Parser::new("when hour 2, dst skip, dst twice")
+            .parse_schedule()
+            .unwrap();
+        assert_eq!(
+            schedule.dst_policy,
+            DstPolicy {
+                gap: GapPolicy::Skip,
+                overlap: OverlapPolicy::Twice
+            }
+        );
+
+        assert_eq!(
+            parse_errors("when hour 2, dst sometimes"),
+            [("invalid-value", "sometimes")]
+        );
+    }
+
+    fn display(input: &str) -> String {
+        Parser::new(input).parse_schedule().unwrap().to_string()
+    }
+
+    #[test]
+    fn displays_canonical_schedules() {
+        assert_eq!(display("when"), "when");
+        assert_eq!(
+            display("WHEN Hour 9 ,  month January to March or 12"),
+            "when month jan to mar or dec, hour 9"
+        );
+        assert_eq!(display("when every 2 hours"), "when hour every 2, minute 0");
+        assert_eq!(
+            display("when weekday last fri or sat to mon, day -3 to last or 1"),
+            "when day 1 or -3 to last, weekday sat to mon or last fri"
+        );
+        assert_eq!(
+            display("when at 2:05 pm, dst twice, combine or in America/New_York"),
+            "when at 14:05, combine or, dst twice in America/New_York"
+        );
+        assert_eq!(
+            display("when on 2026-12-24T09:05, month dec, year every 1 from 2026"),
+            "when year every 1 from 2026, month dec, on 2026-12-24 9:05"
+        );
+        assert_eq!(
+            display("when except (when hour 12) or (when weekday sat, except month dec) or day 1"),
+            "when except hour 12, except (when weekday sat, except month dec), except day 1"
+        );
+    }
+
+    #[test]
+    fn parses_expressions() {
+        let expr = |input| Parser::new(input).parse_expr().unwrap();
+        let schedule = |input| ScheduleExpr::Schedule(Parser::new(input).parse_schedule().unwrap());
+        let boxed = |input| Box::new(schedule(input));
+
+        // A single schedule needs no parentheses
+        assert_eq!(expr("when hour 9"), schedule("when hour 9"));
+        assert_eq!(expr("(when hour 9)"), schedule("when hour 9"));
+
+        // `and` binds tighter than `or`, and `but not` is short for `and not`
+        assert_eq!(
+            expr("(when hour 9) or (when weekday sat) AND (when minute 0)"),
+            ScheduleExpr::Or(
+                boxed("when hour 9"),
+                Box::new(ScheduleExpr::And(
+                    boxed("when weekday sat"),
+                    boxed("when minute 0")
+                ))
+            )
+        );
+        assert_eq!(
+            expr("((when hour 9) or (when hour 17, minute 30)) but not (when month dec)"),
+            ScheduleExpr::And(
+                Box::new(ScheduleExpr::Or(
+                    boxed("when hour 9"),
+                    boxed("when hour 17, minute 30")
+                )),
+                Box::new(ScheduleExpr::Not(boxed("when month dec")))
+            )
+        );
+        assert_eq!(
+            expr("not (when hour 9) and not not (when minute 0 in UTC)"),
+            ScheduleExpr::And(
+                Box::new(ScheduleExpr::Not(boxed("when hour 9"))),
+                Box::new(ScheduleExpr::Not(Box::new(ScheduleExpr::Not(boxed(
+                    "when minute 0 in UTC"
+                )))))
+            )
+        );
+
+        let expr_errors = |input| match Parser::new(input).parse_expr() {
+            Err(Error::Parse(diagnostics)) => diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.code(), &input[diagnostic.span.clone()]))
+                .collect::<Vec<_>>(),
+            other => panic!("expected parse errors, got {other:?}"),
+        };
+        assert_eq!(
+            expr_errors("(when hour 24) or (when minute 60, month 0)"),
+            [
+                ("out-of-range", "24"),
+                ("out-of-range", "60"),
+                ("out-of-range", "0")
+            ]
+        );
+        assert_eq!(
+            expr_errors("(when hour 9) but (when hour 10)"),
+            [("invalid-syntax", "(")]
+        );
+        assert_eq!(
+            expr_errors("(when hour 9) (when hour 10)"),
+            [("invalid-syntax", "(")]
+        );
+        assert_eq!(
+            expr_errors("(when hour 9) or when hour 10"),
+            [("invalid-syntax", "when")]
+        );
+        assert_eq!(expr_errors("(when hour 9"), [("invalid-syntax", "")]);
+        assert_eq!(expr_errors("hour 9"), [("missing-when", "hour 9")]);
+    }
+
+    #[test]
+    fn displays_expressions() {
+        for input in [
+            "when hour 9",
+            "(when hour 9) or (when weekday sat) and (when minute 0)",
+            "((when hour 9) or (when hour 17)) but not (when month dec)",
+            "(when hour 9) and ((when hour 10) and (when minute 0))",
+            "not ((when hour 9) or (when hour 10)) or not (when)",
+            "(when hour 9) but not not (when minute 0 in UTC)",
+        ] {
+            assert_eq!(Parser::new(input).parse_expr().unwrap().to_string(), input);
+        }
+    }
+
+    fn part<T: Debug + 'static>(
+        values: Range<u8>,
+        wraps: bool,
+        atom: fn(u8) -> T,
+    ) -> BoxedStrategy<DateTimePart<T>> {
+        let single = values
+            .clone()
+            .prop_map(move |value| DateTimePart::Single(atom(value)));
+        let range = (values.clone(), values.clone()).prop_filter_map(
+            "single-point range",
+            move |(starting, ending)| match starting.cmp(&ending) {
+                Ordering::Less => Some(DateTimePart::Range {
+                    starting: atom(starting),
+                    ending: atom(ending),
+                }),
+                Ordering::Greater if wraps => Some(DateTimePart::WrappingRange {
+                    starting: atom(starting),
+                    ending: atom(ending),
+                }),
+                _ => None,
+            },
+        );
+        let step = (values, 1..=u8::MAX).prop_map(move |(starting, step)| DateTimePart::Step {
+            starting: atom(starting),
+            step: NonZeroU8::new(step).unwrap(),
+        });
+
+        prop_oneof![single, range, step].boxed()
+    }
+
+    fn parts<T: Debug + 'static>(
+        values: Range<u8>,
+        wraps: bool,
+        atom: fn(u8) -> T,
+    ) -> impl Strategy<Value = Vec<DateTimePart<T>>> {
+        prop::collection::vec(part(values, wraps, atom), 0..3)
+    }
+
+    /// Weekdays counting from Sunday, as weekday ranges do
+    fn weekday(value: u8) -> Weekday {
+        Weekday::from_u8((value + 6) % 7).unwrap()
+    }
+
+    fn month_end_part() -> impl Strategy<Value = DateTimePart<MonthDayFromEnd>> {
+        let from_end = |days| MonthDayFromEnd::from_u8(days).unwrap();
+
+        prop_oneof![
+            (1..=31u8).prop_map(move |days| DateTimePart::Single(from_end(days))),
+            (2..=31u8)
+                .prop_flat_map(|starting| (Just(starting), 1..starting))
+                .prop_map(move |(starting, ending)| DateTimePart::Range {
+                    starting: from_end(starting),
+                    ending: from_end(ending),
+                }),
+        ]
+    }
+
+    fn schedules() -> impl Strategy<Value = Schedule> {
+        let ordinals = [
+            Ordinal::First,
+            Ordinal::Second,
+            Ordinal::Third,
+            Ordinal::Fourth,
+            Ordinal::Fifth,
+            Ordinal::Last,
+        ];
+        let timezones = ["UTC", "America/New_York", "Europe/Berlin", "Etc/GMT+5"];
+
+        let dates = (
+            parts(1..13, true, |month| Month::from_u8(month).unwrap()),
+            parts(1..32, false, |day| MonthDay::from_u8(day).unwrap()),
+            prop::collection::vec(month_end_part(), 0..3),
+            parts(0..7, true, weekday),
+            prop::collection::vec((prop::sample::select(ordinals.to_vec()), 0..7u8), 0..3),
+        );
+        let times = (
+            parts(0..24, true, |hour| Hour::new(hour).unwrap()),
+            parts(0..60, true, |minute| Minute::new(minute).unwrap()),
+            parts(0..60, true, |second| Second::new(second).unwrap()),
+            prop::collection::vec((0..24u8, 0..60u8), 0..3),
+        );
+        // Years and one-off dates around the sampled ones, which they often rule out
+        let once = (
+            prop_oneof![
+                3 => Just(Vec::new()),
+                1 => parts(0..8, false, |offset| Year::new(2022 + u16::from(offset)).unwrap()),
+            ],
+            prop_oneof![
+                3 => Just(Vec::new()),
+                1 => prop::collection::vec(
+                    (0..366 * 4i64, prop::option::of((0..24u8, 0..60u8))),
+                    1..3
+                ),
+            ],
+        );
+        // Exceptions each carving out a clause or two, e.g. `except hour 9`
+        let exception = prop_oneof![
+            prop::collection::vec(
+                part(1..13, true, |month| Month::from_u8(month).unwrap()),
+                1..3
+            )
+            .prop_map(|months| Schedule {
+                month_spec: months.into_iter().collect(),
+                ..Schedule::new()
+            }),
+            prop::collection::vec(part(0..7, true, weekday), 1..3).prop_map(|weekdays| {
+                Schedule {
+                    weekday_spec: weekdays.into_iter().collect(),
+                    ..Schedule::new()
+                }
+            }),
+            (
+                prop::collection::vec(part(0..24, true, |hour| Hour::new(hour).unwrap()), 1..3),
+                parts(0..60, true, |second| Second::new(second).unwrap()),
+            )
+                .prop_map(|(hours, seconds)| Schedule {
+                    hour_spec: hours.into_iter().collect(),
+                    second_spec: seconds.into_iter().collect(),
+                    ..Schedule::new()
+                }),
+        ];
+        let exceptions = prop_oneof![
+            3 => Just(Vec::new()),
+            1 => prop::collection::vec(exception, 1..3),
+        ];
+        let settings = (
+            any::<bool>(),
+            any::<bool>(),
+            any::<bool>(),
+            prop::option::of(prop::sample::select(timezones.to_vec())),
+        );
+
+        (dates, times, once, exceptions, settings).prop_map(
+            |(
+                (months, days, month_ends, weekdays, nth_weekdays),
+                (hours, minutes, seconds, times),
+                (years, on_dates),
+                exceptions,
+                (either_day, skip_gaps, repeat_overlaps, timezone),
+            )| Schedule {
+                year_spec: years.into_iter().collect(),
+                month_spec: months.into_iter().collect(),
+                day_spec: days.into_iter().collect(),
+                month_end_spec: month_ends.into_iter().collect(),
+                weekday_spec: weekdays.into_iter().collect(),
+                nth_weekday_spec: nth_weekdays
+                    .into_iter()
+                    .map(|(ordinal, day)| NthWeekday {
+                        ordinal,
+                        weekday: weekday(day),
+                    })
+                    .collect(),
+                hour_spec: hours.into_iter().collect(),
+                minute_spec: minutes.into_iter().collect(),
+                second_spec: seconds.into_iter().collect(),
+                time_spec: times
+                    .into_iter()
+                    .map(|(hour, minute)| TimeOfDay {
+                        hour: Hour::new(hour).unwrap(),
+                        minute: Minute::new(minute).unwrap(),
+                    })
+                    .collect(),
+                date_spec: on_dates
+                    .into_iter()
+                    .map(|(days, time)| AbsoluteDate {
+                        date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + Duration::days(days),
+                        time: time.map(|(hour, minute)| TimeOfDay {
+                            hour: Hour::new(hour).unwrap(),
+                            minute: Minute::new(minute).unwrap(),
+                        }),
+                    })
+                    .collect(),
+                exceptions,
+                day_combination: if either_day {
+                    DayCombination::Or
+                } else {
+                    DayCombination::And
+                },
+                timezone: timezone.map(|timezone| timezone.parse().unwrap()),
+                dst_policy: DstPolicy {
+                    gap: if skip_gaps {
+                        GapPolicy::Skip
+                    } else {
+                        GapPolicy::NextValid
+                    },
+                    overlap: if repeat_overlaps {
+                        OverlapPolicy::Twice
+                    } else {
+                        OverlapPolicy::Once
+                    },
+                },
+            },
+        )
+    }
+
+    fn exprs() -> impl Strategy<Value = ScheduleExpr> {
+        schedules()
+            .prop_map(ScheduleExpr::Schedule)
+            .prop_recursive(3, 8, 2, |expr| {
+                prop_oneof![
+                    (expr.clone(), expr.clone()).prop_map(|(left, right)| ScheduleExpr::And(
+                        Box::new(left),
+                        Box::new(right)
+                    )),
+                    (expr.clone(), expr.clone()).prop_map(|(left, right)| ScheduleExpr::Or(
+                        Box::new(left),
+                        Box::new(right)
+                    )),
+                    expr.prop_map(|expr| ScheduleExpr::Not(Box::new(expr))),
+                ]
+            })
+    }
+
+    /// Seconds into 2024 to 2027 to check schedules at, half of them on whole minutes
+    fn sample_seconds() -> impl Strategy<Value = Vec<i64>> {
+        let second = prop_oneof![Just(0), 0..60i64];
+
+        prop::collection::vec(
+            (0..60 * 24 * 366 * 4i64, second).prop_map(|(minutes, second)| minutes * 60 + second),
+            10,
+        )
+    }
+
+    /// Checks that `exported`, read back from `written`, fires whenever `schedule` does
+    fn fires_alike(
+        schedule: &Schedule,
+        written: &str,
+        exported: &Schedule,
+        seconds: &[i64],
+    ) -> Result<(), TestCaseError> {
+        for &seconds in seconds {
+            let date_time =
+                Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::seconds(seconds);
+
+            prop_assert_eq!(
+                exported.matches(date_time),
+                schedule.matches(date_time),
+                "{} at {}",
+                written,
+                date_time
+            );
+            prop_assert_eq!(
+                exported.next_after(date_time),
+                schedule.next_after(date_time),
+                "{} after {}",
+                written,
+                date_time
+            );
+        }
+
+        Ok(())
+    }
+
+    proptest! {
+        // Searching from every sampled second is slow, so fewer schedules are tried
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn normalizing_keeps_when_schedules_fire(mut schedule in schedules(), seconds in sample_seconds()) {
+            // Times of day mostly fall outside random hours and minutes, and
+            // schedules that never fire take the longest to search through
+            if !schedule.time_spec.is_empty() {
+                schedule.hour_spec.clear();
+                schedule.minute_spec.clear();
+            }
+
+            let mut normalized = schedule.clone();
+            normalized.normalize();
+
+            fires_alike(&schedule, &normalized.to_string(), &normalized, &seconds)?;
+
+            let mut again = normalized.clone();
+            again.normalize();
+            prop_assert_eq!(&again, &normalized);
+
+            let written = normalized.to_string();
+            prop_assert_eq!(Parser::new(&written).parse_schedule().unwrap(), normalized);
+        }
+
+        #[test]
+        fn rrule_export_round_trips(mut schedule in schedules(), seconds in sample_seconds()) {
+            schedule.timezone = None;
+            schedule.dst_policy = DstPolicy::default();
+            schedule.day_combination = DayCombination::And;
+            schedule.year_spec.clear();
+            schedule.date_spec.clear();
+            schedule.exceptions.clear();
+
+            if let Ok(rule) = schedule.to_rrule() {
+                fires_alike(&schedule, &rule, &Schedule::from_rrule(&rule).unwrap(), &seconds)?;
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn display_round_trips(schedule in schedules()) {
+            let displayed = schedule.to_string();
+
+            prop_assert_eq!(Parser::new(&displayed).parse_schedule().unwrap(), schedule);
+        }
+
+        #[test]
+        fn expr_display_round_trips(expr in exprs()) {
+            let displayed = expr.to_string();
+
+            prop_assert_eq!(Parser::new(&displayed).parse_expr().unwrap(), expr);
+        }
+
+        #[test]
+        fn only_satisfiable_schedules_fire(schedule in schedules(), seconds in sample_seconds()) {
+            let satisfiable = schedule.is_satisfiable();
+
+            for &seconds in &seconds {
+                let date_time =
+                    Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + Duration::seconds(seconds);
+
+                prop_assert!(satisfiable || !schedule.matches(date_time), "{} at {}", schedule, date_time);
+            }
+
+        }
+
+        #[test]
+        fn cron_export_round_trips(mut schedule in schedules(), seconds in sample_seconds()) {
+            // Leave out what cron is known not to have, so that most schedules get exported
+            schedule.timezone = None;
+            schedule.dst_policy = DstPolicy::default();
+            schedule.month_end_spec.clear();
+            schedule.nth_weekday_spec.clear();
+            schedule.year_spec.clear();
+            schedule.date_spec.clear();
+            schedule.exceptions.clear();
+
+            if let Ok(expression) = schedule.to_cron() {
+                fires_alike(&schedule, &expression, &cron::parse(&expression).unwrap(), &seconds)?;
+            }
+        }
+
+        #[test]
+        fn systemd_export_round_trips(mut schedule in schedules(), seconds in sample_seconds()) {
+            schedule.dst_policy = DstPolicy::default();
+            schedule.day_combination = DayCombination::And;
+            schedule.nth_weekday_spec.clear();
+            schedule.exceptions.clear();
+            if !schedule.day_spec.is_empty() {
+                schedule.month_end_spec.clear();
+            }
+
+            if let Ok(event) = schedule.to_systemd() {
+                fires_alike(&schedule, &event, &systemd::parse(&event).unwrap(), &seconds)?;
+            }
+        }
+
+    }
 }
@@ -4,6 +4,9 @@ use scheda_core::chrono::Month;
 use super::AtomParse;
 
 impl AtomParse for Month {
+    const EXPECTED: &'static str = "a month";
+    const BOUNDS: Option<(u8, u8)> = Some((1, 12));
+
     fn parse_atom(val: &str) -> Option<Self> {
         let trimmed = val.trim();
 
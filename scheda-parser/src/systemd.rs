@@ -0,0 +1,534 @@
+//! Imports systemd calendar events, as used by `OnCalendar=`, into [`Schedule`]s.
+//!
+//! Calendar events look like `Mon..Fri *-*-* 09:00:00 Europe/Berlin`: weekdays,
+//! a `year-month-day` date, an `hour:minute:second` time and a time zone, all
+//! of them optional. Components may be lists, `..` ranges and `/` repetitions,
+//! days may count back from the end of the month after a `~`, as in `*-02~03`,
+//! and shorthands such as `hourly` or `weekly` are understood.
+
+use std::num::NonZeroU8;
+
+use scheda_core::{
+    chrono::{Month, Weekday},
+    chrono_tz::Tz,
+    DateTimePart, Diagnostic, Error, Hour, Minute, MonthDay, MonthDayFromEnd, Result, Schedule,
+    WellFormedRange, Year,
+};
+
+use crate::{
+    cron::{lower, parse_field, parse_seconds, parse_value, CronValue, Part, Piece},
+    malformed_range, Spanned,
+};
+
+/// What shorthands such as `daily` stand for
+fn expand_shorthand(expression: &str) -> Option<&'static str> {
+    let expansion = match expression.to_ascii_lowercase().as_str() {
+        "minutely" => "*-*-* *:*:00",
+        "hourly" => "*-*-* *:00:00",
+        "daily" => "*-*-* 00:00:00",
+        "weekly" => "Mon *-*-* 00:00:00",
+        "monthly" => "*-*-01 00:00:00",
+        "quarterly" => "*-01,04,07,10-01 00:00:00",
+        "semiannually" => "*-01,07-01 00:00:00",
+        "yearly" | "annually" => "*-01-01 00:00:00",
+        _ => return None,
+    };
+
+    Some(expansion)
+}
+
+fn parse_weekday(piece: Piece) -> Spanned<u8> {
+    let weekday: Weekday = piece.text.parse().map_err(|_| {
+        Error::InvalidValue {
+            expected: "a weekday, such as `Mon` or `Monday`".into(),
+            found: piece.text.into(),
+        }
+        .at(piece.span())
+    })?;
+
+    // systemd weeks start on Monday, so `Sat..Sun` is a range like `6-7` in cron
+    Ok(weekday.number_from_monday() as u8)
+}
+
+/// Parses weekdays such as `Mon..Fri,Sun`, which may be followed by a stray comma
+fn parse_weekdays(schedule: &mut Schedule, piece: Piece) -> Spanned<()> {
+    let piece = piece.sub(piece.text.trim_end_matches(','));
+
+    for item in piece.split(',') {
+        let part = match item.split_once("..") {
+            Some((starting, ending)) => {
+                Part::Range(parse_weekday(starting)?, parse_weekday(ending)?)
+            }
+            None => Part::Single(parse_weekday(item)?),
+        };
+
+        let parts = lower::<Weekday>(part, item.span())?;
+        if parts.is_empty() {
+            schedule.weekday_spec.clear();
+            return Ok(());
+        }
+
+        schedule.weekday_spec.extend(parts);
+    }
+
+    Ok(())
+}
+
+/// Parses the days after a `~`, which count back from the end of the month.
+///
+/// `~07/2` steps from the seventh last day towards the last one, so, like
+/// bounded steps in cron, it's spelled out value by value.
+fn parse_days_from_end(piece: Piece) -> Spanned<Vec<DateTimePart<MonthDayFromEnd>>> {
+    let mut parts = Vec::new();
+
+    for item in piece.split(',') {
+        let (base, step) = match item.split_once("/") {
+            Some((base, step)) => {
+                let parsed = step.text.parse().ok().and_then(NonZeroU8::new);
+                let step = parsed.ok_or_else(|| {
+                    Error::InvalidValue {
+                        expected: "a step, such as `2`".into(),
+                        found: step.text.into(),
+                    }
+                    .at(step.span())
+                })?;
+
+                (base, Some(step))
+            }
+            None => (item, None),
+        };
+
+        let (starting, ending) = match base.split_once("..") {
+            Some((starting, ending)) => (
+                parse_value(&MonthDay::FIELD, starting)?,
+                parse_value(&MonthDay::FIELD, ending)?,
+            ),
+            None => {
+                let starting = parse_value(&MonthDay::FIELD, base)?;
+
+                // Repetitions carry on until the last day
+                (starting, if step.is_some() { 1 } else { starting })
+            }
+        };
+
+        if ending > starting {
+            return Err(malformed_range().at(item.span()));
+        }
+
+        let from_end = |days| MonthDayFromEnd::from_u8(days).unwrap();
+
+        match step {
+            _ if starting == ending => parts.push(DateTimePart::Single(from_end(starting))),
+            Some(step) if step.get() > 1 => parts.extend(
+                (ending..=starting)
+                    .rev()
+                    .step_by(step.get().into())
+                    .map(|days| DateTimePart::Single(from_end(days))),
+            ),
+            _ => parts.push(DateTimePart::Range {
+                starting: from_end(starting),
+                ending: from_end(ending),
+            }),
+        }
+    }
+
+    Ok(parts)
+}
+
+/// Parses a single year, where two digits stand for one from 1970 to 2069, as in systemd
+fn parse_year(piece: Piece) -> Spanned<Year> {
+    let parsed = piece
+        .text
+        .parse::<u16>()
+        .ok()
+        .map(|year| match piece.text.len() {
+            2 if year < 70 => year + 2000,
+            2 => year + 1900,
+            _ => year,
+        });
+
+    parsed.and_then(Year::new).ok_or_else(|| {
+        Error::InvalidValue {
+            expected: "a year, such as `2027`".into(),
+            found: piece.text.into(),
+        }
+        .at(piece.span())
+    })
+}
+
+/// Parses years such as `2027..2028,2030` or `2026/2`.
+///
+/// Repetitions within a range stop at its end, so, like bounded steps in
+/// cron, they're spelled out value by value.
+fn parse_years(piece: Piece) -> Spanned<Vec<DateTimePart<Year>>> {
+    let mut parts = Vec::new();
+
+    for item in piece.split(',') {
+        if item.text == "*" {
+            return Ok(Vec::new());
+        }
+
+        let (base, step) = match item.split_once("/") {
+            Some((base, step)) => {
+                let parsed = step.text.parse().ok().and_then(NonZeroU8::new);
+                let step = parsed.ok_or_else(|| {
+                    Error::InvalidValue {
+                        expected: "a step, such as `2`".into(),
+                        found: step.text.into(),
+                    }
+                    .at(step.span())
+                })?;
+
+                (base, Some(step))
+            }
+            None => (item, None),
+        };
+
+        let Some((starting, ending)) = base.split_once("..") else {
+            let starting = parse_year(base)?;
+
+            parts.push(match step {
+                Some(step) => DateTimePart::Step { starting, step },
+                None => DateTimePart::Single(starting),
+            });
+            continue;
+        };
+
+        let (starting, ending) = (parse_year(starting)?, parse_year(ending)?);
+        if ending < starting {
+            return Err(malformed_range().at(item.span()));
+        }
+
+        match step {
+            Some(step) if step.get() > 1 => parts.extend(
+                (starting.get()..=ending.get())
+                    .step_by(step.get().into())
+                    .filter_map(Year::new)
+                    .map(DateTimePart::Single),
+            ),
+            _ => parts.push(DateTimePart::Range { starting, ending }),
+        }
+    }
+
+    Ok(parts)
+}
+
+/// Parses a date, such as `*-*-01`, `2027-10-15` or `*-02~03`
+fn parse_date(schedule: &mut Schedule, piece: Piece) -> Spanned<()> {
+    let (rest, days_from_end) = match piece.split_once("~") {
+        Some((rest, days)) => (rest, Some(days)),
+        None => (piece, None),
+    };
+
+    let mut components: Vec<_> = rest.split('-').collect();
+    let days = match days_from_end {
+        Some(_) => None,
+        None => components.pop(),
+    };
+
+    let (year, month) = match components.as_slice() {
+        [month] => (None, *month),
+        [year, month] => (Some(*year), *month),
+        _ => {
+            return Err(
+                Error::InvalidSyntax("expected a date, such as `*-*-01` or `10-15`".into())
+                    .at(piece.span()),
+            )
+        }
+    };
+
+    if let Some(year) = year {
+        schedule.year_spec.extend(parse_years(year)?);
+    }
+
+    schedule
+        .month_spec
+        .extend(parse_field::<Month>(month, "..")?);
+
+    match (days, days_from_end) {
+        (Some(days), _) => schedule
+            .day_spec
+            .extend(parse_field::<MonthDay>(days, "..")?),
+        (_, Some(days)) => schedule.month_end_spec.extend(parse_days_from_end(days)?),
+        (None, None) => unreachable!("dates without days are rejected above"),
+    }
+
+    Ok(())
+}
+
+/// Parses a time, such as `09:00`, `*:0/15` or `12..14:30:00`
+fn parse_time(schedule: &mut Schedule, piece: Piece) -> Spanned<()> {
+    let components: Vec<_> = piece.split(':').collect();
+
+    let (hours, minutes, seconds) = match components.as_slice() {
+        [hours, minutes] => (*hours, *minutes, None),
+        [hours, minutes, seconds] => (*hours, *minutes, Some(*seconds)),
+        _ => {
+            return Err(Error::InvalidSyntax(
+                "expected a time, such as `09:00` or `09:00:00`".into(),
+            )
+            .at(piece.span()))
+        }
+    };
+
+    // `..` separates the ends of ranges, so any other `.` starts a fraction
+    if let Some(seconds) = seconds.filter(|seconds| seconds.text.replace("..", "").contains('.')) {
+        return Err(Error::Unsupported(
+            "schedules only go down to the second, so seconds can't have fractions".into(),
+        )
+        .at(seconds.span()));
+    }
+
+    schedule.hour_spec.extend(parse_field::<Hour>(hours, "..")?);
+    schedule
+        .minute_spec
+        .extend(parse_field::<Minute>(minutes, "..")?);
+    if let Some(seconds) = seconds {
+        schedule.second_spec.extend(parse_seconds(seconds, "..")?);
+    }
+
+    Ok(())
+}
+
+/// Parses a systemd calendar event, or one of its shorthands, into a [`Schedule`].
+///
+/// Like [`Parser::parse_schedule`](crate::Parser::parse_schedule), every part
+/// is checked, and the returned [`Error::Parse`] lists every error found.
+/// Fractions of a second are reported as [`Error::Unsupported`].
+///
+/// As in systemd, a missing date matches every day and a missing time is
+/// midnight, and weekdays and days of the month both have to match.
+pub fn parse(expression: &str) -> Result<Schedule> {
+    if let Some(expansion) = expand_shorthand(expression.trim()) {
+        return parse(expansion);
+    }
+
+    let words = Piece::words(expression);
+    if words.is_empty() {
+        return Err(Error::Parse(vec![Error::InvalidSyntax(
+            "expected a calendar event, such as `Mon *-*-* 09:00`".into(),
+        )
+        .at(0..expression.len())]));
+    }
+
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let (mut weekdays, mut date, mut time, mut timezone) = (None, None, None, None);
+
+    for (idx, word) in words.iter().enumerate() {
+        let slot = if word.text.contains(':') {
+            &mut time
+        } else if word
+            .text
+            .starts_with(|c: char| c.is_ascii_digit() || c == '*')
+        {
+            &mut date
+        } else if idx == 0 && word.text.parse::<Tz>().is_err() {
+            &mut weekdays
+        } else {
+            &mut timezone
+        };
+
+        if slot.replace(*word).is_some() {
+            diagnostics.push(
+                Error::InvalidSyntax(format!("unexpected `{}`", word.text).into()).at(word.span()),
+            );
+        }
+    }
+
+    let mut schedule = Schedule::new();
+
+    if let Some(weekdays) = weekdays {
+        if let Err(diagnostic) = parse_weekdays(&mut schedule, weekdays) {
+            diagnostics.push(diagnostic);
+        }
+    }
+    if let Some(date) = date {
+        if let Err(diagnostic) = parse_date(&mut schedule, date) {
+            diagnostics.push(diagnostic);
+        }
+    }
+    match time {
+        Some(time) => {
+            if let Err(diagnostic) = parse_time(&mut schedule, time) {
+                diagnostics.push(diagnostic);
+            }
+        }
+        None => {
+            schedule.hour_spec.push(DateTimePart::Single(Hour::FIRST));
+            schedule
+                .minute_spec
+                .push(DateTimePart::Single(Minute::FIRST));
+        }
+    }
+    if let Some(timezone) = timezone {
+        match timezone.text.parse() {
+            Ok(timezone) => schedule.timezone = Some(timezone),
+            Err(_) => diagnostics.push(
+                Error::InvalidValue {
+                    expected: "an IANA time zone, such as `Europe/Berlin`".into(),
+                    found: timezone.text.into(),
+                }
+                .at(timezone.span()),
+            ),
+        }
+    }
+
+    if !diagnostics.is_empty() {
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        return Err(Error::Parse(diagnostics));
+    }
+
+    Ok(schedule)
+}
+
+#[cfg(test)]
+mod tests {
+    use scheda_core::{Error, Schedule};
+
+    use super::parse;
+    use crate::Parser;
+
+    fn when(schedule: &str) -> Schedule {
+        Parser::new(schedule).parse_schedule().unwrap()
+    }
+
+    fn parse_errors(expression: &str) -> Vec<(&'static str, &str)> {
+        match parse(expression) {
+            Err(Error::Parse(diagnostics)) => diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.code(), &expression[diagnostic.span.clone()]))
+                .collect(),
+            other => panic!("expected parse errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_calendar_events() {
+        // Examples from systemd.time(7)
+        for (expression, schedule) in [
+            (
+                "Sat,Thu,Mon..Wed,Sat..Sun",
+                "when weekday sat or thu or mon to wed or sat to sun, hour 0, minute 0",
+            ),
+            ("Wed *-1", "when weekday wed, day 1, hour 0, minute 0"),
+            (
+                "Wed..Wed,Wed *-1",
+                "when weekday wed or wed, day 1, hour 0, minute 0",
+            ),
+            ("Wed, 17:48", "when weekday wed, hour 17, minute 48"),
+            ("*-*-7 0:0:0", "when day 7, hour 0, minute 0"),
+            ("10-15", "when month oct, day 15, hour 0, minute 0"),
+            (
+                "monday *-12-* 17:00",
+                "when weekday mon, month dec, hour 17, minute 0",
+            ),
+            (
+                "12,14,13,12:20,10,30",
+                "when hour 12 or 14 or 13 or 12, minute 20 or 10 or 30",
+            ),
+            (
+                "12..14:10,20,30",
+                "when hour 12 to 14, minute 10 or 20 or 30",
+            ),
+            ("03-05", "when month mar, day 5, hour 0, minute 0"),
+            ("05:40", "when hour 5, minute 40"),
+            (
+                "Sat,Sun 08:05:00",
+                "when weekday sat or sun, hour 8, minute 5",
+            ),
+            ("*:2/3", "when minute every 3 from 2"),
+            (
+                "Mon,Fri *-*-3,1,2 *:30:45",
+                "when weekday mon or fri, day 3 or 1 or 2, minute 30, second 45",
+            ),
+            ("*:*:0/10", "when second every 10"),
+            (
+                "Mon *-05~07/1",
+                "when weekday mon, month may, day -7 to last, hour 0, minute 0",
+            ),
+            ("*-02~03", "when month feb, day -3, hour 0, minute 0"),
+            (
+                "*-*-* 09:00 Europe/Berlin",
+                "when hour 9, minute 0 in Europe/Berlin",
+            ),
+            (
+                "Mon,Sun 12-*-* 2,1:23",
+                "when year 2012, weekday mon or sun, hour 2 or 1, minute 23",
+            ),
+            (
+                "2003-03-05 05:40 UTC",
+                "when year 2003, month mar, day 5, hour 5, minute 40 in UTC",
+            ),
+            (
+                "2027..2028,2030-01-01",
+                "when year 2027 to 2028 or 2030, month jan, day 1, hour 0, minute 0",
+            ),
+            (
+                "2026/2-*-* 00:00",
+                "when year every 2 from 2026, hour 0, minute 0",
+            ),
+        ] {
+            assert_eq!(parse(expression).unwrap(), when(schedule), "{expression}");
+        }
+
+        // Repetitions counting back from the end of the month are spelled out
+        assert_eq!(
+            parse("*-*~05/2").unwrap(),
+            when("when day -5 or -3 or last, hour 0, minute 0")
+        );
+    }
+
+    #[test]
+    fn parses_shorthands() {
+        for (shorthand, schedule) in [
+            ("minutely", "when"),
+            ("hourly", "when minute 0"),
+            ("daily", "when hour 0, minute 0"),
+            ("weekly", "when weekday mon, hour 0, minute 0"),
+            ("monthly", "when day 1, hour 0, minute 0"),
+            (
+                "quarterly",
+                "when month jan or apr or jul or oct, day 1, hour 0, minute 0",
+            ),
+            (
+                "semiannually",
+                "when month jan or jul, day 1, hour 0, minute 0",
+            ),
+            ("Yearly", "when month jan, day 1, hour 0, minute 0"),
+            ("annually", "when month jan, day 1, hour 0, minute 0"),
+        ] {
+            assert_eq!(parse(shorthand).unwrap(), when(schedule), "{shorthand}");
+        }
+    }
+
+    #[test]
+    fn reports_unsupported_and_invalid_events() {
+        // An example from systemd.time(7) with fractions of a second
+        assert_eq!(
+            parse_errors("05:40:23.4200004/3.1700005"),
+            [("unsupported", "23.4200004/3.1700005")]
+        );
+
+        assert_eq!(
+            parse_errors("Funday *-13-01 25:00 Mars/Olympus_Mons"),
+            [
+                ("invalid-value", "Funday"),
+                ("invalid-value", "13"),
+                ("invalid-value", "25"),
+                ("invalid-value", "Mars/Olympus_Mons")
+            ]
+        );
+        assert_eq!(
+            parse_errors("*-*-01 *-*-02"),
+            [("invalid-syntax", "*-*-02")]
+        );
+        assert_eq!(parse_errors("*-*~01..05"), [("malformed-range", "01..05")]);
+        assert_eq!(parse_errors("Sun..Mon"), [("malformed-range", "Sun..Mon")]);
+        assert_eq!(
+            parse_errors("2028..2027-01-01"),
+            [("malformed-range", "2028..2027")]
+        );
+        assert_eq!(parse_errors("10000-01-01"), [("invalid-value", "10000")]);
+        assert_eq!(parse_errors(""), [("invalid-syntax", "")]);
+    }
+}
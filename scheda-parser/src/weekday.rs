@@ -1,8 +1,10 @@
-use scheda_core::chrono::Weekday;
+use scheda_core::{chrono::Weekday, Ordinal};
 
 use super::AtomParse;
 
 impl AtomParse for Weekday {
+    const EXPECTED: &'static str = "a weekday";
+
     fn parse_atom(val: &str) -> Option<Self> {
         let trimmed = val.trim();
 
@@ -12,6 +14,22 @@ impl AtomParse for Weekday {
     }
 }
 
+impl AtomParse for Ordinal {
+    const EXPECTED: &'static str = "an ordinal, such as `second` or `last`";
+
+    fn parse_atom(val: &str) -> Option<Self> {
+        match val {
+            x if x.eq_ignore_ascii_case("first") => Some(Ordinal::First),
+            x if x.eq_ignore_ascii_case("second") => Some(Ordinal::Second),
+            x if x.eq_ignore_ascii_case("third") => Some(Ordinal::Third),
+            x if x.eq_ignore_ascii_case("fourth") => Some(Ordinal::Fourth),
+            x if x.eq_ignore_ascii_case("fifth") => Some(Ordinal::Fifth),
+            x if x.eq_ignore_ascii_case("last") => Some(Ordinal::Last),
+            _ => None,
+        }
+    }
+}
+
 pub fn parse_weekday_idx(val: &str) -> Option<Weekday> {
     let idx: u32 = val.parse().ok()?;
 